@@ -0,0 +1,154 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::option::{OverwriteMode, Toggle};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    #[error("failed to read configuration: {0}")]
+    Io(io::Error),
+    #[error("failed to parse configuration: {0}")]
+    Format(toml::de::Error),
+    #[error("failed to parse theme: unknown key `{0}`")]
+    ThemeKey(String),
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        ConfigError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::Format(error)
+    }
+}
+
+/// User configuration.
+///
+/// Configuration provides defaults for options. Options given on the command
+/// line take precedence over configuration, except for exclusions, which are
+/// combined.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub pager: Option<String>,
+    pub color: Option<Toggle>,
+    pub depth: Option<usize>,
+    pub overwrite: Option<bool>,
+    pub overwrite_mode: Option<OverwriteMode>,
+    pub confirm_threshold: Option<usize>,
+    pub exclude: Vec<String>,
+    pub digest_cache: Option<PathBuf>,
+    pub theme: ThemeConfig,
+}
+
+impl Config {
+    /// Reads configuration from the given path or the default path.
+    ///
+    /// If no path is given and there is no file at the default path, then the
+    /// default configuration is used.
+    pub fn read(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Config::read_from(path),
+            None => match default_path() {
+                Some(path) if path.is_file() => Config::read_from(&path),
+                _ => Ok(Config::default()),
+            },
+        }
+    }
+
+    fn read_from(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Gets the theme of the configuration combined with the `NYM_THEME`
+    /// environment variable, which takes precedence.
+    pub fn theme(&self) -> Result<ThemeConfig, ConfigError> {
+        let theme = match env::var("NYM_THEME") {
+            Ok(text) => text.parse()?,
+            Err(_) => ThemeConfig::default(),
+        };
+        Ok(theme.or(&self.theme))
+    }
+}
+
+/// Theme configuration.
+///
+/// Styles are dot-separated colors and attributes, such as `bold.red` (see
+/// `console::Style::from_dotted_str`). When `ascii` is `true`, manifests are
+/// drawn with ASCII characters rather than box-drawing characters.
+///
+/// Themes can also be given by the `NYM_THEME` environment variable as
+/// colon-separated keys and values, such as `source=cyan:change=bold.magenta`.
+/// In this format, `ascii` is a key without a value.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    pub index: Option<String>,
+    pub line: Option<String>,
+    pub source: Option<String>,
+    pub destination: Option<String>,
+    pub change: Option<String>,
+    pub warning: Option<String>,
+    pub ascii: Option<bool>,
+}
+
+impl ThemeConfig {
+    fn or(self, theme: &ThemeConfig) -> Self {
+        ThemeConfig {
+            index: self.index.or_else(|| theme.index.clone()),
+            line: self.line.or_else(|| theme.line.clone()),
+            source: self.source.or_else(|| theme.source.clone()),
+            destination: self.destination.or_else(|| theme.destination.clone()),
+            change: self.change.or_else(|| theme.change.clone()),
+            warning: self.warning.or_else(|| theme.warning.clone()),
+            ascii: self.ascii.or(theme.ascii),
+        }
+    }
+}
+
+impl FromStr for ThemeConfig {
+    type Err = ConfigError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut theme = ThemeConfig::default();
+        for entry in text.split(':').filter(|entry| !entry.is_empty()) {
+            let (key, value) = match entry.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_owned())),
+                None => (entry, None),
+            };
+            match key {
+                "index" => theme.index = value,
+                "line" => theme.line = value,
+                "source" => theme.source = value,
+                "destination" => theme.destination = value,
+                "change" => theme.change = value,
+                "warning" => theme.warning = value,
+                "ascii" => theme.ascii = Some(value.as_deref() != Some("0")),
+                _ => {
+                    return Err(ConfigError::ThemeKey(key.to_owned()));
+                }
+            }
+        }
+        Ok(theme)
+    }
+}
+
+// Configuration is read from `$XDG_CONFIG_HOME/nym/config.toml`, falling back
+// to `~/.config` if `XDG_CONFIG_HOME` is unset or relative.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|path| path.join("nym").join("config.toml"))
+}
@@ -0,0 +1,242 @@
+use console::Term;
+use std::env;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config;
+use crate::option::ChildCommand;
+use crate::terminal;
+
+/// Prints a report of the detected environment.
+///
+/// The report includes the availability of pagers, color and terminal
+/// support, the capabilities of the filesystem of the working directory tree,
+/// and the locations of configuration. Capabilities are probed by writing
+/// files to a temporary directory in the tree, which is removed afterwards.
+pub fn print(
+    output: &mut impl Write,
+    pagers: &[ChildCommand],
+    directory: &Path,
+    config: Option<&Path>,
+) -> io::Result<()> {
+    writeln!(
+        output,
+        "{} {} ({} {})",
+        env!("CARGO_BIN_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH,
+    )?;
+
+    writeln!(output, "\nPager")?;
+    for pager in pagers {
+        let binary = pager.binary();
+        match find_binary(binary) {
+            Some(path) => writeln!(output, "  {}: {}", binary.to_string_lossy(), path.display()),
+            None => writeln!(output, "  {}: not found", binary.to_string_lossy()),
+        }?;
+    }
+
+    writeln!(output, "\nTerminal")?;
+    for (name, term, is_color_enabled) in [
+        ("standard output", Term::stdout(), console::colors_enabled()),
+        (
+            "standard error",
+            Term::stderr(),
+            console::colors_enabled_stderr(),
+        ),
+    ] {
+        writeln!(
+            output,
+            "  {}: {}, color {}",
+            name,
+            if term.features().is_attended() {
+                "attended"
+            }
+            else {
+                "unattended"
+            },
+            enabled(is_color_enabled),
+        )?;
+    }
+    writeln!(
+        output,
+        "  escape sequences: {}",
+        supported(Some(terminal::enable_virtual_terminal_output())),
+    )?;
+    match Term::stdout().size_checked() {
+        Some((height, width)) => writeln!(output, "  size: {}x{}", width, height),
+        None => writeln!(output, "  size: unknown"),
+    }?;
+    for name in &[
+        "TERM",
+        "NO_COLOR",
+        "CLICOLOR",
+        "CLICOLOR_FORCE",
+        "NYM_PAGER",
+        "PAGER",
+    ] {
+        writeln!(output, "  {}: {}", name, variable(name))?;
+    }
+
+    writeln!(output, "\nWorking tree ({})", directory.display())?;
+    match Capabilities::probe(directory) {
+        Ok(capabilities) => {
+            writeln!(
+                output,
+                "  case sensitive: {}",
+                if capabilities.is_case_sensitive {
+                    "yes"
+                }
+                else {
+                    "no"
+                },
+            )?;
+            writeln!(
+                output,
+                "  hard links: {}",
+                supported(Some(capabilities.hard_link))
+            )?;
+            writeln!(
+                output,
+                "  symbolic links: {}",
+                supported(Some(capabilities.soft_link))
+            )?;
+            writeln!(output, "  reflinks: {}", supported(capabilities.reflink))?;
+        }
+        Err(error) => {
+            writeln!(output, "  failed to probe: {}", error)?;
+        }
+    }
+
+    writeln!(output, "\nConfiguration")?;
+    match config.map(Path::to_path_buf).or_else(config::default_path) {
+        Some(path) => writeln!(
+            output,
+            "  file: {} ({})",
+            path.display(),
+            if path.is_file() { "found" } else { "not found" },
+        ),
+        None => writeln!(output, "  file: unknown"),
+    }?;
+    writeln!(output, "  NYM_THEME: {}", variable("NYM_THEME"))?;
+    Ok(())
+}
+
+struct Capabilities {
+    is_case_sensitive: bool,
+    hard_link: bool,
+    soft_link: bool,
+    reflink: Option<bool>,
+}
+
+impl Capabilities {
+    fn probe(directory: &Path) -> io::Result<Self> {
+        let probe = directory.join(format!(".nym-doctor-{}", process::id()));
+        fs::create_dir(&probe)?;
+        let capabilities = Capabilities::probe_in(&probe);
+        fs::remove_dir_all(&probe)?;
+        capabilities
+    }
+
+    fn probe_in(probe: &Path) -> io::Result<Self> {
+        let source = probe.join("source");
+        File::create(&source)?.write_all(b"nym")?;
+        Ok(Capabilities {
+            is_case_sensitive: !probe.join("SOURCE").exists(),
+            hard_link: fs::hard_link(&source, probe.join("hard")).is_ok(),
+            soft_link: soft_link(&source, &probe.join("soft")).is_ok(),
+            reflink: reflink(&source, &probe.join("reflink")),
+        })
+    }
+}
+
+fn enabled(is_enabled: bool) -> &'static str {
+    if is_enabled {
+        "enabled"
+    }
+    else {
+        "disabled"
+    }
+}
+
+fn supported(is_supported: Option<bool>) -> &'static str {
+    match is_supported {
+        Some(true) => "supported",
+        Some(false) => "unsupported",
+        None => "unknown",
+    }
+}
+
+fn variable(name: &str) -> String {
+    env::var_os(name)
+        .map(|value| format!("{:?}", value))
+        .unwrap_or_else(|| "unset".to_owned())
+}
+
+fn find_binary(binary: &OsStr) -> Option<PathBuf> {
+    let is_file = |path: &Path| {
+        path.is_file()
+            || (!env::consts::EXE_EXTENSION.is_empty()
+                && path.with_extension(env::consts::EXE_EXTENSION).is_file())
+    };
+    let path = Path::new(binary);
+    if path.components().count() > 1 {
+        is_file(path).then(|| path.to_path_buf())
+    }
+    else {
+        env::var_os("PATH").and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|directory| directory.join(binary))
+                .find(|path| is_file(path))
+        })
+    }
+}
+
+#[cfg(unix)]
+fn soft_link(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+// NOTE: Creating symbolic links on Windows requires a privilege that is
+//       typically only granted to administrators or in developer mode.
+#[cfg(windows)]
+fn soft_link(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, destination)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn soft_link(_: &Path, _: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, destination: &Path) -> Option<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = File::open(source).ok()?;
+    let destination = File::create(destination).ok()?;
+    // SAFETY: Both file descriptors are open for the duration of the call.
+    let result = unsafe { libc::ioctl(destination.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) };
+    Some(result == 0)
+}
+
+#[cfg(target_os = "macos")]
+fn reflink(source: &Path, destination: &Path) -> Option<bool> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let source = CString::new(source.as_os_str().as_bytes()).ok()?;
+    let destination = CString::new(destination.as_os_str().as_bytes()).ok()?;
+    // SAFETY: Both paths are valid null-terminated strings.
+    let result = unsafe { libc::clonefile(source.as_ptr(), destination.as_ptr(), 0) };
+    Some(result == 0)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink(_: &Path, _: &Path) -> Option<bool> {
+    None
+}
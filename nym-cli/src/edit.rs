@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use tempfile::Builder;
+use thiserror::Error;
+
+use nym::manifest::{Manifest, ManifestError, Routing};
+
+const HEADER: &str = "\
+# Edit the destinations of the routes below. Each line is a source path and a
+# destination path separated by a tab. Delete a line to remove its route. Lines
+# that begin with `#` and empty lines are ignored.
+";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EditError {
+    #[error("failed to edit manifest: {0}")]
+    Io(io::Error),
+    #[error("editor exited unsuccessfully: {0}")]
+    Editor(ExitStatus),
+    #[error("failed to parse line {0} of manifest: expected a source and destination")]
+    Parse(usize),
+    #[error("path cannot be edited, because it is not UTF-8 or contains a tab or newline: `{0}`")]
+    UnsupportedPath(PathBuf),
+    #[error("source path not in manifest: `{0}`")]
+    UnknownSource(PathBuf),
+    #[error("failed to insert route: {0}")]
+    RouteInsertion(ManifestError),
+}
+
+impl From<io::Error> for EditError {
+    fn from(error: io::Error) -> Self {
+        EditError::Io(error)
+    }
+}
+
+/// Edits a manifest in the user's editor.
+///
+/// The routes of the manifest are written to a temporary file that is opened
+/// in `$VISUAL` or `$EDITOR` (falling back to `vi`). Once the editor exits,
+/// the file is parsed into a new manifest. Routes may be removed and
+/// destinations may be changed, but sources that are not in the given
+/// manifest are rejected.
+pub fn edit<M>(manifest: &Manifest<M>) -> Result<Manifest<M>, EditError>
+where
+    M: Routing,
+{
+    // The temporary file is created with a unique name and is removed when
+    // dropped. Editors may replace the file rather than write to it, so it is
+    // read again by its path.
+    let mut file = Builder::new()
+        .prefix("nym-")
+        .suffix(".manifest")
+        .tempfile()?;
+    file.write_all(to_text(manifest)?.as_bytes())?;
+    file.flush()?;
+    open_editor(file.path())?;
+    let text = fs::read_to_string(file.path())?;
+    from_text(manifest, &text)
+}
+
+fn to_text<M>(manifest: &Manifest<M>) -> Result<String, EditError>
+where
+    M: Routing,
+{
+    // Tabs and newlines delimit the text, so paths that contain them are
+    // rejected rather than corrupting the manifest.
+    fn text(path: &Path) -> Result<&str, EditError> {
+        path.to_str()
+            .filter(|text| !text.contains(&['\t', '\n', '\r'][..]))
+            .ok_or_else(|| EditError::UnsupportedPath(path.into()))
+    }
+
+    let mut output = String::from(HEADER);
+    for route in manifest.routes() {
+        let destination = text(route.destination())?;
+        for source in route.sources() {
+            output.push_str(text(source)?);
+            output.push('\t');
+            output.push_str(destination);
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
+
+fn open_editor(path: &Path) -> Result<(), EditError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_default();
+    let mut components = editor.split_whitespace();
+    let status = Command::new(components.next().unwrap_or("vi"))
+        .args(components)
+        .arg(path)
+        .status()?;
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(EditError::Editor(status))
+    }
+}
+
+fn from_text<M>(manifest: &Manifest<M>, text: &str) -> Result<Manifest<M>, EditError>
+where
+    M: Routing,
+{
+    let sources: HashSet<_> = manifest
+        .routes()
+        .flat_map(|route| route.sources().copied().collect::<Vec<_>>())
+        .collect();
+    let mut edited = Manifest::default();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (source, destination) = line
+            .split_once('\t')
+            .filter(|(_, destination)| !destination.is_empty())
+            .ok_or(EditError::Parse(n + 1))?;
+        let source = Path::new(source);
+        if !sources.contains(source) {
+            return Err(EditError::UnknownSource(source.into()));
+        }
+        edited
+            .insert(source, destination)
+            .map_err(EditError::RouteInsertion)?;
+    }
+    Ok(edited)
+}
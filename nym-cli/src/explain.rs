@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+
+use nym::glob::Glob;
+use nym::pattern::ToPattern;
+
+/// Prints how a from-pattern (and optionally a to-pattern) is interpreted.
+///
+/// The from-pattern is described by its literal path prefix, token tree,
+/// compiled regular expression, and the rules that reject variants of it. The
+/// captures used by the to-pattern are described by the tokens of the
+/// from-pattern that they refer to. Returns `false` if either pattern is
+/// rejected.
+pub fn print(output: &mut impl Write, from: &str, to: Option<&str>) -> io::Result<bool> {
+    let (prefix, glob) = match Glob::partitioned(from) {
+        Ok(parts) => parts,
+        Err(error) => {
+            writeln!(output, "From-pattern `{}` is rejected: {}", from, error)?;
+            if let Some(offset) = error.parse_offset(from) {
+                writeln!(output, "\n  {}", from)?;
+                writeln!(output, "  {: >width$}^", "", width = offset)?;
+            }
+            return Ok(false);
+        }
+    };
+
+    writeln!(output, "From-pattern `{}`", from)?;
+    if prefix.as_os_str().is_empty() {
+        writeln!(output, "\nPrefix\n  (none)")?;
+    }
+    else {
+        writeln!(output, "\nPrefix\n  {}", prefix.display())?;
+    }
+    writeln!(output, "\nTokens")?;
+    let tree = glob.token_tree();
+    if tree.is_empty() {
+        writeln!(output, "  (none)")?;
+    }
+    for line in tree.lines() {
+        writeln!(output, "  {}", line)?;
+    }
+    writeln!(output, "\nRegular expression\n  {}", glob.regex())?;
+    writeln!(output, "\nCaptures\n  #0 is the entire matched path")?;
+    if glob.captures_len() > 1 {
+        writeln!(output, "  #1 and above are labeled in the token tree")?;
+    }
+    let constraints = glob.constraints();
+    if !constraints.is_empty() {
+        writeln!(output, "\nRules")?;
+        for rule in constraints {
+            writeln!(output, "  {} (such as `{}`)", rule, rule.example())?;
+        }
+    }
+
+    let to = match to {
+        Some(to) => to,
+        None => {
+            return Ok(true);
+        }
+    };
+    let pattern = match ToPattern::new(to)
+        .and_then(|to| to.with_named_captures(|name| glob.capture_index(name)))
+    {
+        Ok(pattern) => pattern,
+        Err(error) => {
+            writeln!(output, "\nTo-pattern `{}` is rejected: {}", to, error)?;
+            return Ok(false);
+        }
+    };
+    writeln!(output, "\nTo-pattern `{}`", to)?;
+    let mut indices: Vec<_> = pattern.capture_indices().collect();
+    indices.sort_unstable();
+    indices.dedup();
+    if indices.is_empty() {
+        writeln!(output, "  uses no captures")?;
+    }
+    for index in indices {
+        if index < glob.captures_len() {
+            writeln!(output, "  #{} is captured by the from-pattern", index)?;
+        }
+        else {
+            writeln!(
+                output,
+                "  #{} is not captured by the from-pattern and is always empty",
+                index,
+            )?;
+        }
+    }
+    Ok(true)
+}
@@ -1,78 +1,531 @@
+mod config;
+mod doctor;
+mod edit;
+mod explain;
 mod option;
+mod plan;
+mod report;
 mod terminal;
+mod tui;
 
 use anyhow::Error;
-use std::path::PathBuf;
+use indicatif::ProgressBar;
+use miette::{Diagnostic, Report};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use structopt::clap::Shell;
 use structopt::StructOpt;
+use thiserror::Error;
+use tracing::{debug, Level};
 
-use nym::actuator::{Copy, HardLink, Move, Operation, SoftLink};
-use nym::environment::{Environment, Policy};
-use nym::glob::Glob;
-use nym::manifest::Manifest;
-use nym::pattern::{FromPattern, ToPattern};
+use nym::actuator::{Copy, HardLink, HardLinkOrCopy, Move, Operation, SoftLink};
+use nym::environment::{Environment, Overwrite, Policy};
+#[cfg(unix)]
+use nym::filter::OwnerFilter;
+use nym::filter::{self, SizeFilter, TimeFilter};
+use nym::glob::{Captures, Glob, GlobError, GlobSet, MetadataFilter, WalkBehavior, WalkOrder};
+use nym::manifest::ManifestError;
+use nym::manifest::{Bijective, Manifest, Route, Routing};
+use nym::pattern::{DigestCache, FromPattern, PatternError, Resolution, ToPattern};
+use nym::report::{Reporter, Warning};
+use nym::text;
+use nym::transform::{Collision, TransformBuilder, TransformError};
 
-use crate::option::{ChildCommand, Toggle};
-use crate::terminal::{IteratorExt as _, Print, Terminal};
+use crate::config::Config;
+use crate::option::{
+    ChildCommand, EventFormat, FileTypeSelector, ManifestLayout, OptionError, OutputFormat,
+    OverwriteMode, PathForm, Quoting, SortKey, Toggle,
+};
+use crate::plan::{Journal, OperationKind, Outcome, Plan, PlannedSource};
+use crate::report::{PatternTextError, UndiagnosedError};
+use crate::terminal::{Event, IteratorExt as _, Print, Summary, Terminal, Theme};
+
+const DEFAULT_PAGER: &str = "less -R --no-init --quit-if-one-screen --quit-on-intr";
+const DEFAULT_PAGER_FALLBACK: &str = "more";
+const DEFAULT_DEPTH: usize = 255;
+const DEFAULT_CONFIRM_THRESHOLD: usize = 100;
+
+/// Maximum number of routes printed in a text manifest.
+const MANIFEST_PRINT_LIMIT: usize = 1000;
+
+/// Delay used to debounce changes in watch mode.
+const WATCH_DELAY: Duration = Duration::from_secs(2);
+
+const EXIT_STATUS_HELP: &str = "\
+EXIT STATUS:
+    0    Success.
+    1    No files matched.
+    2    Failure, such as an invalid pattern or a route that violates policy.
+    3    Failure while writing files. Some files may have been written.
+    4    Aborted at a prompt.";
+
+/// Exit status of the program.
+///
+/// See `EXIT_STATUS_HELP`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Exit {
+    Success = 0,
+    NoMatches = 1,
+    Failure = 2,
+    PartialFailure = 3,
+    Aborted = 4,
+}
+
+/// Error that occurs while writing files.
+#[derive(Debug, Diagnostic, Error)]
+#[diagnostic(code(nym::actuation))]
+#[error("failed to write route: {0}")]
+struct ActuationError(io::Error);
+
+/// Error that occurs while removing a file.
+#[derive(Debug, Diagnostic, Error)]
+#[diagnostic(code(nym::actuation))]
+#[error("failed to remove `{}`: {1}", .0.display())]
+struct RemovalError(PathBuf, io::Error);
+
+/// Error that occurs while writing a route when routes are written after
+/// failures.
+#[derive(Debug, Diagnostic, Error)]
+#[error("failed to write route to `{}`: {1}", .0.display())]
+struct RouteError(PathBuf, io::Error);
+
+/// Errors that occur while writing files when routes are written after
+/// failures (see `--keep-going`).
+#[derive(Debug, Diagnostic, Error)]
+#[diagnostic(code(nym::actuation))]
+#[error("failed to write {} of {} routes", .errors.len(), .len)]
+struct ActuationFailures {
+    #[related]
+    errors: Vec<RouteError>,
+    len: usize,
+}
+
+/// Error that occurs when a path resolved by `touch` cannot be created.
+#[derive(Debug, Diagnostic, Error)]
+#[diagnostic(
+    code(nym::touch::path_collision),
+    help("use the `{{!n}}` property to resolve a distinct path for each file")
+)]
+#[error("path already exists or is resolved more than once: `{0}`")]
+struct PathCollisionError(PathBuf);
 
 trait Label {
+    const KIND: OperationKind;
     const LABEL: &'static str;
+    const PAST_LABEL: &'static str;
+    /// Determines if sources are removed, in which case confirming many routes
+    /// requires typing their count.
+    const IS_DESTRUCTIVE: bool = false;
+    /// Determines if routes may be copied rather than linked, in which case
+    /// copied routes are detected and reported.
+    const IS_COPY_FALLBACK: bool = false;
 }
 
 impl Label for Copy {
+    const KIND: OperationKind = OperationKind::Copy;
     const LABEL: &'static str = "copy";
+    const PAST_LABEL: &'static str = "copied";
 }
 
 impl Label for HardLink {
+    const KIND: OperationKind = OperationKind::HardLink;
     const LABEL: &'static str = "hard link";
+    const PAST_LABEL: &'static str = "hard linked";
+}
+
+impl Label for HardLinkOrCopy {
+    const KIND: OperationKind = OperationKind::HardLinkOrCopy;
+    const LABEL: &'static str = "hard link";
+    const PAST_LABEL: &'static str = "hard linked";
+    const IS_COPY_FALLBACK: bool = true;
 }
 
 impl Label for Move {
+    const KIND: OperationKind = OperationKind::Move;
     const LABEL: &'static str = "move";
+    const PAST_LABEL: &'static str = "moved";
+    const IS_DESTRUCTIVE: bool = true;
 }
 
 impl Label for SoftLink {
+    const KIND: OperationKind = OperationKind::SoftLink;
     const LABEL: &'static str = "soft link";
+    const PAST_LABEL: &'static str = "soft linked";
 }
 
 /// Append, copy, link, and move files using patterns.
 #[derive(Debug, StructOpt)]
-#[structopt(rename_all = "kebab-case")]
+#[structopt(rename_all = "kebab-case", after_help = EXIT_STATUS_HELP)]
 struct Program {
+    /// Path to a configuration file.
+    ///
+    /// If not given, configuration is read from `~/.config/nym/config.toml`
+    /// if it exists.
+    #[structopt(long = "config", value_name = "path", global = true)]
+    config: Option<PathBuf>,
+    /// Log internal events to standard error. May be repeated.
+    ///
+    /// Warnings are always logged. One occurrence logs informational events
+    /// and two or more occurrences log debugging events, such as planned
+    /// routes and the result of writing each route.
+    #[structopt(long = "verbose", short = "v", parse(from_occurrences), global = true)]
+    verbosity: u8,
+    /// Format of logged events.
+    ///
+    /// One of "text" or "json". When "json", each event is logged as a JSON
+    /// object on its own line.
+    #[structopt(
+        long = "log-format",
+        value_name = "format",
+        default_value = "text",
+        global = true
+    )]
+    log_format: OutputFormat,
     #[structopt(subcommand)]
     command: Command,
 }
 
 impl Program {
-    pub fn run(&mut self) -> Result<(), Error> {
-        terminal::toggle_color_output(self.command.common_option_group().color);
+    pub fn run(&mut self) -> Result<Exit, Error> {
+        let config = Config::read(self.config.as_deref())?;
+        let mut theme = config.theme()?;
+        // Consoles that cannot interpret escape sequences also tend to be
+        // unable to display box-drawing characters.
+        if !terminal::enable_virtual_terminal_output() {
+            theme.ascii.get_or_insert(true);
+        }
+        terminal::set_theme(Theme::from(&theme));
+        self.command.configure(&config)?;
+        if let Some(options) = self.command.common_option_group() {
+            terminal::toggle_color_output(options.color());
+        }
+        self.log();
         match self.command {
             Command::Append { .. } => todo!(
                 "append is not yet implemented and may never be; consider piping into `tar` or \
                  other archiving tools"
             ),
+            Command::Apply {
+                ref mut options,
+                ref manifest,
+                ref from_manifest,
+                operation,
+                ref policy,
+            } => {
+                let plan = match (from_manifest, operation) {
+                    (Some(path), Some(operation)) => Plan::from_manifest(
+                        operation,
+                        &policy.policy(),
+                        &plan::read_manifest::<Bijective>(path)?,
+                    )?,
+                    _ => Plan::read(manifest.as_ref().expect("no plan nor manifest"))?,
+                };
+                if plan.is_edited() {
+                    terminal::warning("routes in plan have been edited since it was written")?;
+                }
+                match plan.operation() {
+                    OperationKind::Copy => apply::<Copy>(options, &plan),
+                    OperationKind::HardLink => apply::<HardLink>(options, &plan),
+                    OperationKind::HardLinkOrCopy => apply::<HardLinkOrCopy>(options, &plan),
+                    OperationKind::Move => apply::<Move>(options, &plan),
+                    OperationKind::SoftLink => apply::<SoftLink>(options, &plan),
+                }
+            }
             Command::Copy {
                 ref mut options,
                 ref transform,
-                ..
-            } => actuate::<Copy>(options, transform),
+                merge,
+            } => {
+                options.policy.merge = merge;
+                actuate::<Copy>(options, transform)
+            }
+            Command::Delete {
+                ref from,
+                trash,
+                ref mut options,
+                ref traversal,
+                interactive,
+                assume_yes,
+                assume_no,
+                confirm_threshold,
+                quiet,
+                dry_run,
+            } => {
+                // Directories and links are not removed, so they are rejected
+                // before anything is matched or confirmed.
+                if traversal.file_type != FileTypeSelector::File {
+                    return Err(OptionError::FileType("delete").into());
+                }
+                let from = parse_from_pattern(from, traversal)?;
+                let mut warnings = Vec::new();
+                let paths: Vec<_> = from
+                    .walk(&traversal.directory, traversal.walk_behavior())
+                    .filter_map(|entry| match entry {
+                        Ok(entry) => Some(Ok(entry.into_path())),
+                        Err(error @ GlobError::LinkLoop(_)) => {
+                            warnings.push(error);
+                            None
+                        }
+                        Err(error) => Some(Err(error)),
+                    })
+                    .collect::<Result<_, _>>()?;
+                for warning in warnings {
+                    terminal::warning(warning.to_string())?;
+                }
+                if paths.is_empty() {
+                    return Ok(Exit::NoMatches);
+                }
+                remove(
+                    &paths,
+                    Removal {
+                        trash,
+                        quiet,
+                        dry_run,
+                        interactive,
+                        assumption: assumption(assume_yes, assume_no),
+                        confirm_threshold,
+                    },
+                    options,
+                    |_| Ok(()),
+                )
+            }
+            Command::Completions { shell } => {
+                Program::clap().gen_completions_to(
+                    env!("CARGO_BIN_NAME"),
+                    shell,
+                    &mut io::stdout(),
+                );
+                Ok(Exit::Success)
+            }
+            Command::Doctor {
+                ref mut options,
+                ref directory,
+            } => {
+                doctor::print(
+                    &mut io::stdout(),
+                    options.pager(),
+                    directory,
+                    self.config.as_deref(),
+                )?;
+                Ok(Exit::Success)
+            }
+            Command::Dupes {
+                ref from,
+                link,
+                delete: is_deleted,
+                trash,
+                ref mut options,
+                ref paths,
+                ref traversal,
+                interactive,
+                assume_yes,
+                assume_no,
+                quiet,
+                dry_run,
+                confirm_threshold,
+            } => {
+                let from = parse_from_pattern(from, traversal)?;
+                let groups = find_duplicates(&from, traversal)?;
+                if groups.is_empty() {
+                    return Ok(Exit::NoMatches);
+                }
+                if !quiet {
+                    let form = paths.form(&traversal.directory);
+                    let paging = options.paging;
+                    Terminal::with_output_process_scoped(options.pager(), paging, |mut output| {
+                        for (n, group) in groups.iter().enumerate() {
+                            if n > 0 {
+                                writeln!(output)?;
+                            }
+                            for duplicate in group {
+                                form.apply(&duplicate.path).as_ref().print(&mut output)?;
+                            }
+                        }
+                        Ok::<_, io::Error>(())
+                    })?;
+                }
+                if !(link || is_deleted) {
+                    return Ok(Exit::Success);
+                }
+                // The first file in each group is kept and the remaining
+                // files are its duplicates.
+                let duplicates: Vec<_> = groups
+                    .iter()
+                    .flat_map(|group| {
+                        let kept = &group[0];
+                        group[1..].iter().map(move |duplicate| (kept, duplicate))
+                    })
+                    .filter(|(kept, duplicate)| {
+                        !(link
+                            && same_file::is_same_file(&kept.path, &duplicate.path)
+                                .unwrap_or(false))
+                    })
+                    .collect();
+                if duplicates.is_empty() {
+                    return Ok(Exit::Success);
+                }
+                if is_deleted {
+                    // Duplicates are removed like any other matched files,
+                    // but only if neither they nor the files that they
+                    // duplicate have changed since they were hashed.
+                    let paths: Vec<_> = duplicates
+                        .iter()
+                        .map(|(_, duplicate)| duplicate.path.clone())
+                        .collect();
+                    return remove(
+                        &paths,
+                        Removal {
+                            trash,
+                            quiet,
+                            dry_run,
+                            interactive,
+                            assumption: assumption(assume_yes, assume_no),
+                            confirm_threshold,
+                        },
+                        options,
+                        |n| {
+                            let (kept, duplicate) = duplicates[n];
+                            kept.verify().and_then(|_| duplicate.verify())
+                        },
+                    );
+                }
+                if dry_run {
+                    return Ok(Exit::Success);
+                }
+                if !confirm(
+                    interactive,
+                    assumption(assume_yes, assume_no),
+                    format!("Ready to hard link {} duplicate files.", duplicates.len()),
+                    duplicates.len(),
+                    Some(confirm_threshold.unwrap_or(DEFAULT_CONFIRM_THRESHOLD)),
+                )? {
+                    return Ok(Exit::Aborted);
+                }
+                link_duplicates(&duplicates, quiet)
+            }
+            Command::Explain { ref from, ref to } => {
+                Ok(if explain::print(&mut io::stdout(), from, to.as_deref())? {
+                    Exit::Success
+                }
+                else {
+                    Exit::Failure
+                })
+            }
             Command::Find {
                 ref mut options,
+                ref traversal,
                 ref from,
-                ..
+                ref paths,
+                print0,
+                json,
+                long,
+                print_shell,
+                count,
+                by_directory,
             } => {
-                let from = parse_from_pattern(from)?;
-                let mut output = Terminal::with_output_process(&mut options.pager, options.paging);
-                for entry in from.walk(&options.directory, options.depth + 1).flatten() {
-                    entry.path().print(&mut output)?;
+                let from = parse_from_pattern(from, traversal)?;
+                let form = paths.form(&traversal.directory);
+                // Never page null-terminated nor JSON output, which are
+                // intended for other programs and not people. Counts are
+                // never long enough to page.
+                let paging = if print0 || json || count {
+                    Toggle::Never
                 }
-                Ok(())
+                else {
+                    options.paging
+                };
+                let mut output = Terminal::with_output_process(options.pager(), paging);
+                let mut exit = Exit::NoMatches;
+                let mut total = 0usize;
+                let mut directories = BTreeMap::<PathBuf, usize>::new();
+                for entry in from.walk(&traversal.directory, traversal.walk_behavior()) {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(error @ GlobError::LinkLoop(_)) => {
+                            terminal::warning(error.to_string())?;
+                            continue;
+                        }
+                        Err(error) => {
+                            return Err(error.into());
+                        }
+                    };
+                    exit = Exit::Success;
+                    if count {
+                        total += 1;
+                        if by_directory {
+                            // Files within the working directory are counted
+                            // against the working directory itself.
+                            let directory = entry
+                                .path()
+                                .strip_prefix(&traversal.directory)
+                                .ok()
+                                .filter(|path| path.components().nth(1).is_some())
+                                .and_then(|path| path.components().next())
+                                .map_or_else(
+                                    || traversal.directory.clone(),
+                                    |component| traversal.directory.join(component),
+                                );
+                            *directories.entry(directory).or_default() += 1;
+                        }
+                        continue;
+                    }
+                    if print0 {
+                        terminal::print_null_terminated(&form.apply(entry.path()), &mut output)?;
+                    }
+                    else if json {
+                        terminal::print_json(&entry, &form, &mut output)?;
+                    }
+                    else if long {
+                        terminal::print_long(&entry, &form, &mut output)?;
+                    }
+                    else if let Some(quoting) = print_shell {
+                        let quoting = quoting.unwrap_or_default();
+                        terminal::print_shell_quoted(
+                            &form.apply(entry.path()),
+                            quoting,
+                            &mut output,
+                        )?;
+                    }
+                    else {
+                        form.apply(entry.path()).as_ref().print(&mut output)?;
+                    }
+                }
+                if count {
+                    if by_directory {
+                        for (directory, count) in directories {
+                            writeln!(output, "{}\t{}", count, form.apply(&directory).display())?;
+                        }
+                    }
+                    else {
+                        writeln!(output, "{}", total)?;
+                    }
+                }
+                Ok(exit)
             }
             Command::Link { ref mut link, .. } => match link {
                 Link::Hard {
                     ref mut options,
                     ref transform,
-                    ..
-                } => actuate::<HardLink>(options, transform),
+                    or_copy,
+                } => {
+                    if *or_copy {
+                        actuate::<HardLinkOrCopy>(options, transform)
+                    }
+                    else {
+                        actuate::<HardLink>(options, transform)
+                    }
+                }
                 Link::Soft {
                     ref mut options,
                     ref transform,
@@ -84,6 +537,177 @@ impl Program {
                 ref transform,
                 ..
             } => actuate::<Move>(options, transform),
+            Command::Plan {
+                operation,
+                ref transform,
+                ref traversal,
+                ref policy,
+                ref output,
+                ..
+            } => match operation {
+                OperationKind::Copy => {
+                    plan::<Copy>(operation, transform, traversal, policy, output)
+                }
+                OperationKind::HardLink => {
+                    plan::<HardLink>(operation, transform, traversal, policy, output)
+                }
+                OperationKind::HardLinkOrCopy => {
+                    plan::<HardLinkOrCopy>(operation, transform, traversal, policy, output)
+                }
+                OperationKind::Move => {
+                    plan::<Move>(operation, transform, traversal, policy, output)
+                }
+                OperationKind::SoftLink => {
+                    plan::<SoftLink>(operation, transform, traversal, policy, output)
+                }
+            },
+            Command::Preview {
+                ref mut options,
+                ref traversal,
+                ref from,
+                ref to,
+                count,
+            } => {
+                let from = parse_from_pattern(from, traversal)?;
+                let digests = traversal.read_digest_cache()?;
+                let to = with_digest_cache(parse_to_pattern(to, &from)?, digests.as_ref());
+                let paging = options.paging;
+                let mut output = Terminal::with_output_process(options.pager(), paging);
+                let mut position = 0;
+                let mut destination = String::new();
+                for entry in from
+                    .walk(&traversal.directory, traversal.walk_behavior())
+                    .filter(|entry| matches!(entry, Ok(_) | Err(GlobError::LinkLoop(_))))
+                    .take(count)
+                {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(error) => {
+                            terminal::warning(error.to_string())?;
+                            continue;
+                        }
+                    };
+                    position += 1;
+                    destination.clear();
+                    to.resolve_into(
+                        &mut destination,
+                        Resolution {
+                            source: entry.path(),
+                            directory: &traversal.directory,
+                            captures: entry.captures(),
+                            position,
+                        },
+                    )?;
+                    terminal::print_resolution(
+                        entry.path(),
+                        &traversal.directory.join(&destination),
+                        &mut output,
+                    )?;
+                }
+                traversal.write_digest_cache(digests.as_deref())?;
+                Ok(if position > 0 {
+                    Exit::Success
+                }
+                else {
+                    Exit::NoMatches
+                })
+            }
+            Command::Stat {
+                ref mut options,
+                ref traversal,
+                ref from,
+                ref format,
+            } => {
+                let from = parse_from_pattern(from, traversal)?;
+                let digests = traversal.read_digest_cache()?;
+                let format = with_digest_cache(parse_to_pattern(format, &from)?, digests.as_ref());
+                let paging = options.paging;
+                let mut output = Terminal::with_output_process(options.pager(), paging);
+                let mut position = 0;
+                let mut text = String::new();
+                for entry in from.walk(&traversal.directory, traversal.walk_behavior()) {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(error @ GlobError::LinkLoop(_)) => {
+                            terminal::warning(error.to_string())?;
+                            continue;
+                        }
+                        Err(error) => {
+                            return Err(error.into());
+                        }
+                    };
+                    position += 1;
+                    text.clear();
+                    format.resolve_into(
+                        &mut text,
+                        Resolution {
+                            source: entry.path(),
+                            directory: &traversal.directory,
+                            captures: entry.captures(),
+                            position,
+                        },
+                    )?;
+                    writeln!(output, "{}", text)?;
+                }
+                traversal.write_digest_cache(digests.as_deref())?;
+                Ok(if position > 0 {
+                    Exit::Success
+                }
+                else {
+                    Exit::NoMatches
+                })
+            }
+            Command::Tui {
+                operation,
+                ref transform,
+                ref mut actuation,
+                ref traversal,
+                ref policy,
+            } => match operation {
+                OperationKind::Copy => tui::<Copy>(actuation, transform, traversal, policy),
+                OperationKind::HardLink => tui::<HardLink>(actuation, transform, traversal, policy),
+                OperationKind::HardLinkOrCopy => {
+                    tui::<HardLinkOrCopy>(actuation, transform, traversal, policy)
+                }
+                OperationKind::Move => tui::<Move>(actuation, transform, traversal, policy),
+                OperationKind::SoftLink => tui::<SoftLink>(actuation, transform, traversal, policy),
+            },
+            Command::Touch {
+                ref to,
+                count,
+                ref directory,
+                directories,
+                parents,
+                ref mut options,
+                quiet,
+                dry_run,
+            } => touch(
+                to,
+                count,
+                directory,
+                directories,
+                parents,
+                options,
+                quiet,
+                dry_run,
+            ),
+        }
+    }
+
+    fn log(&self) {
+        let level = match self.verbosity {
+            0 => Level::WARN,
+            1 => Level::INFO,
+            2 => Level::DEBUG,
+            _ => Level::TRACE,
+        };
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_ansi(console::colors_enabled_stderr())
+            .with_writer(io::stderr);
+        match self.log_format {
+            OutputFormat::Json => subscriber.json().init(),
+            OutputFormat::Text => subscriber.init(),
         }
     }
 }
@@ -91,15 +715,6 @@ impl Program {
 #[derive(Debug, StructOpt)]
 #[structopt(rename_all = "kebab-case")]
 struct CommonOptionGroup {
-    /// Working directory tree.
-    #[structopt(long = "tree", short = "C", default_value = ".")]
-    directory: PathBuf,
-    /// Maximum depth traversed into the working directory tree.
-    ///
-    /// A depth of zero only includes files within the working directory (there
-    /// is no traversal into directories).
-    #[structopt(long = "depth", default_value = "255")]
-    depth: usize,
     /// Determines if and when non-error output is routed to a configured pager.
     ///
     /// One of "always", "never", or "automatic" (or its abbreviation "auto").
@@ -108,25 +723,510 @@ struct CommonOptionGroup {
     /// redirected, etc.).
     #[structopt(long = "paging", value_name = "when", default_value = "automatic")]
     paging: Toggle,
+    /// Disables paging. Equivalent to `--paging never`.
+    #[structopt(long = "no-pager", overrides_with = "paging")]
+    no_pager: bool,
     /// Pager command line.
-    #[structopt(
-        long = "pager",
-        value_name = "command",
-        default_value = "less -R --no-init --quit-if-one-screen --quit-on-intr"
-    )]
-    pager: ChildCommand,
+    ///
+    /// If the pager cannot be started, then the `NYM_PAGER` and `PAGER`
+    /// environment variables, "less -R --no-init --quit-if-one-screen
+    /// --quit-on-intr", and "more" are tried in that order.
+    #[structopt(long = "pager", value_name = "command", env = "NYM_PAGER")]
+    pager: Option<ChildCommand>,
+    #[structopt(skip)]
+    pagers: Vec<ChildCommand>,
     /// Determines if and when color and style is enabled in output.
     ///
     /// One of "always", "never", or "automatic" (or its abbreviation "auto").
     /// When "automatic", output is colored and styled based on the CLI colors
-    /// specification: https://bixense.com/clicolors/
-    #[structopt(long = "color", value_name = "when", default_value = "automatic")]
-    color: Toggle,
+    /// specification (https://bixense.com/clicolors/) and `NO_COLOR`
+    /// (https://no-color.org/). That is, `CLICOLOR_FORCE` forces colors,
+    /// `NO_COLOR` and `CLICOLOR=0` disable colors, and otherwise colors are
+    /// used when writing to an attended terminal. This also applies to prompts
+    /// and progress bars.
+    ///
+    /// Defaults to "automatic".
+    #[structopt(long = "color", value_name = "when", env = "NYM_COLOR")]
+    color: Option<Toggle>,
+}
+
+impl CommonOptionGroup {
+    fn configure(&mut self, config: &Config) -> Result<(), OptionError> {
+        if self.pager.is_none() {
+            self.pager = config.pager.as_deref().map(str::parse).transpose()?;
+        }
+        if self.no_pager {
+            self.paging = Toggle::Never;
+        }
+        self.color = self.color.or(config.color);
+        Ok(())
+    }
+
+    fn pager(&mut self) -> &mut [ChildCommand] {
+        if self.pagers.is_empty() {
+            self.pagers = self
+                .pager
+                .take()
+                .into_iter()
+                .chain(
+                    ["NYM_PAGER", "PAGER"]
+                        .iter()
+                        .flat_map(env::var)
+                        .flat_map(|pager| pager.parse()),
+                )
+                .chain(
+                    [DEFAULT_PAGER, DEFAULT_PAGER_FALLBACK]
+                        .iter()
+                        .map(|pager| pager.parse().expect("failed to parse default pager")),
+                )
+                .collect();
+        }
+        &mut self.pagers
+    }
+
+    fn color(&self) -> Toggle {
+        self.color.unwrap_or_default()
+    }
 }
 
 #[derive(Debug, StructOpt)]
 #[structopt(rename_all = "kebab-case")]
-struct TransformOptionGroup {
+struct TraversalOptionGroup {
+    /// Working directory tree.
+    #[structopt(long = "tree", short = "C", default_value = ".", env = "NYM_TREE")]
+    directory: PathBuf,
+    /// Maximum depth traversed into the working directory tree.
+    ///
+    /// A depth of zero only includes files within the working directory (there
+    /// is no traversal into directories). Defaults to 255.
+    #[structopt(long = "depth", env = "NYM_DEPTH")]
+    depth: Option<usize>,
+    /// Minimum depth of matched files in the working directory tree.
+    ///
+    /// Files shallower than this depth are not matched, but directories are
+    /// still traversed. A minimum depth of one skips files within the working
+    /// directory. Defaults to zero.
+    #[structopt(long = "min-depth", value_name = "depth")]
+    min_depth: Option<usize>,
+    /// Do not traverse into directories.
+    ///
+    /// Only files within the working directory are matched. This is the same
+    /// as a depth of zero.
+    #[structopt(long = "no-recurse", conflicts_with = "depth")]
+    no_recurse: bool,
+    /// Glob of paths to exclude. May be repeated.
+    ///
+    /// Exclusions are applied relative to the working directory tree, just
+    /// like from-patterns. Directories that match an exclusion are not
+    /// traversed. For example, `--exclude='**/node_modules'` ignores any
+    /// `node_modules` directory and its contents.
+    #[structopt(
+        long = "exclude",
+        short = "x",
+        value_name = "glob",
+        number_of_values = 1
+    )]
+    exclude: Vec<String>,
+    /// Size bound of matched files. May be repeated.
+    ///
+    /// Sizes prefixed with `+` match larger files and sizes prefixed with `-`
+    /// match smaller files. Units are `k`, `M`, `G`, and `T` (powers of 1024)
+    /// and sizes without a unit are in bytes. For example, `--size=+10M`
+    /// matches files larger than ten mebibytes and `--size=+1k --size=-1M`
+    /// matches files between one kibibyte and one mebibyte.
+    #[structopt(
+        long = "size",
+        value_name = "size",
+        number_of_values = 1,
+        allow_hyphen_values = true
+    )]
+    size: Vec<SizeFilter>,
+    /// Only match files modified more recently than a duration ago or a
+    /// timestamp.
+    ///
+    /// Durations are numbers with units `s`, `m`, `h`, `d`, and `w`, like
+    /// `30d` or `1h30m`. Timestamps are local dates and times, like
+    /// `2021-06-01` or `2021-06-01T12:00:00`, or RFC 3339 date times with
+    /// offsets.
+    #[structopt(
+        long = "newer-than",
+        value_name = "duration|timestamp",
+        parse(try_from_str = filter::parse_time)
+    )]
+    newer_than: Option<SystemTime>,
+    /// Only match files modified less recently than a duration ago or a
+    /// timestamp.
+    ///
+    /// For example, `--older-than=30d` only matches files that have not been
+    /// modified in the past thirty days. See `--newer-than`.
+    #[structopt(
+        long = "older-than",
+        value_name = "duration|timestamp",
+        parse(try_from_str = filter::parse_time)
+    )]
+    older_than: Option<SystemTime>,
+    /// Only match files owned by a user, given by name or ID.
+    #[cfg(unix)]
+    #[structopt(
+        long = "owner",
+        value_name = "user",
+        parse(try_from_str = option::parse_user)
+    )]
+    owner: Option<u32>,
+    /// Only match files owned by a group, given by name or ID.
+    #[cfg(unix)]
+    #[structopt(
+        long = "group",
+        value_name = "group",
+        parse(try_from_str = option::parse_group)
+    )]
+    group: Option<u32>,
+    /// Include hidden files and directories.
+    ///
+    /// Files and directories are hidden if their names begin with a dot `.`.
+    /// By default, hidden files are not matched and hidden directories are
+    /// not traversed.
+    #[structopt(long = "hidden", short = "H")]
+    hidden: bool,
+    /// Respect ignore files like `.gitignore` (default).
+    ///
+    /// Paths ignored by `.gitignore` and `.ignore` files and the
+    /// `.git/info/exclude` file of a Git repository are not matched and
+    /// ignored directories are not traversed.
+    #[structopt(long = "ignore", overrides_with = "no-ignore")]
+    _ignore: bool,
+    /// Do not respect ignore files like `.gitignore`.
+    #[structopt(long = "no-ignore", overrides_with = "ignore")]
+    no_ignore: bool,
+    /// Follow symbolic links.
+    ///
+    /// Linked files are matched and linked directories are traversed. Links
+    /// that form loops are reported and are not traversed.
+    #[structopt(long = "follow", short = "L")]
+    follow: bool,
+    /// Kind of file matched by from-patterns.
+    ///
+    /// One of "file", "directory", or "link" (or their
+    /// abbreviations "f", "d", and "l"). Directories are traversed regardless
+    /// of the kind of file that is matched. Links are matched whether or not
+    /// they are followed. `delete` only accepts "file".
+    #[structopt(
+        long = "type",
+        short = "t",
+        value_name = "kind",
+        default_value = "file"
+    )]
+    file_type: FileTypeSelector,
+    /// Maximum number of matched files.
+    ///
+    /// Traversal stops once this many files have matched, which is useful
+    /// for sampling the matches of a pattern in a large directory tree.
+    #[structopt(long = "limit", value_name = "count")]
+    limit: Option<usize>,
+    /// Read directories in parallel.
+    ///
+    /// This can be much faster for large directory trees, but files are
+    /// matched in the order in which they are read, which varies from run to
+    /// run. With `--limit`, the files that are matched are those that happen
+    /// to be read first.
+    #[structopt(long = "parallel")]
+    parallel: bool,
+    /// Match from-patterns and exclusions without regard to case.
+    ///
+    /// For example, `*.jpg` also matches `photo.JPG`. Only ASCII letters are
+    /// case insensitive and literal leading directories in patterns (like
+    /// `photos` in `photos/*.jpg`) must still match exactly.
+    #[structopt(long = "case-insensitive", short = "i")]
+    case_insensitive: bool,
+    /// Parse from-patterns as regular expressions rather than globs.
+    ///
+    /// Regular expressions must match entire paths relative to the working
+    /// directory tree. Named groups like `(?P<name>...)` can be used by name
+    /// in to-patterns via `{@[name]}`.
+    #[structopt(long = "regex")]
+    regex: bool,
+    /// File in which digests like `{!b3sum}` are cached between runs.
+    ///
+    /// Cached digests are only used if the size and modification time of
+    /// their files are unchanged, so files that have not changed are not read
+    /// again. The file is created if it does not exist.
+    #[structopt(long = "digest-cache", value_name = "file")]
+    digest_cache: Option<PathBuf>,
+}
+
+impl TraversalOptionGroup {
+    fn configure(&mut self, config: &Config) {
+        self.depth = self.depth.or(config.depth);
+        self.exclude.extend(config.exclude.iter().cloned());
+        if self.digest_cache.is_none() {
+            self.digest_cache = config.digest_cache.clone();
+        }
+    }
+
+    /// Reads the digest cache, if any.
+    ///
+    /// Digests can always be computed again, so a cache that cannot be read is
+    /// reported as a warning and not used.
+    fn read_digest_cache(&self) -> Result<Option<Arc<DigestCache>>, Error> {
+        let path = match self.digest_cache {
+            Some(ref path) => path,
+            None => {
+                return Ok(None);
+            }
+        };
+        match DigestCache::read(path) {
+            Ok(digests) => Ok(Some(Arc::new(digests))),
+            Err(error) => {
+                terminal::warning(format!("failed to read digest cache: {}", error))?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn write_digest_cache(&self, digests: Option<&DigestCache>) -> Result<(), Error> {
+        if let (Some(path), Some(digests)) = (self.digest_cache.as_ref(), digests) {
+            if let Err(error) = digests.write(path) {
+                terminal::warning(format!("failed to write digest cache: {}", error))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn depth(&self) -> usize {
+        if self.no_recurse {
+            0
+        }
+        else {
+            self.depth.unwrap_or(DEFAULT_DEPTH)
+        }
+    }
+
+    fn time_filters(&self) -> impl Iterator<Item = TimeFilter> {
+        self.newer_than
+            .map(TimeFilter::Newer)
+            .into_iter()
+            .chain(self.older_than.map(TimeFilter::Older))
+    }
+
+    #[cfg(unix)]
+    fn owner_filters(&self) -> impl Iterator<Item = OwnerFilter> {
+        self.owner
+            .map(OwnerFilter::User)
+            .into_iter()
+            .chain(self.group.map(OwnerFilter::Group))
+    }
+
+    /// Gets a predicate that determines if the metadata of a matched file is
+    /// within all size, time, and owner bounds.
+    ///
+    /// Returns `None` if there are no such bounds.
+    fn metadata_filter(&self) -> Option<MetadataFilter> {
+        let sizes = self.size.clone();
+        let times: Vec<_> = self.time_filters().collect();
+        #[cfg(unix)]
+        let owners: Vec<_> = self.owner_filters().collect();
+        #[cfg(unix)]
+        let is_owned = move |metadata: &_| owners.iter().all(|owner| owner.is_match(metadata));
+        #[cfg(not(unix))]
+        let is_owned = |_: &_| true;
+
+        #[cfg(unix)]
+        let has_owner = self.owner_filters().next().is_some();
+        #[cfg(not(unix))]
+        let has_owner = false;
+        if sizes.is_empty() && times.is_empty() && !has_owner {
+            return None;
+        }
+        Some(MetadataFilter::new(move |metadata| {
+            sizes.iter().all(|size| size.is_match(metadata))
+                && times.iter().all(|time| time.is_match(metadata))
+                && is_owned(metadata)
+        }))
+    }
+
+    fn walk_behavior(&self) -> WalkBehavior {
+        WalkBehavior {
+            depth: self.depth() + 1,
+            min_depth: self.min_depth.unwrap_or(0) + 1,
+            hidden: self.hidden,
+            ignore: !self.no_ignore,
+            follow: self.follow,
+            limit: self.limit.unwrap_or(usize::MAX),
+            order: WalkOrder::Unsorted,
+            parallel: self.parallel,
+            filter: self.metadata_filter(),
+        }
+    }
+
+    fn configure_builder<'e, 'f, 't>(
+        &self,
+        transform: TransformBuilder<'e, 'f, 't>,
+    ) -> TransformBuilder<'e, 'f, 't> {
+        let transform = match self.metadata_filter() {
+            Some(filter) => transform.filter_metadata(move |metadata| filter.is_match(metadata)),
+            None => transform,
+        };
+        transform
+            .depth(self.depth())
+            .min_depth(self.min_depth.unwrap_or(0))
+            .hidden(self.hidden)
+            .ignore(!self.no_ignore)
+            .follow(self.follow)
+            .parallel(self.parallel)
+            .limit(self.limit.unwrap_or(usize::MAX))
+    }
+}
+
+/// Options that determine the form of printed paths.
+///
+/// By default, paths are printed as matched and so are joined to the working
+/// directory tree (such as `./photo.jpg`).
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct PathOptionGroup {
+    /// Print absolute paths.
+    #[structopt(
+        long = "absolute",
+        conflicts_with_all = &["relative-to-tree", "relative-to"]
+    )]
+    absolute: bool,
+    /// Print paths relative to the working directory tree.
+    #[structopt(long = "relative-to-tree", conflicts_with = "relative-to")]
+    relative_to_tree: bool,
+    /// Print paths relative to a directory.
+    #[structopt(long = "relative-to", value_name = "directory")]
+    relative_to: Option<PathBuf>,
+}
+
+impl PathOptionGroup {
+    fn form(&self, directory: &Path) -> PathForm {
+        if self.absolute {
+            PathForm::Absolute
+        }
+        else if self.relative_to_tree {
+            PathForm::RelativeTo(directory.to_path_buf())
+        }
+        else if let Some(ref directory) = self.relative_to {
+            PathForm::RelativeTo(directory.clone())
+        }
+        else {
+            PathForm::Matched
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct PolicyOptionGroup {
+    /// Determines if and when existing files resolved by to-patterns are
+    /// overwritten.
+    ///
+    /// One of "always", "never" (default), "newer", or "size-differs". When
+    /// "newer" or "size-differs", existing files are only overwritten if they
+    /// are older than or differ in size from their sources, respectively, and
+    /// other routes to existing files are skipped.
+    #[structopt(
+        long = "overwrite-mode",
+        value_name = "mode",
+        overrides_with_all = &["overwrite", "no-overwrite"]
+    )]
+    overwrite_mode: Option<OverwriteMode>,
+    /// Overwrite existing files resolved by to-patterns.
+    ///
+    /// This is the same as `--overwrite-mode=always`.
+    #[structopt(
+        long = "overwrite",
+        short = "w",
+        overrides_with_all = &["no-overwrite", "overwrite-mode"]
+    )]
+    overwrite: bool,
+    /// Do not overwrite existing files resolved by to-patterns (default).
+    ///
+    /// This is the same as `--overwrite-mode=never`.
+    #[structopt(
+        long = "no-overwrite",
+        overrides_with_all = &["overwrite", "overwrite-mode"]
+    )]
+    no_overwrite: bool,
+    /// Create parent directories for paths resolved by to-patterns.
+    #[structopt(long = "parents", short = "p")]
+    parents: bool,
+    /// Preserve metadata when copying files.
+    ///
+    /// Access and modification times, permissions, and (on Unix) extended
+    /// attributes are copied from source files to destination files.
+    #[structopt(long = "preserve")]
+    preserve: bool,
+    /// Skip files that are identical to existing files resolved by
+    /// to-patterns.
+    ///
+    /// Existing files are identical if they have the same size and content as
+    /// their sources. Routes to them are skipped rather than overwritten or
+    /// rejected, so re-running a transform (such as a copy) only writes files
+    /// that have changed. Skipped routes are logged with `--verbose`.
+    #[structopt(long = "skip-identical")]
+    skip_identical: bool,
+    /// Merge files into existing directories resolved by to-patterns.
+    ///
+    /// This is only available to some commands (see `copy --merge`).
+    #[structopt(skip)]
+    merge: bool,
+    /// Archive files: create parents, preserve metadata, and never overwrite.
+    ///
+    /// This is the same as `--parents --preserve --overwrite-mode=never`.
+    #[structopt(
+        long = "archive",
+        short = "a",
+        conflicts_with_all = &["overwrite", "overwrite-mode"]
+    )]
+    archive: bool,
+}
+
+impl PolicyOptionGroup {
+    fn configure(&mut self, config: &Config) {
+        if self.overwrite_mode.is_none() && !self.overwrite && !self.no_overwrite && !self.archive {
+            self.overwrite_mode = config.overwrite_mode.or_else(|| {
+                config.overwrite.map(|overwrite| {
+                    if overwrite {
+                        OverwriteMode(Overwrite::Always)
+                    }
+                    else {
+                        OverwriteMode(Overwrite::Never)
+                    }
+                })
+            });
+        }
+    }
+
+    fn overwrite_mode(&self) -> OverwriteMode {
+        if self.archive {
+            OverwriteMode(Overwrite::Never)
+        }
+        else if self.overwrite {
+            OverwriteMode(Overwrite::Always)
+        }
+        else if self.no_overwrite {
+            OverwriteMode(Overwrite::Never)
+        }
+        else {
+            self.overwrite_mode.unwrap_or_default()
+        }
+    }
+
+    fn policy(&self) -> Policy {
+        let mut policy = Policy::default();
+        policy.parents = self.parents || self.archive;
+        policy.overwrite = self.overwrite_mode().into();
+        policy.preserve = self.preserve || self.archive;
+        policy.skip_identical = self.skip_identical;
+        policy.merge = self.merge;
+        policy
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct ActuationOptionGroup {
     #[structopt(flatten)]
     common: CommonOptionGroup,
     /// Determines if and when interactive prompts are used.
@@ -136,49 +1236,447 @@ struct TransformOptionGroup {
     /// attended terminal (not piped, redirected, etc.).
     ///
     /// Note that if standard error is piped or redirected and this option is
-    /// "always", then prompts will default to taking no action and commands
-    /// will never be executed.
+    /// "always", then prompts are declined with a warning and commands are
+    /// never executed. Use `--assume-yes` to execute commands without
+    /// prompting.
     #[structopt(long = "interactive", value_name = "when", default_value = "always")]
     interactive: Toggle,
+    /// Continue without prompting, regardless of `--interactive`.
+    #[structopt(long = "assume-yes", short = "y", conflicts_with = "assume-no")]
+    assume_yes: bool,
+    /// Abort without prompting after printing the manifest, regardless of
+    /// `--interactive`.
+    #[structopt(long = "assume-no", conflicts_with = "assume-yes")]
+    assume_no: bool,
     /// Do not print manifests nor warnings.
     #[structopt(long = "quiet", short = "q")]
     quiet: bool,
-    /// Overwrite existing files resolved by to-patterns.
-    #[structopt(long = "overwrite", short = "w")]
-    overwrite: bool,
-    /// Create parent directories for paths resolved by to-patterns.
-    #[structopt(long = "parents", short = "p")]
-    parents: bool,
+    /// Print the manifest and exit without prompting nor writing.
+    #[structopt(long = "dry-run", short = "n")]
+    dry_run: bool,
+    /// Number of routes above which moves must be confirmed by typing.
+    ///
+    /// When a move has more routes than this, the prompt requires typing the
+    /// number of routes (or "yes") rather than answering yes or no. Defaults
+    /// to 100.
+    #[structopt(long = "confirm-threshold", value_name = "count")]
+    confirm_threshold: Option<usize>,
+    /// Number of threads used to write files.
+    ///
+    /// When greater than one, routes are written in parallel and so are not
+    /// necessarily written in the order in which they are printed.
+    #[structopt(
+        long = "threads",
+        short = "j",
+        value_name = "count",
+        default_value = "1"
+    )]
+    threads: usize,
+    /// Edit the manifest in an editor before writing.
+    ///
+    /// Routes are written to a temporary file and opened in `$VISUAL` or
+    /// `$EDITOR`. Removing a line removes its route and changing a
+    /// destination path changes where its source is written.
+    #[structopt(long = "edit")]
+    edit: bool,
+    /// Format of printed manifests.
+    ///
+    /// One of "text" or "json". When "json", the manifest is printed to
+    /// standard output as a single JSON object and is never paged.
+    #[structopt(long = "output", value_name = "format", default_value = "text")]
+    output: OutputFormat,
+    /// Print the number of routes per destination directory and extension
+    /// rather than each route.
+    ///
+    /// Only applies to text output. Manifests with more than 1,000 routes are
+    /// otherwise truncated when printed as text.
+    #[structopt(long = "summary-only")]
+    summary_only: bool,
+    /// Layout of manifests printed as text.
+    ///
+    /// One of "flat" or "tree". When "flat", each route is printed with its
+    /// sources and destination. When "tree", routes are grouped beneath their
+    /// destination directory, which is printed once, and each route is printed
+    /// as its sources and the file name of its destination.
+    #[structopt(
+        long = "layout",
+        value_name = "layout",
+        default_value = "flat",
+        conflicts_with = "summary-only"
+    )]
+    layout: ManifestLayout,
+    /// Paths in manifests printed as text.
+    #[structopt(flatten)]
+    paths: PathOptionGroup,
+    /// Order in which routes are printed and written.
+    ///
+    /// One of "matched", "mtime", "name", "natural", or "size". When
+    /// "matched", routes are ordered as their sources are matched while
+    /// traversing the working directory tree. Otherwise, routes are ordered
+    /// by the modification time, path, natural path (such that `file2` is
+    /// ordered before `file10`), or size of their sources. Except for "size",
+    /// the working directory tree is also traversed in this order, which
+    /// determines the order of positions (see the `{!n}` property). Note that
+    /// the tree is traversed one directory at a time, so positions are ordered
+    /// by modification time within each directory for "mtime".
+    #[structopt(long = "sort", value_name = "key", default_value = "matched")]
+    sort: SortKey,
+    /// Print an event for each route as it is written.
+    ///
+    /// Must be "jsonl". Events are printed to standard output as JSON objects
+    /// on their own lines and include the sources and destination of the
+    /// route. Events are "planned" for each route once the manifest is final,
+    /// "started" and then "finished" or "failed" as each route is written, and
+    /// "skipped" for routes that are not written because another route
+    /// failed, and "changed" for routes that are not written because a source
+    /// changed (see `--verify-unchanged`). The progress bar is not shown when
+    /// events are printed. Use with `--quiet` to print only events to standard
+    /// output.
+    #[structopt(long = "events", value_name = "format")]
+    events: Option<EventFormat>,
+    /// Write the manifest and the outcome of each route to a file after
+    /// writing.
+    ///
+    /// The file is written as JSON and includes the operation and policy
+    /// along with each route and whether it was written, failed, skipped, or
+    /// changed. Failed routes include the error and changed routes include the
+    /// source that changed. Skipped routes were not written, because a
+    /// previous route failed. It is written even if a route fails, but not if
+    /// the manifest is never written, such as with `--dry-run`.
+    #[structopt(long = "manifest-out", value_name = "file", conflicts_with = "watch")]
+    manifest_out: Option<PathBuf>,
+    /// Stop writing routes once a route fails.
+    ///
+    /// Remaining routes are skipped. This is the default.
+    #[structopt(long = "fail-fast", overrides_with = "keep-going")]
+    fail_fast: bool,
+    /// Keep writing routes after a route fails.
+    ///
+    /// Every route is attempted and each failure is reported once all routes
+    /// have been written.
+    #[structopt(long = "keep-going", overrides_with = "fail-fast")]
+    keep_going: bool,
+    /// Skip routes with sources that change while the manifest is reviewed.
+    ///
+    /// The size and modification time of each source is recorded once the
+    /// manifest is final and is checked again immediately before its route is
+    /// written. Routes with sources that have changed are skipped and
+    /// reported, or fail if `--fail-fast` is given. It is an error if a
+    /// source cannot be read when it is recorded.
+    #[structopt(long = "verify-unchanged")]
+    verify_unchanged: bool,
 }
 
-#[derive(Debug, StructOpt)]
-#[structopt(rename_all = "kebab-case")]
-enum Command {
-    // TODO: This may not provide much utility and is the only many-to-one
-    //       transform. The order in which files are appended may be important
-    //       and the `find` command can be trivially used to do simple
+impl ActuationOptionGroup {
+    fn configure(&mut self, config: &Config) -> Result<(), OptionError> {
+        self.common.configure(config)?;
+        self.confirm_threshold = self.confirm_threshold.or(config.confirm_threshold);
+        Ok(())
+    }
+
+    fn confirm_threshold(&self) -> usize {
+        self.confirm_threshold.unwrap_or(DEFAULT_CONFIRM_THRESHOLD)
+    }
+
+    fn assumption(&self) -> Option<bool> {
+        assumption(self.assume_yes, self.assume_no)
+    }
+
+    fn keep_going(&self) -> bool {
+        // These flags override each other, so at most one is set.
+        self.keep_going && !self.fail_fast
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct TransformOptionGroup {
+    #[structopt(flatten)]
+    actuation: ActuationOptionGroup,
+    #[structopt(flatten)]
+    traversal: TraversalOptionGroup,
+    #[structopt(flatten)]
+    policy: PolicyOptionGroup,
+    /// Keep running and transform newly matched files as they appear.
+    ///
+    /// Matched files are transformed as usual and then the working directory
+    /// tree is watched for changes. Each batch of newly matched files is
+    /// printed and prompted for like any other manifest. Files that have
+    /// already been transformed (and their destinations) are not matched
+    /// again.
+    #[structopt(long = "watch")]
+    watch: bool,
+}
+
+impl TransformOptionGroup {
+    fn configure(&mut self, config: &Config) -> Result<(), OptionError> {
+        self.actuation.configure(config)?;
+        self.traversal.configure(config);
+        self.policy.configure(config);
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Command {
+    // TODO: This may not provide much utility and is the only many-to-one
+    //       transform. The order in which files are appended may be important
+    //       and the `find` command can be trivially used to do simple
     //       archiving. Consider removing this feature and simplifying
     //       manifests.
     /// Appends matched files.
+    #[allow(dead_code)]
     Append {
         #[structopt(flatten)]
         transform: UnparsedTransform,
         #[structopt(flatten)]
         options: TransformOptionGroup,
     },
+    /// Applies a plan written by the `plan` command or a manifest.
+    ///
+    /// The policy of the plan (such as whether or not to overwrite files) is
+    /// used. Plans are rejected if any source file has changed since the plan
+    /// was written.
+    ///
+    /// Manifests are read via `--from-manifest` and are written as JSON by
+    /// transforms with `--output=json` (or by other tools in the same format).
+    /// Manifests do not include an operation nor policy, so these are given
+    /// by options.
+    Apply {
+        /// Path to the plan.
+        #[structopt(required_unless = "from-manifest")]
+        manifest: Option<PathBuf>,
+        /// Path to a manifest to apply rather than a plan.
+        #[structopt(
+            long = "from-manifest",
+            value_name = "path",
+            conflicts_with = "manifest",
+            requires = "operation"
+        )]
+        from_manifest: Option<PathBuf>,
+        /// The operation to apply to a manifest.
+        ///
+        /// One of "copy", "hard-link", "hard-link-or-copy", "move", or
+        /// "soft-link". When "hard-link-or-copy", files are copied if they
+        /// cannot be hard linked, because their destination is on another file
+        /// system.
+        #[structopt(
+            long = "operation",
+            value_name = "operation",
+            requires = "from-manifest"
+        )]
+        operation: Option<OperationKind>,
+        #[structopt(flatten)]
+        options: ActuationOptionGroup,
+        /// Policy options only apply to manifests. Plans use their own policy.
+        #[structopt(flatten)]
+        policy: PolicyOptionGroup,
+    },
+    /// Prints a completion script for a shell.
+    ///
+    /// For example, to install completions for Bash, write the output of
+    /// `nym completions bash` to a file in the `bash-completion` directory.
+    Completions {
+        /// The target shell.
+        ///
+        /// One of "bash", "elvish", "fish", "powershell", or "zsh".
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        shell: Shell,
+    },
+    /// Prints a report of the detected environment.
+    ///
+    /// The report includes the availability of pagers, color and terminal
+    /// support, the capabilities of the filesystem of the working directory
+    /// tree (such as case sensitivity and support for links), and the
+    /// location of configuration. This is useful when reporting bugs.
+    Doctor {
+        /// Working directory tree.
+        #[structopt(long = "tree", short = "C", default_value = ".", env = "NYM_TREE")]
+        directory: PathBuf,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+    },
+    /// Deletes matched files.
+    Delete {
+        /// The from-pattern used to match files.
+        from: String,
+        /// Move files to the trash rather than deleting them.
+        #[structopt(long = "trash")]
+        trash: bool,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+        /// Determines if and when interactive prompts are used.
+        ///
+        /// One of "always", "never", or "automatic" (or its abbreviation
+        /// "auto"). When "automatic", prompts are used if standard error is
+        /// attached to an attended terminal (not piped, redirected, etc.).
+        #[structopt(long = "interactive", value_name = "when", default_value = "always")]
+        interactive: Toggle,
+        /// Delete without prompting, regardless of `--interactive`.
+        #[structopt(long = "assume-yes", short = "y", conflicts_with = "assume-no")]
+        assume_yes: bool,
+        /// Abort without prompting after printing matched files, regardless
+        /// of `--interactive`.
+        #[structopt(long = "assume-no", conflicts_with = "assume-yes")]
+        assume_no: bool,
+        /// Do not print matched files nor warnings.
+        #[structopt(long = "quiet", short = "q")]
+        quiet: bool,
+        /// Print matched files and exit without prompting nor deleting.
+        #[structopt(long = "dry-run", short = "n")]
+        dry_run: bool,
+        /// Number of files above which deletion must be confirmed by typing.
+        ///
+        /// When more files than this are matched, the prompt requires typing
+        /// the number of files (or "yes") rather than answering yes or no.
+        /// Defaults to 100.
+        #[structopt(long = "confirm-threshold", value_name = "count")]
+        confirm_threshold: Option<usize>,
+    },
+    /// Finds duplicate files among matched files.
+    ///
+    /// Matched files are compared by size and then by their BLAKE3 digests
+    /// and each group of identical files is printed. Empty files are never
+    /// considered duplicates. Within each group, the first file in path
+    /// order is kept by `--link` and `--delete`.
+    Dupes {
+        /// The from-pattern used to match files.
+        from: String,
+        /// Replace duplicates with hard links to the first file in their
+        /// group.
+        ///
+        /// Duplicates that are already hard links to that file are not
+        /// replaced.
+        #[structopt(long = "link", conflicts_with = "delete")]
+        link: bool,
+        /// Delete duplicates, keeping the first file in their group.
+        #[structopt(long = "delete")]
+        delete: bool,
+        /// Move duplicates to the trash rather than deleting them.
+        #[structopt(long = "trash", requires = "delete")]
+        trash: bool,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        #[structopt(flatten)]
+        paths: PathOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+        /// Determines if and when interactive prompts are used.
+        ///
+        /// One of "always", "never", or "automatic" (or its abbreviation
+        /// "auto"). When "automatic", prompts are used if standard error is
+        /// attached to an attended terminal (not piped, redirected, etc.).
+        #[structopt(long = "interactive", value_name = "when", default_value = "always")]
+        interactive: Toggle,
+        /// Link or delete without prompting, regardless of `--interactive`.
+        #[structopt(long = "assume-yes", short = "y", conflicts_with = "assume-no")]
+        assume_yes: bool,
+        /// Abort without prompting after printing duplicates, regardless of
+        /// `--interactive`.
+        #[structopt(long = "assume-no", conflicts_with = "assume-yes")]
+        assume_no: bool,
+        /// Do not print duplicates nor warnings.
+        #[structopt(long = "quiet", short = "q")]
+        quiet: bool,
+        /// Print duplicates and exit without prompting nor writing.
+        #[structopt(long = "dry-run", short = "n")]
+        dry_run: bool,
+        /// Number of files above which linking or deletion must be confirmed
+        /// by typing.
+        ///
+        /// Defaults to 100.
+        #[structopt(long = "confirm-threshold", value_name = "count")]
+        confirm_threshold: Option<usize>,
+    },
+    /// Explains how patterns are parsed and matched.
+    ///
+    /// Prints the literal path prefix, token tree, and regular expression of
+    /// a from-pattern, how its captures are numbered, and the rules that
+    /// reject variants of it. If a to-pattern is given, then the captures that
+    /// it uses are checked against the from-pattern. This is useful when a
+    /// pattern does not match or is rejected unexpectedly.
+    Explain {
+        /// The from-pattern to explain.
+        from: String,
+        /// The to-pattern to explain.
+        to: Option<String>,
+    },
     /// Copies matched files.
     Copy {
         #[structopt(flatten)]
         transform: UnparsedTransform,
         #[structopt(flatten)]
         options: TransformOptionGroup,
+        /// Merge files into existing directories resolved by to-patterns.
+        ///
+        /// If a to-pattern resolves to an existing directory, then matched
+        /// files are copied into that directory with their file names rather
+        /// than rejected. The overwrite mode applies to files in the directory
+        /// as usual.
+        #[structopt(long = "merge")]
+        merge: bool,
     },
     /// Finds matched files.
     Find {
         /// The from-pattern used to match files.
         from: String,
+        /// Terminate paths with a null character rather than a newline.
+        ///
+        /// Paths are written as is without color or style. This is useful when
+        /// piping into tools like `xargs -0`, because paths may contain
+        /// newlines and other whitespace.
+        #[structopt(long = "print0", short = "0", conflicts_with = "json")]
+        print0: bool,
+        /// Print each match as a JSON object on its own line.
+        ///
+        /// Objects include the path, depth, and file type of the match as well
+        /// as its captures. Captures are ordered by index and captures that
+        /// do not participate in the match are `null`.
+        #[structopt(long = "json")]
+        json: bool,
+        /// Print the file type, size, and modification time of each match
+        /// alongside its path.
+        #[structopt(long = "long", short = "l", conflicts_with_all = &["print0", "json"])]
+        long: bool,
+        /// Print each path quoted for a shell.
+        ///
+        /// One of "posix" or "powershell". Defaults to "posix". Paths are
+        /// quoted and escaped such that each is a single word when pasted into
+        /// a command in that shell, even if it contains whitespace, quotes, or
+        /// other special characters.
+        #[structopt(
+            long = "print-shell",
+            value_name = "shell",
+            require_equals = true,
+            conflicts_with_all = &["print0", "json", "long"]
+        )]
+        print_shell: Option<Option<Quoting>>,
+        /// Print only the number of matches rather than their paths.
+        ///
+        /// This skips printing paths entirely, which is much faster for large
+        /// directory trees.
+        #[structopt(
+            long = "count",
+            short = "c",
+            conflicts_with_all = &["print0", "json", "long", "print-shell"]
+        )]
+        count: bool,
+        /// Print the number of matches in each top-level directory.
+        ///
+        /// Counts are printed alongside each directory in the working
+        /// directory tree that contains matches. Files within the working
+        /// directory are counted against the working directory itself.
+        #[structopt(long = "by-directory", requires = "count")]
+        by_directory: bool,
         #[structopt(flatten)]
         options: CommonOptionGroup,
+        #[structopt(flatten)]
+        paths: PathOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
     },
     /// Links matched files.
     Link {
@@ -192,18 +1690,239 @@ enum Command {
         #[structopt(flatten)]
         options: TransformOptionGroup,
     },
+    /// Writes a plan of a transform without writing to matched files.
+    ///
+    /// Plans are written as JSON and can be executed later via the `apply`
+    /// command.
+    Plan {
+        /// The operation to plan.
+        ///
+        /// One of "copy", "hard-link", "hard-link-or-copy", "move", or
+        /// "soft-link". When "hard-link-or-copy", files are copied if they
+        /// cannot be hard linked, because their destination is on another file
+        /// system.
+        operation: OperationKind,
+        #[structopt(flatten)]
+        transform: UnparsedTransform,
+        /// Path to which the plan is written.
+        ///
+        /// If not given, the plan is written to standard output.
+        #[structopt(long = "output", short = "o", value_name = "path")]
+        output: Option<PathBuf>,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+        #[structopt(flatten)]
+        policy: PolicyOptionGroup,
+    },
+    /// Prints the destinations of matched files without writing them.
+    ///
+    /// The first matched files are resolved through the to-pattern and each
+    /// source is printed with its destination. Unlike `--dry-run`, no
+    /// manifest is built and no policy is checked, so this is fast and useful
+    /// when developing patterns.
+    Preview {
+        /// The from-pattern used to match files.
+        from: String,
+        /// The to-pattern used to resolve destinations.
+        to: String,
+        /// Maximum number of matched files to resolve.
+        #[structopt(long = "count", short = "n", default_value = "10")]
+        count: usize,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+    },
+    /// Prints properties of matched files.
+    ///
+    /// The format is a to-pattern that is resolved and printed for each
+    /// matched file. For example, `nym stat '**/*.iso' '{!b3sum:[16]}  {#0}'`
+    /// prints the first sixteen characters of the BLAKE3 digest followed by
+    /// the path of each matched ISO file.
+    Stat {
+        /// The from-pattern used to match files.
+        from: String,
+        /// The to-pattern used to format each matched file.
+        format: String,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+    },
+    /// Selects and edits the routes of a transform interactively.
+    ///
+    /// Routes are presented in a scrollable list. Selecting a route toggles it
+    /// or edits its destination and the remaining routes are written once the
+    /// list is applied.
+    Tui {
+        /// The operation to apply.
+        ///
+        /// One of "copy", "hard-link", "hard-link-or-copy", "move", or
+        /// "soft-link". When "hard-link-or-copy", files are copied if they
+        /// cannot be hard linked, because their destination is on another file
+        /// system.
+        #[structopt(long = "operation", value_name = "operation", default_value = "move")]
+        operation: OperationKind,
+        #[structopt(flatten)]
+        transform: UnparsedTransform,
+        #[structopt(flatten)]
+        actuation: ActuationOptionGroup,
+        #[structopt(flatten)]
+        traversal: TraversalOptionGroup,
+        #[structopt(flatten)]
+        policy: PolicyOptionGroup,
+    },
+    /// Creates empty files named by a to-pattern.
+    ///
+    /// The to-pattern is resolved once per file with no source. The `{!n}`
+    /// property outputs the one-based position of each file, so that
+    /// `chapter-{!n|>2[0]}.md` creates `chapter-01.md`, `chapter-02.md`, etc.
+    Touch {
+        /// The to-pattern used to resolve created paths.
+        to: String,
+        /// Number of files to create.
+        #[structopt(long = "count", short = "c", default_value = "1")]
+        count: usize,
+        /// Working directory tree.
+        #[structopt(long = "tree", short = "C", default_value = ".", env = "NYM_TREE")]
+        directory: PathBuf,
+        /// Create directories rather than files.
+        #[structopt(long = "directories", short = "D")]
+        directories: bool,
+        /// Create parent directories for resolved paths.
+        #[structopt(long = "parents", short = "p")]
+        parents: bool,
+        #[structopt(flatten)]
+        options: CommonOptionGroup,
+        /// Do not print resolved paths.
+        #[structopt(long = "quiet", short = "q")]
+        quiet: bool,
+        /// Print resolved paths and exit without creating them.
+        #[structopt(long = "dry-run", short = "n")]
+        dry_run: bool,
+    },
 }
 
 impl Command {
-    fn common_option_group(&self) -> &CommonOptionGroup {
+    fn configure(&mut self, config: &Config) -> Result<(), OptionError> {
+        match self {
+            Command::Append {
+                ref mut options, ..
+            }
+            | Command::Copy {
+                ref mut options, ..
+            }
+            | Command::Move {
+                ref mut options, ..
+            } => options.configure(config),
+            Command::Apply {
+                ref mut options,
+                ref mut policy,
+                ..
+            } => {
+                options.configure(config)?;
+                policy.configure(config);
+                Ok(())
+            }
+            Command::Completions { .. } | Command::Explain { .. } => Ok(()),
+            Command::Delete {
+                ref mut options,
+                ref mut traversal,
+                ref mut confirm_threshold,
+                ..
+            }
+            | Command::Dupes {
+                ref mut options,
+                ref mut traversal,
+                ref mut confirm_threshold,
+                ..
+            } => {
+                options.configure(config)?;
+                traversal.configure(config);
+                *confirm_threshold = confirm_threshold.or(config.confirm_threshold);
+                Ok(())
+            }
+            Command::Find {
+                ref mut options,
+                ref mut traversal,
+                ..
+            }
+            | Command::Preview {
+                ref mut options,
+                ref mut traversal,
+                ..
+            }
+            | Command::Stat {
+                ref mut options,
+                ref mut traversal,
+                ..
+            } => {
+                options.configure(config)?;
+                traversal.configure(config);
+                Ok(())
+            }
+            Command::Link { ref mut link, .. } => match link {
+                Link::Hard {
+                    ref mut options, ..
+                }
+                | Link::Soft {
+                    ref mut options, ..
+                } => options.configure(config),
+            },
+            Command::Plan {
+                ref mut options,
+                ref mut traversal,
+                ref mut policy,
+                ..
+            } => {
+                options.configure(config)?;
+                traversal.configure(config);
+                policy.configure(config);
+                Ok(())
+            }
+            Command::Doctor {
+                ref mut options, ..
+            }
+            | Command::Touch {
+                ref mut options, ..
+            } => options.configure(config),
+            Command::Tui {
+                ref mut actuation,
+                ref mut traversal,
+                ref mut policy,
+                ..
+            } => {
+                actuation.configure(config)?;
+                traversal.configure(config);
+                policy.configure(config);
+                Ok(())
+            }
+        }
+    }
+
+    fn common_option_group(&self) -> Option<&CommonOptionGroup> {
         match self {
             Command::Append { ref options, .. }
             | Command::Copy { ref options, .. }
-            | Command::Move { ref options, .. } => &options.common,
+            | Command::Move { ref options, .. } => Some(&options.actuation.common),
+            Command::Apply { ref options, .. } => Some(&options.common),
+            Command::Completions { .. } | Command::Explain { .. } => None,
+            Command::Tui { ref actuation, .. } => Some(&actuation.common),
             Command::Link { ref link, .. } => match link {
-                Link::Hard { ref options, .. } | Link::Soft { ref options, .. } => &options.common,
+                Link::Hard { ref options, .. } | Link::Soft { ref options, .. } => {
+                    Some(&options.actuation.common)
+                }
             },
-            Command::Find { ref options, .. } => options,
+            Command::Delete { ref options, .. }
+            | Command::Doctor { ref options, .. }
+            | Command::Dupes { ref options, .. }
+            | Command::Find { ref options, .. }
+            | Command::Plan { ref options, .. }
+            | Command::Preview { ref options, .. }
+            | Command::Stat { ref options, .. }
+            | Command::Touch { ref options, .. } => Some(options),
         }
     }
 }
@@ -217,6 +1936,12 @@ enum Link {
         transform: UnparsedTransform,
         #[structopt(flatten)]
         options: TransformOptionGroup,
+        /// Copy files that cannot be hard linked, because their destination is
+        /// on a different file system than their source.
+        ///
+        /// Copied files are listed after writing.
+        #[structopt(long = "or-copy")]
+        or_copy: bool,
     },
     /// Symbolically links matched files.
     Soft {
@@ -235,70 +1960,1158 @@ struct UnparsedTransform {
     from: String,
     /// The to-pattern used to resolve destination files.
     to: String,
+    /// Additional from-patterns used to match source files. May be repeated.
+    ///
+    /// Files matching any from-pattern are routed. Captures are those of the
+    /// first from-pattern that matches a file, beginning with the positional
+    /// from-pattern, so from-patterns should agree on the meaning of their
+    /// captures. For example, `copy '*.flac' --from='*.mp3' 'music/{#1}'`
+    /// routes both FLAC and MP3 files. Cannot be used with `--regex`.
+    #[structopt(
+        long = "from",
+        value_name = "pattern",
+        number_of_values = 1,
+        conflicts_with = "regex"
+    )]
+    froms: Vec<String>,
+    /// Extensions of companion files routed along with matched files.
+    ///
+    /// Companion (sidecar) files share the path of a matched file except for
+    /// their extension and are routed to the destination of the matched file
+    /// with the extension replaced. For example, `--sidecar=srt,xmp` routes
+    /// `movie.srt` along with `movie.mkv`.
+    #[structopt(
+        long = "sidecar",
+        value_name = "extensions",
+        use_delimiter = true,
+        number_of_values = 1
+    )]
+    sidecars: Vec<String>,
+    /// Preserve the directory structure of matched files in destinations.
+    ///
+    /// The file name of each destination is prefixed with the directory of
+    /// its source relative to the working directory tree, as if by the
+    /// `{!dirs}` property. For example, `--relative '**/*.jpg' 'backup/{#2}.jpg'`
+    /// copies `a/b/c.jpg` to `backup/a/b/c.jpg`.
+    #[structopt(long = "relative")]
+    relative: bool,
+    /// Route matched files into the directory resolved by the to-pattern.
+    ///
+    /// The destination of each file is its file name in that directory, so
+    /// deep trees are collapsed into a single directory. Names that collide
+    /// with other destinations or existing files are suffixed with an
+    /// incrementing number, such as `photo-2.jpg`. For example, `move
+    /// --flatten '**/*.jpg' photos` moves all JPEG files into `photos`.
+    #[structopt(long = "flatten", conflicts_with = "relative")]
+    flatten: bool,
 }
 
 impl UnparsedTransform {
-    fn parse(&self) -> Result<(FromPattern<'_>, ToPattern<'_>), Error> {
-        let from = parse_from_pattern(&self.from)?;
-        let to = ToPattern::new(&self.to)?;
+    fn build<'e, 'f, 't>(
+        &self,
+        environment: &'e Environment,
+        from: FromPattern<'f>,
+        to: ToPattern<'t>,
+        traversal: &TraversalOptionGroup,
+        sort: SortKey,
+    ) -> TransformBuilder<'e, 'f, 't> {
+        let transform = traversal
+            .configure_builder(environment.transform_builder(from, to))
+            .with_sidecars(&self.sidecars)
+            .order(sort.walk_order());
+        if self.relative {
+            transform.relative()
+        }
+        else if self.flatten {
+            transform.flatten().collision(Collision::Number)
+        }
+        else {
+            transform
+        }
+    }
+
+    fn parse<'t>(
+        &'t self,
+        traversal: &'t TraversalOptionGroup,
+    ) -> Result<(FromPattern<'t>, ToPattern<'t>), Error> {
+        let from = if self.froms.is_empty() {
+            parse_from_pattern(&self.from, traversal)?
+        }
+        else {
+            parse_from_patterns(Some(&self.from).into_iter().chain(&self.froms), traversal)?
+        };
+        let to = parse_to_pattern(&self.to, &from)?;
         Ok((from, to))
     }
 }
 
-fn parse_from_pattern(text: &str) -> Result<FromPattern, Error> {
-    let parts = Glob::partitioned(text)?;
+fn parse_from_pattern<'t>(
+    text: &'t str,
+    traversal: &'t TraversalOptionGroup,
+) -> Result<FromPattern<'t>, Error> {
+    let from = if traversal.regex {
+        let from =
+            FromPattern::from_regex(text).map_err(|error| PatternTextError::new(text, error))?;
+        debug!(pattern = %text, "parsed from-pattern");
+        from
+    }
+    else {
+        FromPattern::from(parse_glob(text)?)
+    };
+    configure_from_pattern(from, traversal)
+}
+
+/// Parses globs into a from-pattern that matches files matching any glob.
+fn parse_from_patterns<'t>(
+    texts: impl IntoIterator<Item = &'t String>,
+    traversal: &'t TraversalOptionGroup,
+) -> Result<FromPattern<'t>, Error> {
+    let globs = texts
+        .into_iter()
+        .map(|text| parse_glob(text))
+        .collect::<Result<Vec<_>, _>>()?;
+    configure_from_pattern(FromPattern::from(GlobSet::partitioned(globs)), traversal)
+}
+
+fn parse_glob(text: &str) -> Result<(PathBuf, Glob<'_>), Error> {
+    let parts = Glob::partitioned(text).map_err(|error| PatternTextError::new(text, error))?;
     if parts.1.has_semantic_literals() {
         terminal::warning(
             "from-pattern has semantic literal components that likely match no paths; avoid \
              semantic components like `..` after wildcards and other variant tokens.",
         )?;
     }
-    Ok(parts.into())
+    debug!(pattern = %text, "parsed from-pattern");
+    Ok(parts)
 }
 
-fn actuate<A>(
-    options: &mut TransformOptionGroup,
+fn configure_from_pattern<'t>(
+    from: FromPattern<'t>,
+    traversal: &'t TraversalOptionGroup,
+) -> Result<FromPattern<'t>, Error> {
+    let from = if traversal.case_insensitive {
+        from.case_insensitive()?
+    }
+    else {
+        from
+    };
+    Ok(from
+        .with_kind(traversal.file_type.into())
+        .with_excludes(traversal.exclude.iter().map(String::as_str))?)
+}
+
+fn parse_to_pattern<'t>(text: &'t str, from: &FromPattern) -> Result<ToPattern<'t>, Error> {
+    let to = ToPattern::new(text)
+        .and_then(|to| {
+            if to
+                .capture_names()
+                .all(|name| from.capture_index(name).is_some())
+            {
+                to.with_named_captures(|name| from.capture_index(name))
+            }
+            else if to.capture_names().all(|name| from.has_capture(name)) {
+                // The globs of a set may disagree on the index of a named
+                // capture, in which case it is resolved by name when matched.
+                Ok(to)
+            }
+            else {
+                Err(PatternError::CaptureNotFound)
+            }
+        })
+        .map_err(|error| PatternTextError::new(text, error))?;
+    debug!(pattern = %text, "parsed to-pattern");
+    Ok(to)
+}
+
+/// Collects warnings while reading a manifest, so that they can be printed
+/// afterward.
+#[derive(Default)]
+struct WarningReporter(RefCell<Vec<String>>);
+
+impl Reporter for WarningReporter {
+    fn warned(&self, warning: Warning<'_>) {
+        if let Warning::LinkLoop(path) = warning {
+            self.0
+                .borrow_mut()
+                .push(GlobError::LinkLoop(path.to_path_buf()).to_string());
+        }
+    }
+}
+
+fn with_digest_cache<'t>(to: ToPattern<'t>, digests: Option<&Arc<DigestCache>>) -> ToPattern<'t> {
+    match digests {
+        Some(digests) => to.with_digest_cache(digests.clone()),
+        None => to,
+    }
+}
+
+fn read<A>(
+    environment: &Environment,
     transform: &UnparsedTransform,
-) -> Result<(), Error>
+    traversal: &TraversalOptionGroup,
+    sort: SortKey,
+) -> Result<Manifest<A::Routing>, Error>
 where
-    A: Label + Operation,
+    A: Operation,
 {
-    let environment = Environment::new(Policy {
-        parents: options.parents,
-        overwrite: options.overwrite,
-    });
-    let (from, to) = transform.parse()?;
+    let (from, to) = transform.parse(traversal)?;
+    let digests = traversal.read_digest_cache()?;
+    let to = with_digest_cache(to, digests.as_ref());
+    let transform = transform.build(environment, from, to, traversal, sort);
+    let warnings = WarningReporter::default();
+    let manifest = transform.read_with_reporter(&traversal.directory, &warnings)?;
+    for warning in warnings.0.into_inner() {
+        terminal::warning(warning)?;
+    }
+    traversal.write_digest_cache(digests.as_deref())?;
+    Ok(manifest)
+}
 
-    let transform = environment.transform(from, to);
+fn execute<A>(
+    options: &mut ActuationOptionGroup,
+    environment: &Environment,
+    directory: &Path,
+    manifest: Manifest<A::Routing>,
+) -> Result<Exit, Error>
+where
+    A: Label + Operation,
+{
+    if manifest.routes().len() == 0 {
+        return Ok(Exit::NoMatches);
+    }
+    let manifest = if options.edit {
+        let manifest = edit::edit(&manifest)?;
+        environment.verify(&manifest)?;
+        manifest
+    }
+    else {
+        manifest
+    };
+    let manifest = &sort(manifest, options.sort)?;
+    // Sources are recorded before the manifest is printed and confirmed, so
+    // that changes made while it is reviewed are detected when writing.
+    let snapshots: Option<Vec<Vec<_>>> = options
+        .verify_unchanged
+        .then(|| {
+            manifest
+                .routes()
+                .map(|route| route.sources().map(PlannedSource::read).collect())
+                .collect::<io::Result<_>>()
+        })
+        .transpose()?;
     let actuator = environment.actuator();
-    let manifest: Manifest<A::Routing> =
-        transform.read(&options.common.directory, options.common.depth + 1)?;
-
     if !options.quiet {
-        Terminal::with_output_process_scoped(
-            &mut options.common.pager,
-            options.common.paging,
-            |mut output| manifest.print(&mut output),
-        )?;
+        match options.output {
+            OutputFormat::Json => {
+                terminal::print_manifest_json(manifest, &mut io::stdout().lock())?;
+            }
+            OutputFormat::Text => {
+                let paging = options.common.paging;
+                let summary_only = options.summary_only;
+                let layout = options.layout;
+                let form = options.paths.form(directory);
+                Terminal::with_output_process_scoped(
+                    options.common.pager(),
+                    paging,
+                    |mut output| {
+                        if summary_only {
+                            terminal::print_manifest_summary(manifest, &form, &mut output)
+                        }
+                        else if let ManifestLayout::Tree = layout {
+                            terminal::print_manifest_tree(
+                                manifest,
+                                MANIFEST_PRINT_LIMIT,
+                                &form,
+                                &mut output,
+                            )
+                        }
+                        else {
+                            terminal::print_manifest(
+                                manifest,
+                                MANIFEST_PRINT_LIMIT,
+                                &form,
+                                &mut output,
+                            )
+                        }
+                    },
+                )?;
+                if !summary_only && manifest.routes().len() > MANIFEST_PRINT_LIMIT {
+                    terminal::warning(format!(
+                        "printed {} of {} routes; use `--summary-only` to print the number of \
+                         routes per directory or `--output json` to print every route.",
+                        MANIFEST_PRINT_LIMIT,
+                        manifest.routes().len(),
+                    ))?;
+                }
+            }
+        }
         terminal::warning(
             "paths may be ambiguous and undetected collisions may cause overwriting, truncation, \
              and data loss; review patterns and paths carefully.",
         )?;
     }
-    if !terminal::is_interactive(options.interactive)
-        || terminal::confirm(format!(
-            "Ready to {} into {} files. Continue?",
+    let is_event_printed = options.events.is_some();
+    let event = |event: Event<'_>, route: &Route<A::Routing, &Path>| {
+        if is_event_printed {
+            terminal::print_event_json(event, route, &mut io::stdout().lock())
+        }
+        else {
+            Ok(())
+        }
+    };
+    for route in manifest.routes() {
+        event(Event::Planned, &route)?;
+    }
+    if options.dry_run {
+        return Ok(Exit::Success);
+    }
+    if !confirm(
+        options.interactive,
+        options.assumption(),
+        format!(
+            "Ready to {} into {} files.",
             A::LABEL,
-            manifest.routes().len(),
-        ))?
-    {
-        for route in manifest.routes().printed() {
-            actuator.write::<A, _>(route)?;
+            manifest.routes().len()
+        ),
+        manifest.routes().len(),
+        A::IS_DESTRUCTIVE.then(|| options.confirm_threshold()),
+    )? {
+        return Ok(Exit::Aborted);
+    }
+    let start = Instant::now();
+    let written = AtomicUsize::new(0);
+    let bytes = AtomicU64::new(0);
+    let routes: Vec<_> = manifest
+        .routes()
+        .map(|route| {
+            let len: u64 = route
+                .sources()
+                .flat_map(fs::metadata)
+                .map(|metadata| metadata.len())
+                .sum();
+            (route, len)
+        })
+        .collect();
+    // Progress is measured in bytes for operations that write file data and
+    // in routes otherwise.
+    let progress = if is_event_printed {
+        ProgressBar::hidden()
+    }
+    else if A::IS_DATA_WRITTEN {
+        terminal::progress_bytes(routes.iter().map(|(_, len)| len).sum())
+    }
+    else {
+        terminal::progress(routes.len())
+    };
+    let is_failed = AtomicBool::new(false);
+    let outcomes = options
+        .manifest_out
+        .as_ref()
+        .map(|_| Mutex::new(vec![Outcome::Skipped; routes.len()]));
+    let record = |index: usize, outcome: Outcome| {
+        if let Some(ref outcomes) = outcomes {
+            outcomes.lock().expect("failed to lock outcomes")[index] = outcome;
+        }
+    };
+    let copies = Mutex::new(Vec::new());
+    let changes = Mutex::new(Vec::new());
+    let fail_fast = options.fail_fast;
+    let write_route = |index: usize, (route, len): (Route<A::Routing, _>, u64)| {
+        if let Some(source) = snapshots
+            .as_ref()
+            .and_then(|snapshots| snapshots[index].iter().find(|source| source.is_stale()))
+        {
+            if fail_fast {
+                let error = io::Error::other(format!(
+                    "source changed after manifest was read: `{}`",
+                    source.path().display(),
+                ));
+                record(
+                    index,
+                    Outcome::Failed {
+                        error: error.to_string(),
+                    },
+                );
+                return event(Event::Failed { error: &error }, &route).and(Err(error));
+            }
+            changes
+                .lock()
+                .expect("failed to lock changed sources")
+                .push(source.path().to_path_buf());
+            progress.inc(if A::IS_DATA_WRITTEN { len } else { 1 });
+            record(
+                index,
+                Outcome::Changed {
+                    source: source.path().to_path_buf(),
+                },
+            );
+            return event(Event::Changed, &route);
+        }
+        event(Event::Started, &route)?;
+        // File data is only written in chunks when progress is drawn, because
+        // otherwise platform features like copy-on-write can be used.
+        let result = if A::IS_DATA_WRITTEN && !progress.is_hidden() {
+            actuator.write_with_progress::<A, _, _>(route.clone(), |n| progress.inc(n))
+        }
+        else {
+            actuator
+                .write::<A, _>(route.clone())
+                .map(|_| progress.inc(if A::IS_DATA_WRITTEN { len } else { 1 }))
+        };
+        match result {
+            Ok(_) => {
+                if A::IS_COPY_FALLBACK && !is_linked(&route) {
+                    copies
+                        .lock()
+                        .expect("failed to lock copied routes")
+                        .push(AsRef::<Path>::as_ref(route.destination()).to_path_buf());
+                }
+                record(index, Outcome::Written { bytes: len });
+                event(Event::Finished { bytes: len }, &route)
+            }
+            Err(error) => {
+                record(
+                    index,
+                    Outcome::Failed {
+                        error: error.to_string(),
+                    },
+                );
+                event(Event::Failed { error: &error }, &route).and(Err(error))
+            }
+        }?;
+        written.fetch_add(1, Ordering::Relaxed);
+        bytes.fetch_add(len, Ordering::Relaxed);
+        Ok(())
+    };
+    // Once a route has failed, remaining routes are skipped rather than
+    // interrupting iteration, so that events are printed for every route.
+    // Routes may still be written concurrently when a route fails. Routes are
+    // never skipped when keeping going.
+    let keep_going = options.keep_going();
+    let len = routes.len();
+    let failures = Mutex::new(Vec::new());
+    let write = |(index, (route, len)): (usize, (Route<A::Routing, _>, u64))| {
+        let destination = AsRef::<Path>::as_ref(route.destination()).to_path_buf();
+        let result = if is_failed.load(Ordering::Relaxed) {
+            event(Event::Skipped, &route)
+        }
+        else {
+            write_route(index, (route, len))
+        };
+        if let Err(error) = result {
+            if !keep_going {
+                is_failed.store(true, Ordering::Relaxed);
+            }
+            failures
+                .lock()
+                .expect("failed to lock actuation errors")
+                .push(RouteError(destination, error));
+        }
+    };
+    if options.threads > 1 {
+        ThreadPoolBuilder::new()
+            .num_threads(options.threads)
+            .build()?
+            .install(|| routes.into_par_iter().enumerate().for_each(write));
+    }
+    else {
+        routes.into_iter().enumerate().for_each(write);
+    }
+    let failures = failures
+        .into_inner()
+        .expect("failed to lock actuation errors");
+    let failed = failures.len();
+    let result: Result<(), Error> = if failures.is_empty() {
+        Ok(())
+    }
+    else if keep_going {
+        Err(ActuationFailures {
+            errors: failures,
+            len,
+        }
+        .into())
+    }
+    else {
+        let RouteError(_, error) = failures.into_iter().next().unwrap();
+        Err(ActuationError(error).into())
+    };
+    progress.finish();
+    if let (Some(path), Some(outcomes)) = (options.manifest_out.as_ref(), outcomes) {
+        let outcomes = outcomes.into_inner().expect("failed to lock outcomes");
+        Journal::from_outcomes(
+            A::KIND,
+            environment.policy(),
+            manifest.routes().zip(outcomes),
+        )?
+        .write(File::create(path)?)?;
+    }
+    if !options.quiet {
+        let copies = copies.into_inner().expect("failed to lock copied routes");
+        terminal::print_summary(Summary {
+            label: A::PAST_LABEL,
+            written: written.into_inner(),
+            failed,
+            copied: copies.len(),
+            bytes: bytes.into_inner(),
+            elapsed: start.elapsed(),
+        })?;
+        terminal::print_copies(&copies)?;
+        for source in changes
+            .into_inner()
+            .expect("failed to lock changed sources")
+        {
+            terminal::warning(format!(
+                "skipped route; source changed after manifest was read: `{}`",
+                source.display(),
+            ))?;
+        }
+    }
+    result?;
+    Ok(Exit::Success)
+}
+
+fn actuate<A>(
+    options: &mut TransformOptionGroup,
+    transform: &UnparsedTransform,
+) -> Result<Exit, Error>
+where
+    A: Label + Operation,
+{
+    let environment = Environment::new(options.policy.policy());
+    if options.watch {
+        return watch::<A>(options, &environment, transform);
+    }
+    let manifest = read::<A>(
+        &environment,
+        transform,
+        &options.traversal,
+        options.actuation.sort,
+    )?;
+    execute::<A>(
+        &mut options.actuation,
+        &environment,
+        &options.traversal.directory,
+        manifest,
+    )
+}
+
+fn watch<A>(
+    options: &mut TransformOptionGroup,
+    environment: &Environment,
+    transform: &UnparsedTransform,
+) -> Result<Exit, Error>
+where
+    A: Label + Operation,
+{
+    let TransformOptionGroup {
+        ref mut actuation,
+        ref traversal,
+        ..
+    } = *options;
+    let (from, to) = transform.parse(traversal)?;
+    let transform = transform.build(environment, from, to, traversal, actuation.sort);
+    let manifests = transform.watch::<A::Routing>(&traversal.directory, WATCH_DELAY)?;
+    for manifest in manifests {
+        // Errors in a batch do not stop watching. Batches that are declined
+        // are not offered again.
+        let result = manifest.map_err(Error::from).and_then(|manifest| {
+            execute::<A>(actuation, environment, &traversal.directory, manifest)
+        });
+        if let Err(error) = result {
+            terminal::warning(error.to_string())?;
+        }
+    }
+    Ok(Exit::Success)
+}
+
+fn tui<A>(
+    options: &mut ActuationOptionGroup,
+    transform: &UnparsedTransform,
+    traversal: &TraversalOptionGroup,
+    policy: &PolicyOptionGroup,
+) -> Result<Exit, Error>
+where
+    A: Label + Operation,
+{
+    let environment = Environment::new(policy.policy());
+    let manifest = read::<A>(&environment, transform, traversal, options.sort)?;
+    if manifest.routes().len() == 0 {
+        return Ok(Exit::NoMatches);
+    }
+    let manifest = match tui::select(&manifest)? {
+        Some(manifest) => manifest,
+        None => {
+            return Ok(Exit::Aborted);
+        }
+    };
+    environment.verify(&manifest)?;
+    // The routes have already been confirmed by applying them in the list.
+    options.interactive = Toggle::Never;
+    options.assume_no = false;
+    execute::<A>(options, &environment, &traversal.directory, manifest)
+}
+
+fn plan<A>(
+    operation: OperationKind,
+    transform: &UnparsedTransform,
+    traversal: &TraversalOptionGroup,
+    policy: &PolicyOptionGroup,
+    output: &Option<PathBuf>,
+) -> Result<Exit, Error>
+where
+    A: Operation,
+{
+    let environment = Environment::new(policy.policy());
+    let manifest = read::<A>(&environment, transform, traversal, SortKey::Matched)?;
+    let plan = Plan::from_manifest(operation, environment.policy(), &manifest)?;
+    if let Some(output) = output {
+        plan.write(File::create(output)?)?;
+    }
+    else {
+        plan.write(io::stdout())?;
+    }
+    if manifest.routes().len() == 0 {
+        Ok(Exit::NoMatches)
+    }
+    else {
+        Ok(Exit::Success)
+    }
+}
+
+fn apply<A>(options: &mut ActuationOptionGroup, plan: &Plan) -> Result<Exit, Error>
+where
+    A: Label + Operation,
+{
+    let environment = Environment::new(plan.policy());
+    let manifest = plan.to_manifest::<A::Routing>()?;
+    environment.verify(&manifest)?;
+    // Plans do not have a working directory tree. Paths in plans are relative
+    // to the working directory in which they are applied.
+    execute::<A>(options, &environment, Path::new("."), manifest)
+}
+
+/// Finds groups of identical files among the files matched by a from-pattern.
+///
+/// Files are grouped by size before their digests are computed, so files with
+/// a unique size are never read. Paths in each group are sorted and groups are
+/// sorted by their first path.
+fn find_duplicates(
+    from: &FromPattern<'_>,
+    traversal: &TraversalOptionGroup,
+) -> Result<Vec<Vec<Duplicate>>, Error> {
+    let mut sizes = HashMap::<u64, Vec<Duplicate>>::new();
+    for entry in from.walk(&traversal.directory, traversal.walk_behavior()) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error @ GlobError::LinkLoop(_)) => {
+                terminal::warning(error.to_string())?;
+                continue;
+            }
+            Err(error) => {
+                return Err(error.into());
+            }
+        };
+        let duplicate = match entry.metadata() {
+            Ok(metadata) if metadata.len() > 0 => Duplicate {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+                path: entry.into_path(),
+            },
+            _ => {
+                continue;
+            }
+        };
+        sizes.entry(duplicate.len).or_default().push(duplicate);
+    }
+    let digests = traversal.read_digest_cache()?;
+    // Digests are cached under the same name as the `{!b3sum}` property, so
+    // the cache is shared with to-patterns.
+    let b3sum = |path: &Path| -> io::Result<[u8; 32]> {
+        let f = || {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut File::open(path)?, &mut hasher)?;
+            Ok(*hasher.finalize().as_bytes())
+        };
+        match digests {
+            Some(ref digests) => digests.get_or_insert_with("b3sum", path, f),
+            None => f(),
+        }
+    };
+    let candidates: Vec<_> = sizes
+        .into_values()
+        .filter(|duplicates| duplicates.len() > 1)
+        .flatten()
+        .collect();
+    let digested: Vec<_> = candidates
+        .into_par_iter()
+        .map(|duplicate| {
+            (
+                b3sum(&duplicate.path).map(|digest| (duplicate.len, digest)),
+                duplicate,
+            )
+        })
+        .collect();
+    traversal.write_digest_cache(digests.as_deref())?;
+    let mut groups = HashMap::<_, Vec<Duplicate>>::new();
+    for (key, duplicate) in digested {
+        match key {
+            Ok(key) => groups.entry(key).or_default().push(duplicate),
+            Err(error) => {
+                terminal::warning(format!(
+                    "failed to read `{}`: {}",
+                    duplicate.path.display(),
+                    error
+                ))?;
+            }
         }
     }
-    Ok(())
+    let mut groups: Vec<_> = groups
+        .into_values()
+        .filter(|duplicates| duplicates.len() > 1)
+        .map(|mut duplicates| {
+            duplicates.sort_by(|left, right| left.path.cmp(&right.path));
+            duplicates
+        })
+        .collect();
+    groups.sort_by(|left, right| left[0].path.cmp(&right[0].path));
+    Ok(groups)
 }
 
-fn main() -> Result<(), Error> {
-    Program::from_args().run()
+/// File in a group of identical files found by `find_duplicates`.
+///
+/// The size and modification time of the file are read before it is hashed,
+/// so that files that change before they are linked or removed are detected.
+#[derive(Clone, Debug)]
+struct Duplicate {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Duplicate {
+    /// Fails if the file has changed since it was hashed.
+    fn verify(&self) -> io::Result<()> {
+        let metadata = fs::metadata(&self.path)?;
+        if metadata.len() == self.len && metadata.modified().ok() == self.modified {
+            Ok(())
+        }
+        else {
+            Err(io::Error::other(format!(
+                "`{}` has changed since it was hashed",
+                self.path.display()
+            )))
+        }
+    }
+}
+
+/// Replaces duplicate files with hard links to the files that they duplicate.
+///
+/// Each pair is a kept file and its duplicate. Duplicates are replaced
+/// atomically by linking to a staged path and renaming it over the duplicate.
+/// Linking stops if either file has changed since it was hashed.
+fn link_duplicates(duplicates: &[(&Duplicate, &Duplicate)], quiet: bool) -> Result<Exit, Error> {
+    let start = Instant::now();
+    let mut written = 0;
+    let mut bytes = 0;
+    let result = duplicates
+        .iter()
+        .printed()
+        .try_for_each(|(kept, duplicate)| {
+            kept.verify()
+                .and_then(|_| duplicate.verify())
+                .map_err(ActuationError)?;
+            let mut staged = duplicate.path.as_os_str().to_owned();
+            staged.push(".nym-link");
+            let staged = PathBuf::from(staged);
+            fs::hard_link(&kept.path, &staged)
+                .and_then(|_| {
+                    fs::rename(&staged, &duplicate.path).inspect_err(|_| {
+                        let _ = fs::remove_file(&staged);
+                    })
+                })
+                .map_err(ActuationError)?;
+            written += 1;
+            bytes += duplicate.len;
+            Ok::<_, ActuationError>(())
+        });
+    if !quiet {
+        terminal::print_summary(Summary {
+            label: "linked",
+            written,
+            failed: usize::from(result.is_err()),
+            copied: 0,
+            bytes,
+            elapsed: start.elapsed(),
+        })?;
+    }
+    result?;
+    Ok(Exit::Success)
+}
+
+/// Options that determine how files are removed by `remove`.
+struct Removal {
+    trash: bool,
+    quiet: bool,
+    dry_run: bool,
+    interactive: Toggle,
+    assumption: Option<bool>,
+    confirm_threshold: Option<usize>,
+}
+
+/// Prints and confirms the removal of files and then removes them.
+///
+/// `verify` is called with the index of each path immediately before it is
+/// removed and removal stops if it fails.
+fn remove(
+    paths: &[PathBuf],
+    removal: Removal,
+    options: &mut CommonOptionGroup,
+    verify: impl FnMut(usize) -> io::Result<()>,
+) -> Result<Exit, Error> {
+    let Removal {
+        trash,
+        quiet,
+        dry_run,
+        interactive,
+        assumption,
+        confirm_threshold,
+    } = removal;
+    if !quiet {
+        let paging = options.paging;
+        Terminal::with_output_process_scoped(options.pager(), paging, |mut output| {
+            paths
+                .iter()
+                .try_for_each(|path| path.as_path().print(&mut output))
+        })?;
+        if !trash {
+            terminal::warning(
+                "deleted files cannot be recovered; review patterns and paths carefully.",
+            )?;
+        }
+    }
+    if dry_run {
+        return Ok(Exit::Success);
+    }
+    if !confirm(
+        interactive,
+        assumption,
+        format!(
+            "Ready to {} {} files.",
+            if trash { "trash" } else { "delete" },
+            paths.len(),
+        ),
+        paths.len(),
+        Some(confirm_threshold.unwrap_or(DEFAULT_CONFIRM_THRESHOLD)),
+    )? {
+        return Ok(Exit::Aborted);
+    }
+    delete(paths, trash, quiet, verify)
+}
+
+fn delete(
+    paths: &[PathBuf],
+    trash: bool,
+    quiet: bool,
+    mut verify: impl FnMut(usize) -> io::Result<()>,
+) -> Result<Exit, Error> {
+    let start = Instant::now();
+    let mut written = 0;
+    let mut bytes = 0;
+    let result = paths
+        .iter()
+        .enumerate()
+        .printed()
+        .try_for_each(|(n, path)| {
+            let error = |error| RemovalError(path.clone(), error);
+            verify(n).map_err(error)?;
+            let len = fs::metadata(path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(0);
+            if trash {
+                trash::delete(path).map_err(|error| io::Error::other(error.to_string()))
+            }
+            else {
+                fs::remove_file(path)
+            }
+            .map_err(error)?;
+            written += 1;
+            bytes += len;
+            Ok::<_, RemovalError>(())
+        });
+    if !quiet {
+        terminal::print_summary(Summary {
+            label: if trash { "trashed" } else { "deleted" },
+            written,
+            failed: usize::from(result.is_err()),
+            copied: 0,
+            bytes,
+            elapsed: start.elapsed(),
+        })?;
+    }
+    result?;
+    Ok(Exit::Success)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn touch(
+    to: &str,
+    count: usize,
+    directory: &Path,
+    directories: bool,
+    parents: bool,
+    options: &mut CommonOptionGroup,
+    quiet: bool,
+    dry_run: bool,
+) -> Result<Exit, Error> {
+    let pattern = ToPattern::new(to).map_err(|error| PatternTextError::new(to, error))?;
+    debug!(pattern = %to, "parsed to-pattern");
+    let captures = Captures::default();
+    let mut paths = Vec::with_capacity(count);
+    let mut resolved = HashSet::with_capacity(count);
+    for position in 1..=count {
+        let path = directory.join(pattern.resolve(Resolution {
+            source: Path::new(""),
+            directory: Path::new(""),
+            captures: &captures,
+            position,
+        })?);
+        if path.exists() || !resolved.insert(path.clone()) {
+            return Err(PathCollisionError(path).into());
+        }
+        paths.push(path);
+    }
+    if paths.is_empty() {
+        return Ok(Exit::NoMatches);
+    }
+    if !quiet {
+        let paging = options.paging;
+        Terminal::with_output_process_scoped(options.pager(), paging, |mut output| {
+            paths
+                .iter()
+                .try_for_each(|path| path.as_path().print(&mut output))
+        })?;
+    }
+    if dry_run {
+        return Ok(Exit::Success);
+    }
+    for path in &paths {
+        if parents {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(ActuationError)?;
+            }
+        }
+        if directories {
+            fs::create_dir(path)
+        }
+        else {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .map(|_| ())
+        }
+        .map_err(ActuationError)?;
+    }
+    Ok(Exit::Success)
+}
+
+fn sort<M>(manifest: Manifest<M>, key: SortKey) -> Result<Manifest<M>, Error>
+where
+    M: Routing,
+{
+    if let SortKey::Matched = key {
+        return Ok(manifest);
+    }
+    let mut routes: Vec<_> = manifest.routes().collect();
+    match key {
+        SortKey::Matched => {}
+        SortKey::Mtime => {
+            routes.sort_by_cached_key(|route| {
+                route
+                    .sources()
+                    .next()
+                    .and_then(|source| fs::metadata(source).ok())
+                    .and_then(|metadata| metadata.modified().ok())
+            });
+        }
+        SortKey::Name => {
+            routes.sort_by_cached_key(|route| route.sources().next().copied());
+        }
+        SortKey::Natural => {
+            routes.sort_by(
+                |left, right| match (left.sources().next(), right.sources().next()) {
+                    (Some(left), Some(right)) => natural_path_cmp(left, right),
+                    (left, right) => left.is_some().cmp(&right.is_some()),
+                },
+            );
+        }
+        SortKey::Size => {
+            routes.sort_by_cached_key(|route| {
+                route
+                    .sources()
+                    .next()
+                    .and_then(|source| fs::metadata(source).ok())
+                    .map(|metadata| metadata.len())
+            });
+        }
+    }
+    let mut sorted = Manifest::default();
+    for route in routes {
+        for source in route.sources() {
+            sorted.insert(*source, *route.destination())?;
+        }
+    }
+    Ok(sorted)
+}
+
+/// Compares paths in natural order component by component.
+fn natural_path_cmp(left: &Path, right: &Path) -> cmp::Ordering {
+    let mut left = left.components();
+    let mut right = right.components();
+    loop {
+        match (left.next(), right.next()) {
+            (Some(l), Some(r)) => {
+                let ordering = text::natural_cmp(
+                    &l.as_os_str().to_string_lossy(),
+                    &r.as_os_str().to_string_lossy(),
+                );
+                if ordering != cmp::Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (left, right) => {
+                return left.is_some().cmp(&right.is_some());
+            }
+        }
+    }
+}
+
+/// Determines if the destination of a route is a link to its source.
+fn is_linked<M, P>(route: &Route<M, P>) -> bool
+where
+    M: Routing,
+    P: AsRef<Path>,
+{
+    route
+        .sources()
+        .any(|source| same_file::is_same_file(source, route.destination()).unwrap_or(false))
+}
+
+/// Gets the answer to prompts given by `--assume-yes` and `--assume-no`.
+fn assumption(assume_yes: bool, assume_no: bool) -> Option<bool> {
+    if assume_yes {
+        Some(true)
+    }
+    else if assume_no {
+        Some(false)
+    }
+    else {
+        None
+    }
+}
+
+/// Determines if an operation on the given number of files continues.
+///
+/// An assumed answer is used as is. Otherwise, if prompts are interactive,
+/// then a prompt is used. If the count exceeds the threshold, then the count
+/// (or "yes") must be typed to continue. Otherwise, the prompt is answered yes
+/// or no. Prompts that cannot be shown, because standard error is not
+/// attended, are declined with a warning.
+fn confirm(
+    interactive: Toggle,
+    assumption: Option<bool>,
+    prompt: String,
+    count: usize,
+    threshold: Option<usize>,
+) -> io::Result<bool> {
+    if let Some(answer) = assumption {
+        return Ok(answer);
+    }
+    if !terminal::is_interactive(interactive) {
+        return Ok(true);
+    }
+    if !terminal::is_interactive(Toggle::Automatic) {
+        terminal::warning(
+            "declined prompt, because standard error is not a terminal; use `--assume-yes` to \
+             continue without prompting.",
+        )?;
+        return Ok(false);
+    }
+    if threshold.is_some_and(|threshold| count > threshold) {
+        terminal::confirm_count(prompt, count)
+    }
+    else {
+        terminal::confirm(format!("{} Continue?", prompt))
+    }
+}
+
+/// Converts an error into a report that renders its diagnostic information.
+///
+/// Errors that provide no diagnostic information are reported with only their
+/// message and chain of causes.
+fn into_report(error: Error) -> Report {
+    fn downcast<E>(error: Error) -> Result<Report, Error>
+    where
+        E: Diagnostic + Send + Sync + 'static,
+    {
+        // Errors may be wrapped with context, in which case the diagnostic
+        // error is further down the chain. Its context is kept in the report.
+        let context: Vec<_> = error
+            .chain()
+            .take_while(|error| !error.is::<E>())
+            .map(ToString::to_string)
+            .collect();
+        error.downcast::<E>().map(|error| {
+            context
+                .into_iter()
+                .rev()
+                .fold(Report::new(error), |report, context| {
+                    report.wrap_err(context)
+                })
+        })
+    }
+
+    downcast::<PatternTextError<GlobError>>(error)
+        .or_else(downcast::<PatternTextError<PatternError>>)
+        .or_else(downcast::<TransformError>)
+        .or_else(downcast::<GlobError>)
+        .or_else(downcast::<PatternError>)
+        .or_else(downcast::<ManifestError>)
+        .or_else(downcast::<ActuationError>)
+        .or_else(downcast::<ActuationFailures>)
+        .or_else(downcast::<RemovalError>)
+        .or_else(downcast::<PathCollisionError>)
+        .unwrap_or_else(|error| Report::new(UndiagnosedError::from(error)))
+}
+
+fn main() {
+    let mut program = match Program::from_iter_safe(std::env::args_os()) {
+        Ok(program) => program,
+        Err(error) => {
+            // Help and version information are not errors.
+            if error.use_stderr() {
+                eprintln!("{}", error.message);
+                process::exit(Exit::Failure as i32);
+            }
+            else {
+                println!("{}", error.message);
+                process::exit(Exit::Success as i32);
+            }
+        }
+    };
+    report::set_handler();
+    let exit = program.run().unwrap_or_else(|error| {
+        let exit = if error.is::<ActuationError>()
+            || error.is::<ActuationFailures>()
+            || error.is::<RemovalError>()
+        {
+            Exit::PartialFailure
+        }
+        else {
+            Exit::Failure
+        };
+        eprintln!("{:?}", into_report(error));
+        exit
+    });
+    process::exit(exit as i32);
 }
@@ -1,9 +1,19 @@
+#[cfg(unix)]
+use nix::unistd::{Group, User};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::ffi::OsStr;
 use std::io::{self, Write};
+use std::path::{self, Component, Path, PathBuf};
 use std::process::{Child, Command, Stdio};
 use std::str::FromStr;
 use thiserror::Error;
 
+use nym::environment::Overwrite;
+use nym::glob::WalkOrder;
+use nym::pattern::FileKind;
+
 pub trait ResultExt<T, E>: Sized {
     fn broken_pipe_ok(self, value: T) -> Self {
         self.broken_pipe_ok_with(move || value)
@@ -38,11 +48,20 @@ impl<T> ResultExt<T, io::Error> for Result<T, io::Error> {
 pub enum OptionError {
     #[error("failed to parse option")]
     Parse,
+    #[error("unknown user: `{0}`")]
+    User(String),
+    #[error("unknown group: `{0}`")]
+    Group(String),
+    #[error("`{0}` only matches files; `--type` must be \"file\"")]
+    FileType(&'static str),
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum Toggle {
     Always,
+    #[default]
+    #[serde(alias = "auto")]
     Automatic,
     Never,
 }
@@ -60,12 +79,268 @@ impl FromStr for Toggle {
     }
 }
 
-impl Default for Toggle {
+/// Overwrite policy that is parsed from and written as text, such as
+/// "size-differs".
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub struct OverwriteMode(pub Overwrite);
+
+impl Default for OverwriteMode {
     fn default() -> Self {
-        Toggle::Automatic
+        OverwriteMode(Overwrite::Never)
+    }
+}
+
+impl From<Overwrite> for OverwriteMode {
+    fn from(overwrite: Overwrite) -> Self {
+        OverwriteMode(overwrite)
+    }
+}
+
+impl From<OverwriteMode> for Overwrite {
+    fn from(mode: OverwriteMode) -> Self {
+        mode.0
+    }
+}
+
+impl From<OverwriteMode> for &'static str {
+    fn from(mode: OverwriteMode) -> Self {
+        match mode.0 {
+            Overwrite::Always => "always",
+            Overwrite::Never => "never",
+            Overwrite::Newer => "newer",
+            Overwrite::SizeDiffers => "size-differs",
+        }
+    }
+}
+
+impl FromStr for OverwriteMode {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "always" => Ok(OverwriteMode(Overwrite::Always)),
+            "never" => Ok(OverwriteMode(Overwrite::Never)),
+            "newer" => Ok(OverwriteMode(Overwrite::Newer)),
+            "size-differs" => Ok(OverwriteMode(Overwrite::SizeDiffers)),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+impl TryFrom<String> for OverwriteMode {
+    type Error = OptionError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventFormat {
+    JsonLines,
+}
+
+impl FromStr for EventFormat {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "jsonl" => Ok(EventFormat::JsonLines),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    #[default]
+    Text,
+}
+
+impl FromStr for OutputFormat {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "json" => Ok(OutputFormat::Json),
+            "text" => Ok(OutputFormat::Text),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Kind of file matched by from-patterns.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FileTypeSelector {
+    #[default]
+    File,
+    Directory,
+    Link,
+}
+
+impl From<FileTypeSelector> for FileKind {
+    fn from(selector: FileTypeSelector) -> Self {
+        match selector {
+            FileTypeSelector::File => FileKind::File,
+            FileTypeSelector::Directory => FileKind::Directory,
+            FileTypeSelector::Link => FileKind::Link,
+        }
+    }
+}
+
+impl FromStr for FileTypeSelector {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "f" | "file" => Ok(FileTypeSelector::File),
+            "d" | "directory" => Ok(FileTypeSelector::Directory),
+            "l" | "link" => Ok(FileTypeSelector::Link),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Layout of manifests printed as text.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ManifestLayout {
+    /// Each route is printed with its sources and destination.
+    #[default]
+    Flat,
+    /// Routes are grouped beneath their destination directory.
+    Tree,
+}
+
+impl FromStr for ManifestLayout {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "flat" => Ok(ManifestLayout::Flat),
+            "tree" => Ok(ManifestLayout::Tree),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Shell for which printed paths are quoted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Quoting {
+    #[default]
+    Posix,
+    PowerShell,
+}
+
+impl FromStr for Quoting {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "posix" | "sh" => Ok(Quoting::Posix),
+            "powershell" | "pwsh" => Ok(Quoting::PowerShell),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Form of printed paths.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum PathForm {
+    /// Paths are printed as matched, joined to the working directory tree.
+    #[default]
+    Matched,
+    Absolute,
+    RelativeTo(PathBuf),
+}
+
+impl PathForm {
+    pub fn apply<'p>(&self, path: &'p Path) -> Cow<'p, Path> {
+        match self {
+            PathForm::Matched => path.into(),
+            PathForm::Absolute => absolute(path).into(),
+            PathForm::RelativeTo(ref directory) => {
+                relative_to(&absolute(path), &absolute(directory)).into()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortKey {
+    #[default]
+    Matched,
+    Mtime,
+    Name,
+    Natural,
+    Size,
+}
+
+impl SortKey {
+    /// Gets the order in which directory trees are traversed for the key.
+    ///
+    /// Sizes are not considered when traversing directory trees, so the order
+    /// of `Size` (like `Matched`) is unspecified.
+    pub fn walk_order(&self) -> WalkOrder {
+        match self {
+            SortKey::Matched | SortKey::Size => WalkOrder::Unsorted,
+            SortKey::Mtime => WalkOrder::Modified,
+            SortKey::Name => WalkOrder::Name,
+            SortKey::Natural => WalkOrder::Natural,
+        }
     }
 }
 
+impl FromStr for SortKey {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "matched" => Ok(SortKey::Matched),
+            "mtime" => Ok(SortKey::Mtime),
+            "name" => Ok(SortKey::Name),
+            "natural" => Ok(SortKey::Natural),
+            "size" => Ok(SortKey::Size),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Parses a user name or ID into a user ID.
+#[cfg(unix)]
+pub fn parse_user(text: &str) -> Result<u32, OptionError> {
+    text.parse()
+        .ok()
+        .or_else(|| user_id(text))
+        .ok_or_else(|| OptionError::User(text.into()))
+}
+
+/// Parses a group name or ID into a group ID.
+#[cfg(unix)]
+pub fn parse_group(text: &str) -> Result<u32, OptionError> {
+    text.parse()
+        .ok()
+        .or_else(|| group_id(text))
+        .ok_or_else(|| OptionError::Group(text.into()))
+}
+
+#[cfg(unix)]
+fn user_id(name: &str) -> Option<u32> {
+    User::from_name(name)
+        .ok()
+        .flatten()
+        .map(|user| user.uid.as_raw())
+}
+
+#[cfg(unix)]
+fn group_id(name: &str) -> Option<u32> {
+    Group::from_name(name)
+        .ok()
+        .flatten()
+        .map(|group| group.gid.as_raw())
+}
+
 #[derive(Debug)]
 pub struct Wait {
     child: Child,
@@ -116,6 +391,10 @@ impl ChildCommand {
         ChildCommand { command }
     }
 
+    pub fn binary(&self) -> &OsStr {
+        self.command.get_program()
+    }
+
     pub fn wait(&mut self) -> io::Result<Wait> {
         let child = self.command.spawn()?;
         Ok(Wait { child })
@@ -131,3 +410,49 @@ impl FromStr for ChildCommand {
         Ok(ChildCommand::from_command(binary, components))
     }
 }
+
+// Paths are made absolute lexically, because destination paths may not yet
+// exist and so cannot be canonicalized. `path::absolute` does not resolve `..`
+// components on all platforms, so they are resolved here. This may not agree
+// with the file system if a parent directory is a symbolic link.
+fn absolute(path: &Path) -> PathBuf {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut absolute = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            _ => absolute.push(component),
+        }
+    }
+    absolute
+}
+
+fn relative_to(path: &Path, directory: &Path) -> PathBuf {
+    let mut components = path.components().peekable();
+    let mut directory = directory.components().peekable();
+    // Paths with different prefixes (e.g., Windows drives) cannot be made
+    // relative to one another.
+    if components.peek() != directory.peek() {
+        return path.to_path_buf();
+    }
+    while let (Some(a), Some(b)) = (components.peek(), directory.peek()) {
+        if a != b {
+            break;
+        }
+        components.next();
+        directory.next();
+    }
+    let path: PathBuf = directory
+        .map(|_| Component::ParentDir)
+        .chain(components)
+        .collect();
+    if path.as_os_str().is_empty() {
+        PathBuf::from(Component::CurDir.as_os_str())
+    }
+    else {
+        path
+    }
+}
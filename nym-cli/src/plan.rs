@@ -0,0 +1,428 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+use thiserror::Error;
+
+use nym::environment::Policy;
+use nym::manifest::{Manifest, ManifestError, Route, Routing};
+
+use crate::option::{OptionError, OverwriteMode};
+
+/// Version of the format of plans and journals.
+///
+/// The version is only incremented by changes that earlier versions of Nym
+/// cannot read correctly. Fields that are added without incrementing the
+/// version are ignored by earlier versions.
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum PlanError {
+    #[error("failed to read or write plan: {0}")]
+    Io(io::Error),
+    #[error("failed to encode or decode plan: {0}")]
+    Encoding(serde_json::Error),
+    #[error("failed to insert route: {0}")]
+    RouteInsertion(ManifestError),
+    #[error("source file has changed since planning: `{0}`")]
+    SourceChanged(PathBuf),
+    #[error("source file not found: `{0}`")]
+    SourceNotFound(PathBuf),
+    #[error(
+        "unsupported format version {0}; the file was written by a later version of Nym \
+         (the latest supported version is {})",
+        FORMAT_VERSION
+    )]
+    UnsupportedVersion(u64),
+}
+
+impl From<io::Error> for PlanError {
+    fn from(error: io::Error) -> Self {
+        PlanError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for PlanError {
+    fn from(error: serde_json::Error) -> Self {
+        PlanError::Encoding(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperationKind {
+    Copy,
+    HardLink,
+    HardLinkOrCopy,
+    Move,
+    SoftLink,
+}
+
+impl FromStr for OperationKind {
+    type Err = OptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "copy" => Ok(OperationKind::Copy),
+            "hard-link" => Ok(OperationKind::HardLink),
+            "hard-link-or-copy" => Ok(OperationKind::HardLinkOrCopy),
+            "move" => Ok(OperationKind::Move),
+            "soft-link" => Ok(OperationKind::SoftLink),
+            _ => Err(OptionError::Parse),
+        }
+    }
+}
+
+/// Metadata of a plan or journal.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Header {
+    /// Version of the format.
+    ///
+    /// Files written before the format was versioned have no version and are
+    /// read as version zero.
+    #[serde(default)]
+    version: u64,
+    /// Version of Nym that wrote the file.
+    #[serde(default)]
+    nym: Option<String>,
+    /// Time at which the file was written.
+    #[serde(default)]
+    created: Option<SystemTime>,
+    /// Digest of the routes when the file was written.
+    ///
+    /// This is used to detect routes that have been edited since and is
+    /// prefixed by the name of its algorithm, such as `blake3:`.
+    #[serde(default)]
+    fingerprint: Option<String>,
+}
+
+impl Header {
+    fn new<T>(routes: &T) -> Result<Self, PlanError>
+    where
+        T: Serialize,
+    {
+        Ok(Header {
+            version: FORMAT_VERSION,
+            nym: Some(env!("CARGO_PKG_VERSION").to_owned()),
+            created: Some(SystemTime::now()),
+            fingerprint: Some(fingerprint(&serde_json::to_value(routes)?)),
+        })
+    }
+}
+
+/// Computes the fingerprint of the routes of a plan or journal.
+///
+/// Routes are digested as compact JSON with sorted keys, so the fingerprint
+/// does not depend on formatting nor on the order of fields.
+fn fingerprint(routes: &Value) -> String {
+    let routes = serde_json::to_vec(routes).expect("failed to encode routes");
+    format!("blake3:{}", blake3::hash(&routes).to_hex())
+}
+
+/// Reads a plan, journal, or manifest written as JSON.
+///
+/// Fails if the file was written in a later, unsupported version of the format.
+/// Also returns `true` if the file has a fingerprint that no longer matches
+/// its routes. Fingerprints computed by unknown algorithms are ignored.
+fn read_versioned<T>(path: impl AsRef<Path>) -> Result<(T, bool), PlanError>
+where
+    T: DeserializeOwned,
+{
+    let file = File::open(path)?;
+    let value: Value = serde_json::from_reader(BufReader::new(file))?;
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > FORMAT_VERSION {
+        return Err(PlanError::UnsupportedVersion(version));
+    }
+    let is_edited = match (
+        value.get("fingerprint").and_then(Value::as_str),
+        value.get("routes"),
+    ) {
+        (Some(expected), Some(routes)) if expected.starts_with("blake3:") => {
+            expected != fingerprint(routes)
+        }
+        _ => false,
+    };
+    Ok((serde_json::from_value(value)?, is_edited))
+}
+
+/// Route of a manifest written as JSON.
+#[derive(Clone, Debug, Deserialize)]
+struct ManifestRoute {
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+}
+
+/// Manifest written as JSON, such as by transforms with `--output=json`.
+#[derive(Clone, Debug, Deserialize)]
+struct ManifestRoutes {
+    routes: Vec<ManifestRoute>,
+}
+
+/// Reads a manifest written as JSON.
+///
+/// Unlike plans, manifests do not include an operation nor policy. Fails if
+/// any source file does not exist.
+pub fn read_manifest<M>(path: impl AsRef<Path>) -> Result<Manifest<M>, PlanError>
+where
+    M: Routing,
+{
+    let (ManifestRoutes { routes }, _) = read_versioned(path)?;
+    let mut manifest = Manifest::default();
+    for route in routes {
+        for source in route.sources {
+            if !source.is_file() {
+                return Err(PlanError::SourceNotFound(source));
+            }
+            manifest
+                .insert(source, &route.destination)
+                .map_err(PlanError::RouteInsertion)?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Source file of a planned route.
+///
+/// The size and modification time of the file are recorded when planning so
+/// that changes can be detected before a plan is applied.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlannedSource {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl PlannedSource {
+    pub fn read(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let metadata = fs::metadata(&path)?;
+        Ok(PlannedSource {
+            path,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Determines if the size or modification time of the file has changed
+    /// or if it can no longer be read.
+    pub fn is_stale(&self) -> bool {
+        fs::metadata(&self.path)
+            .map(|metadata| metadata.len() != self.len || metadata.modified().ok() != self.modified)
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlannedRoute {
+    sources: Vec<PlannedSource>,
+    destination: PathBuf,
+}
+
+/// Manifest and policy that can be written to and read from a file.
+///
+/// Plans are encoded as JSON and include a header with the version of the
+/// format, the time at which the plan was written, and a fingerprint of its
+/// routes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Plan {
+    #[serde(flatten)]
+    header: Header,
+    #[serde(skip)]
+    is_edited: bool,
+    operation: OperationKind,
+    parents: bool,
+    overwrite: OverwriteMode,
+    #[serde(default)]
+    preserve: bool,
+    #[serde(default)]
+    skip_identical: bool,
+    #[serde(default)]
+    merge: bool,
+    routes: Vec<PlannedRoute>,
+}
+
+impl Plan {
+    pub fn from_manifest<M>(
+        operation: OperationKind,
+        policy: &Policy,
+        manifest: &Manifest<M>,
+    ) -> Result<Self, PlanError>
+    where
+        M: Routing,
+    {
+        let routes = manifest
+            .routes()
+            .map(|route| {
+                Ok(PlannedRoute {
+                    sources: route
+                        .sources()
+                        .map(PlannedSource::read)
+                        .collect::<io::Result<_>>()?,
+                    destination: route.destination().into(),
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Plan {
+            header: Header::new(&routes)?,
+            is_edited: false,
+            operation,
+            parents: policy.parents,
+            overwrite: policy.overwrite.into(),
+            preserve: policy.preserve,
+            skip_identical: policy.skip_identical,
+            merge: policy.merge,
+            routes,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, PlanError> {
+        let (plan, is_edited) = read_versioned(path)?;
+        Ok(Plan { is_edited, ..plan })
+    }
+
+    pub fn write(&self, output: impl Write) -> Result<(), PlanError> {
+        let mut output = BufWriter::new(output);
+        serde_json::to_writer_pretty(&mut output, self)?;
+        writeln!(output)?;
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Constructs a manifest from the plan.
+    ///
+    /// Fails if any source file has changed since the plan was written.
+    pub fn to_manifest<M>(&self) -> Result<Manifest<M>, PlanError>
+    where
+        M: Routing,
+    {
+        let mut manifest = Manifest::default();
+        for route in &self.routes {
+            for source in &route.sources {
+                if source.is_stale() {
+                    return Err(PlanError::SourceChanged(source.path.clone()));
+                }
+                manifest
+                    .insert(&source.path, &route.destination)
+                    .map_err(PlanError::RouteInsertion)?;
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn operation(&self) -> OperationKind {
+        self.operation
+    }
+
+    /// Returns `true` if the routes of the plan have been edited since it was
+    /// written.
+    pub fn is_edited(&self) -> bool {
+        self.is_edited
+    }
+
+    pub fn policy(&self) -> Policy {
+        let mut policy = Policy::default();
+        policy.parents = self.parents;
+        policy.overwrite = self.overwrite.into();
+        policy.preserve = self.preserve;
+        policy.skip_identical = self.skip_identical;
+        policy.merge = self.merge;
+        policy
+    }
+}
+
+/// Outcome of writing a route.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "outcome", rename_all = "kebab-case")]
+pub enum Outcome {
+    Written {
+        bytes: u64,
+    },
+    Failed {
+        error: String,
+    },
+    /// The route was not written, because a previous route failed.
+    Skipped,
+    /// The route was not written, because a source changed after the
+    /// manifest was read (see `--verify-unchanged`).
+    Changed {
+        source: PathBuf,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JournalRoute {
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+/// Manifest, policy, and the outcome of each route of an applied transform.
+///
+/// Outcomes include the error of failed routes and the changed source of
+/// changed routes. Skipped routes are those that were not attempted after a
+/// route failed.
+///
+/// Journals are encoded as JSON with the same header as plans. Like manifests
+/// written with `--output=json`, journals can be read by `read_manifest`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Journal {
+    #[serde(flatten)]
+    header: Header,
+    operation: OperationKind,
+    parents: bool,
+    overwrite: OverwriteMode,
+    preserve: bool,
+    skip_identical: bool,
+    merge: bool,
+    routes: Vec<JournalRoute>,
+}
+
+impl Journal {
+    pub fn from_outcomes<'m, M, I>(
+        operation: OperationKind,
+        policy: &Policy,
+        routes: I,
+    ) -> Result<Self, PlanError>
+    where
+        M: Routing,
+        I: IntoIterator<Item = (Route<M, &'m Path>, Outcome)>,
+    {
+        let routes: Vec<_> = routes
+            .into_iter()
+            .map(|(route, outcome)| JournalRoute {
+                sources: route.sources().map(|source| source.to_path_buf()).collect(),
+                destination: route.destination().into(),
+                outcome,
+            })
+            .collect();
+        Ok(Journal {
+            header: Header::new(&routes)?,
+            operation,
+            parents: policy.parents,
+            overwrite: policy.overwrite.into(),
+            preserve: policy.preserve,
+            skip_identical: policy.skip_identical,
+            merge: policy.merge,
+            routes,
+        })
+    }
+
+    pub fn write(&self, output: impl Write) -> Result<(), PlanError> {
+        let mut output = BufWriter::new(output);
+        serde_json::to_writer_pretty(&mut output, self)?;
+        writeln!(output)?;
+        output.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use miette::{Diagnostic, LabeledSpan, MietteHandlerOpts, SourceCode};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::iter;
+use thiserror::Error;
+
+use nym::glob::GlobError;
+use nym::pattern::PatternError;
+
+use crate::terminal;
+
+/// Error that can locate where parsing failed in the text of a pattern.
+pub trait ParseOffset {
+    fn parse_offset(&self, text: &str) -> Option<usize>;
+}
+
+impl ParseOffset for GlobError {
+    fn parse_offset(&self, text: &str) -> Option<usize> {
+        GlobError::parse_offset(self, text)
+    }
+}
+
+impl ParseOffset for PatternError {
+    fn parse_offset(&self, text: &str) -> Option<usize> {
+        PatternError::parse_offset(self, text)
+    }
+}
+
+/// Error in a pattern along with the text of that pattern.
+///
+/// The code and help of the error are forwarded and the pattern is rendered
+/// as its source code. If parsing failed, then the location of the failure is
+/// labeled. Otherwise, the entire pattern is labeled.
+#[derive(Debug)]
+pub struct PatternTextError<E> {
+    text: String,
+    offset: Option<usize>,
+    error: E,
+}
+
+impl<E> PatternTextError<E>
+where
+    E: ParseOffset,
+{
+    pub fn new(text: impl Into<String>, error: E) -> Self {
+        let text = text.into();
+        let offset = error.parse_offset(&text);
+        PatternTextError {
+            text,
+            offset,
+            error,
+        }
+    }
+}
+
+impl<E> Diagnostic for PatternTextError<E>
+where
+    E: Diagnostic,
+{
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.text)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = match self.offset {
+            Some(offset) => LabeledSpan::at_offset(offset, "failed here"),
+            None => LabeledSpan::at(0..self.text.len(), "in this pattern"),
+        };
+        Some(Box::new(iter::once(label)))
+    }
+}
+
+impl<E> Display for PatternTextError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, formatter)
+    }
+}
+
+impl<E> std::error::Error for PatternTextError<E>
+where
+    E: std::error::Error,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Error without diagnostic information.
+///
+/// Such errors are reported with only their message and chain of causes.
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct UndiagnosedError(#[from] anyhow::Error);
+
+impl Diagnostic for UndiagnosedError {}
+
+/// Sets the handler used to render error reports.
+///
+/// Reports use the glyphs of the theme and the color output of standard error
+/// at the time that they are rendered.
+pub fn set_handler() {
+    let _ = miette::set_hook(Box::new(|_| {
+        Box::new(
+            MietteHandlerOpts::new()
+                .color(console::colors_enabled_stderr())
+                .unicode(terminal::is_unicode_enabled())
+                .build(),
+        )
+    }));
+}
@@ -1,34 +1,154 @@
+use chrono::offset::Local;
+use chrono::DateTime;
 use console::{self, Style, Term};
-use dialoguer::theme::ColorfulTheme;
-use dialoguer::Confirm;
-use indicatif::{ProgressBar, ProgressBarIter, ProgressDrawTarget, ProgressIterator};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme as PromptTheme};
+use dialoguer::{Confirm, Input};
+use indicatif::{
+    HumanBytes, HumanDuration, ProgressBar, ProgressBarIter, ProgressDrawTarget, ProgressIterator,
+    ProgressStyle,
+};
 use itertools::{Itertools as _, Position};
 use lazy_static::lazy_static;
 use lscolors::{self, LsColors};
+use serde_json::json;
+use similar::{ChangeTag, TextDiff};
+use std::borrow::Cow;
 use std::cmp;
-use std::convert::{TryFrom, TryInto};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::env;
+use std::fs::FileType;
 use std::io::{self, Read, Write};
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{RwLock, RwLockReadGuard};
+use std::time::Duration;
 
-use nym::manifest::{Manifest, Routing};
+use nym::glob::WalkEntry;
+use nym::manifest::{Manifest, Route, Routing};
 
-use crate::option::{ChildCommand, Toggle, Wait};
+use crate::config::ThemeConfig;
+use crate::option::{ChildCommand, PathForm, Quoting, Toggle, Wait};
 
 const MIN_TERMINAL_WIDTH: usize = 16;
 
 lazy_static! {
-    static ref STYLE_INDEX: Style = Style::new().bright().white();
-    static ref STYLE_LINE: Style = Style::new();
-    static ref STYLE_SOURCE_PATH: Style = Style::new().green();
-    static ref STYLE_DESTINATION_PATH: Style = Style::new().red();
-    static ref STYLE_WARNING: Style = Style::new().bold();
-    static ref STYLE_WARNING_HEADER: Style = Style::new().blink().bold().yellow();
+    static ref THEME: RwLock<Theme> = RwLock::new(Theme::default());
+}
+
+/// Characters used to draw manifests.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Glyphs {
+    first_source: &'static str,
+    next_source: &'static str,
+    continuation: &'static str,
+    destination: &'static str,
+    ellipsis: &'static str,
+    arrow: &'static str,
+    branch: &'static str,
+    last_branch: &'static str,
+}
+
+impl Glyphs {
+    const ASCII: Self = Glyphs {
+        first_source: "-+--",
+        next_source: "|--",
+        continuation: "|",
+        destination: "`->",
+        ellipsis: "...",
+        arrow: "->",
+        branch: "|--",
+        last_branch: "`--",
+    };
+    const UNICODE: Self = Glyphs {
+        first_source: "─┬──",
+        next_source: "├──",
+        continuation: "│",
+        destination: "╰─⯈",
+        ellipsis: "…",
+        arrow: "→",
+        branch: "├──",
+        last_branch: "╰──",
+    };
+}
+
+/// Styles and glyphs of printed output.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    index: Style,
+    line: Style,
+    source: Style,
+    destination: Style,
+    change: Style,
+    warning: Style,
+    warning_header: Style,
+    glyphs: Glyphs,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            index: Style::new().bright().white(),
+            line: Style::new(),
+            source: Style::new().green(),
+            destination: Style::new(),
+            change: Style::new().bold().red(),
+            warning: Style::new().for_stderr().bold(),
+            warning_header: Style::new().for_stderr().blink().bold().yellow(),
+            glyphs: Glyphs::UNICODE,
+        }
+    }
+}
+
+impl From<&ThemeConfig> for Theme {
+    fn from(config: &ThemeConfig) -> Self {
+        let style = |text: &Option<String>, default: Style| {
+            text.as_deref().map_or(default, Style::from_dotted_str)
+        };
+        let theme = Theme::default();
+        // Warnings are printed to standard error, so their style must be
+        // configured for that stream. The header and body share the style.
+        let warning = config
+            .warning
+            .as_deref()
+            .map(|text| Style::from_dotted_str(text).for_stderr());
+        Theme {
+            index: style(&config.index, theme.index),
+            line: style(&config.line, theme.line),
+            source: style(&config.source, theme.source),
+            destination: style(&config.destination, theme.destination),
+            change: style(&config.change, theme.change),
+            warning: warning.clone().unwrap_or(theme.warning),
+            warning_header: warning.unwrap_or(theme.warning_header),
+            glyphs: if config.ascii.unwrap_or(false) {
+                Glyphs::ASCII
+            }
+            else {
+                Glyphs::UNICODE
+            },
+        }
+    }
+}
+
+/// Sets the theme used by subsequent output.
+pub fn set_theme(theme: Theme) {
+    *THEME.write().expect("failed to write theme") = theme;
+}
+
+fn theme() -> RwLockReadGuard<'static, Theme> {
+    THEME.read().expect("failed to read theme")
+}
+
+/// Gets whether or not the theme draws with Unicode glyphs.
+pub fn is_unicode_enabled() -> bool {
+    theme().glyphs == Glyphs::UNICODE
 }
 
 pub trait FromStyle<T>: Sized {
     fn from_style(style: T) -> Self;
 }
 
+#[allow(dead_code)]
 pub trait IntoStyle<T>: Sized {
     fn into_style(self) -> T;
 }
@@ -134,13 +254,19 @@ pub struct Terminal {
 }
 
 impl Terminal {
-    pub fn with_output_process(command: &mut ChildCommand, toggle: Toggle) -> Self {
+    /// Gets a terminal that writes to the first of the given commands that can
+    /// be started.
+    ///
+    /// Commands are tried in order and a notice is printed if the first
+    /// command cannot be started. If no command can be started, then the
+    /// terminal writes to standard output.
+    pub fn with_output_process(commands: &mut [ChildCommand], toggle: Toggle) -> Self {
         match toggle {
-            Toggle::Always => command.try_into().unwrap_or_else(|_| Term::stdout().into()),
+            Toggle::Always => Self::with_first_output_process(commands),
             Toggle::Automatic => {
                 let terminal = Term::stdout();
                 if terminal.features().is_attended() {
-                    command.try_into().unwrap_or_else(|_| Term::stdout().into())
+                    Self::with_first_output_process(commands)
                 }
                 else {
                     terminal.into()
@@ -151,14 +277,40 @@ impl Terminal {
     }
 
     pub fn with_output_process_scoped<T, F>(
-        command: &mut ChildCommand,
+        commands: &mut [ChildCommand],
         toggle: Toggle,
         mut f: F,
     ) -> T
     where
         F: FnMut(Terminal) -> T,
     {
-        f(Self::with_output_process(command, toggle))
+        f(Self::with_output_process(commands, toggle))
+    }
+
+    fn with_first_output_process(commands: &mut [ChildCommand]) -> Self {
+        let mut missing = None;
+        for command in commands.iter_mut() {
+            match Terminal::try_from(&mut *command) {
+                Ok(terminal) => {
+                    if let Some(missing) = missing {
+                        let _ = warning(format!(
+                            "pager `{}` not found; using `{}` instead",
+                            missing,
+                            command.binary().to_string_lossy(),
+                        ));
+                    }
+                    return terminal;
+                }
+                Err(_) => {
+                    missing =
+                        missing.or_else(|| Some(command.binary().to_string_lossy().into_owned()));
+                }
+            }
+        }
+        if let Some(missing) = missing {
+            let _ = warning(format!("pager `{}` not found; paging disabled", missing));
+        }
+        Term::stdout().into()
     }
 }
 
@@ -182,8 +334,8 @@ impl Page for Terminal {
         self.inner.features().is_attended().then(|| {
             let (height, width) = self.inner.size();
             Layout {
-                width: usize::try_from(width).expect("width overflow"),
-                height: usize::try_from(height).expect("height overflow"),
+                width: usize::from(width),
+                height: usize::from(height),
             }
         })
     }
@@ -227,17 +379,15 @@ pub trait IteratorExt: Iterator + Sized {
     where
         Self: ExactSizeIterator,
     {
-        let n = u64::try_from(self.len()).expect("length overflow");
-        self.progress_with(ProgressBar::with_draw_target(
-            n,
-            ProgressDrawTarget::stderr(),
-        ))
+        let progress = progress(self.len());
+        self.progress_with(progress)
     }
 }
 
 impl<I> IteratorExt for I where I: Iterator + Sized {}
 
 pub trait Stylize {
+    #[allow(dead_code)]
     fn stylize(&self) -> Vec<u8> {
         let mut output = Vec::new();
         self.stylize_into(&mut output).expect("");
@@ -247,7 +397,7 @@ pub trait Stylize {
     fn stylize_into(&self, output: &mut impl Write) -> io::Result<()>;
 }
 
-impl<'p> Stylize for &'p Path {
+impl Stylize for &Path {
     // TODO: This reads file metadata regardless of whether or not color is
     //       enabled. If color is disabled, do not read metadata.
     // TODO: `LS_COLORS` is only used by the `find` sub-command, but it could
@@ -256,7 +406,7 @@ impl<'p> Stylize for &'p Path {
     //       Implement a way to format stylized outputs.
     fn stylize_into(&self, output: &mut impl Write) -> io::Result<()> {
         let colors = LsColors::from_env().unwrap_or_default();
-        for (text, style) in colors.style_for_path_components(*self) {
+        for (text, style) in colors.style_for_path_components(self) {
             let style = style.cloned().map(Style::from_style).unwrap_or_default();
             write!(output, "{}", style.apply_to(text.to_string_lossy()))?;
         }
@@ -268,7 +418,7 @@ pub trait Print {
     fn print(&self, output: &mut (impl Page + Write)) -> io::Result<()>;
 }
 
-impl<'p> Print for &'p Path {
+impl Print for &Path {
     fn print(&self, output: &mut (impl Page + Write)) -> io::Result<()> {
         self.stylize_into(output)?;
         writeln!(output)
@@ -280,93 +430,616 @@ where
     M: Routing,
 {
     fn print(&self, output: &mut (impl Page + Write)) -> io::Result<()> {
-        let routes = self.routes();
-        let margin = ((routes.len() as f64).log10() as usize) + 1;
-        let width = width(output, margin + 6);
-        for (n, route) in routes.enumerate() {
-            for source in route.sources().with_position() {
-                match source {
-                    Position::First(source) | Position::Only(source) => {
-                        let source = source.to_string_lossy();
-                        for line in textwrap::wrap(source.as_ref(), width)
-                            .into_iter()
-                            .with_position()
-                        {
-                            match line {
-                                Position::First(line) | Position::Only(line) => writeln!(
-                                    output,
-                                    "{:0>width$} {} {}",
-                                    STYLE_INDEX.apply_to(n + 1),
-                                    STYLE_LINE.apply_to("─┬──"),
-                                    STYLE_SOURCE_PATH.apply_to(line),
-                                    width = margin,
-                                ),
-                                Position::Middle(line) | Position::Last(line) => writeln!(
-                                    output,
-                                    "{: >width$}   {}",
-                                    STYLE_LINE.apply_to("│"),
-                                    STYLE_SOURCE_PATH.apply_to(line),
-                                    width = margin + 3,
-                                ),
-                            }?;
-                        }
+        print_manifest(self, usize::MAX, &PathForm::Matched, output)
+    }
+}
+
+/// Prints at most `len` routes of a manifest with paths in the given form.
+///
+/// If the manifest has more routes, then the number of remaining routes is
+/// printed after the last printed route.
+pub fn print_manifest<M>(
+    manifest: &Manifest<M>,
+    len: usize,
+    form: &PathForm,
+    output: &mut (impl Page + Write),
+) -> io::Result<()>
+where
+    M: Routing,
+{
+    let theme = theme();
+    let routes = manifest.routes();
+    let remaining = routes.len().saturating_sub(len);
+    let margin = ((routes.len() as f64).log10() as usize) + 1;
+    let width = width(output, margin + 6);
+    for (n, route) in routes.enumerate().take(len) {
+        for source in route.sources().with_position() {
+            match source {
+                Position::First(source) | Position::Only(source) => {
+                    let source = form.apply(source);
+                    let source = source.to_string_lossy();
+                    for line in textwrap::wrap(source.as_ref(), width)
+                        .into_iter()
+                        .with_position()
+                    {
+                        match line {
+                            Position::First(line) | Position::Only(line) => writeln!(
+                                output,
+                                "{:0>width$} {} {}",
+                                theme.index.apply_to(n + 1),
+                                theme.line.apply_to(theme.glyphs.first_source),
+                                theme.source.apply_to(line),
+                                width = margin,
+                            ),
+                            Position::Middle(line) | Position::Last(line) => writeln!(
+                                output,
+                                "{: >width$}   {}",
+                                theme.line.apply_to(theme.glyphs.continuation),
+                                theme.source.apply_to(line),
+                                width = margin + 3,
+                            ),
+                        }?;
                     }
-                    Position::Middle(source) | Position::Last(source) => {
-                        let source = source.to_string_lossy();
-                        for line in textwrap::wrap(source.as_ref(), width)
-                            .into_iter()
-                            .with_position()
-                        {
-                            match line {
-                                Position::First(line) | Position::Only(line) => writeln!(
-                                    output,
-                                    "{: >width$} {}",
-                                    STYLE_LINE.apply_to("├──"),
-                                    STYLE_SOURCE_PATH.apply_to(line),
-                                    width = margin + 3,
-                                ),
-                                Position::Middle(line) | Position::Last(line) => writeln!(
-                                    output,
-                                    "{: >width$}   {}",
-                                    STYLE_LINE.apply_to("│"),
-                                    STYLE_SOURCE_PATH.apply_to(line),
-                                    width = margin + 3,
-                                ),
-                            }?;
-                        }
+                }
+                Position::Middle(source) | Position::Last(source) => {
+                    let source = form.apply(source);
+                    let source = source.to_string_lossy();
+                    for line in textwrap::wrap(source.as_ref(), width)
+                        .into_iter()
+                        .with_position()
+                    {
+                        match line {
+                            Position::First(line) | Position::Only(line) => writeln!(
+                                output,
+                                "{: >width$} {}",
+                                theme.line.apply_to(theme.glyphs.next_source),
+                                theme.source.apply_to(line),
+                                width = margin + 3,
+                            ),
+                            Position::Middle(line) | Position::Last(line) => writeln!(
+                                output,
+                                "{: >width$}   {}",
+                                theme.line.apply_to(theme.glyphs.continuation),
+                                theme.source.apply_to(line),
+                                width = margin + 3,
+                            ),
+                        }?;
                     }
                 }
             }
-            let destination = route.destination().to_string_lossy();
-            for line in textwrap::wrap(destination.as_ref(), width)
-                .into_iter()
-                .with_position()
-            {
-                match line {
-                    Position::First(line) | Position::Only(line) => writeln!(
-                        output,
-                        "{: >width$} {}",
-                        STYLE_LINE.apply_to("╰─⯈"),
-                        STYLE_DESTINATION_PATH.apply_to(line),
-                        width = margin + 5,
-                    ),
-                    Position::Middle(line) | Position::Last(line) => writeln!(
-                        output,
-                        "{: >width$}{}",
-                        "",
-                        STYLE_DESTINATION_PATH.apply_to(line),
-                        width = margin + 6,
-                    ),
-                }?;
+        }
+        let destination = form.apply(route.destination());
+        let destination = destination.to_string_lossy();
+        let changes = route
+            .sources()
+            .next()
+            .map(|source| changes(&form.apply(source).to_string_lossy(), &destination))
+            .unwrap_or_default();
+        let mut position = 0;
+        for line in textwrap::wrap(destination.as_ref(), width)
+            .into_iter()
+            .with_position()
+        {
+            let text = match line {
+                Position::First(ref text)
+                | Position::Middle(ref text)
+                | Position::Last(ref text)
+                | Position::Only(ref text) => text,
+            };
+            // Wrapped lines may omit whitespace, so find the offset of each
+            // line in the destination path.
+            let offset = destination[position..]
+                .find(text.as_ref())
+                .map_or(position, |offset| position + offset);
+            position = offset + text.len();
+            let text = stylize_destination(&theme, text, offset, &changes);
+            match line {
+                Position::First(_) | Position::Only(_) => writeln!(
+                    output,
+                    "{: >width$} {}",
+                    theme.line.apply_to(theme.glyphs.destination),
+                    text,
+                    width = margin + 5,
+                ),
+                Position::Middle(_) | Position::Last(_) => {
+                    writeln!(output, "{: >width$}{}", "", text, width = margin + 6)
+                }
+            }?;
+        }
+    }
+    if remaining > 0 {
+        writeln!(
+            output,
+            "{: >width$} and {} more",
+            theme.line.apply_to(theme.glyphs.ellipsis),
+            separated(remaining),
+            width = margin,
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints at most `len` routes of a manifest grouped beneath their destination
+/// directories with paths in the given form.
+///
+/// Directories are printed once in the order in which they are first routed
+/// and each route is printed beneath its directory as its sources and the file
+/// name of its destination. If the manifest has more routes, then the number
+/// of remaining routes is printed after the last printed route.
+pub fn print_manifest_tree<M>(
+    manifest: &Manifest<M>,
+    len: usize,
+    form: &PathForm,
+    output: &mut (impl Page + Write),
+) -> io::Result<()>
+where
+    M: Routing,
+{
+    let theme = theme();
+    let routes = manifest.routes();
+    let remaining = routes.len().saturating_sub(len);
+    let mut directories: Vec<(PathBuf, Vec<_>)> = Vec::new();
+    for route in routes.take(len) {
+        let destination = form.apply(route.destination());
+        let directory = destination.parent().unwrap_or_else(|| Path::new(""));
+        let directory = if directory.as_os_str().is_empty() {
+            Path::new(".")
+        }
+        else {
+            directory
+        };
+        let index = match directories
+            .iter()
+            .position(|(parent, _)| parent.as_path() == directory)
+        {
+            Some(index) => index,
+            None => {
+                directories.push((directory.to_path_buf(), Vec::new()));
+                directories.len() - 1
             }
+        };
+        directories[index].1.push(route);
+    }
+    for (directory, routes) in &directories {
+        writeln!(
+            output,
+            "{} {}",
+            theme.destination.apply_to(directory.to_string_lossy()),
+            theme
+                .index
+                .apply_to(format!("({})", separated(routes.len()))),
+        )?;
+        for route in routes.iter().with_position() {
+            let (glyph, route) = match route {
+                Position::First(route) | Position::Middle(route) => (theme.glyphs.branch, route),
+                Position::Last(route) | Position::Only(route) => (theme.glyphs.last_branch, route),
+            };
+            let sources = route
+                .sources()
+                .map(|source| form.apply(source).to_string_lossy().into_owned())
+                .join(", ");
+            let name = route
+                .destination()
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            let changes = route
+                .sources()
+                .next()
+                .and_then(|source| source.file_name())
+                .map(|source| changes(&source.to_string_lossy(), &name))
+                .unwrap_or_default();
+            writeln!(
+                output,
+                "{} {} {} {}",
+                theme.line.apply_to(glyph),
+                theme.source.apply_to(sources),
+                theme.line.apply_to(theme.glyphs.arrow),
+                stylize_destination(&theme, &name, 0, &changes),
+            )?;
         }
-        Ok(())
     }
+    if remaining > 0 {
+        writeln!(
+            output,
+            "{} and {} more",
+            theme.line.apply_to(theme.glyphs.ellipsis),
+            separated(remaining),
+        )?;
+    }
+    Ok(())
+}
+
+/// Prints a source path and the destination path to which it resolves.
+pub fn print_resolution(
+    source: &Path,
+    destination: &Path,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let theme = theme();
+    writeln!(
+        output,
+        "{} {} {}",
+        theme.source.apply_to(source.to_string_lossy()),
+        theme.line.apply_to(theme.glyphs.arrow),
+        theme.destination.apply_to(destination.to_string_lossy()),
+    )
+}
+
+/// Prints the number of routes of a manifest per destination directory and
+/// extension with paths in the given form.
+pub fn print_manifest_summary<M>(
+    manifest: &Manifest<M>,
+    form: &PathForm,
+    output: &mut (impl Page + Write),
+) -> io::Result<()>
+where
+    M: Routing,
+{
+    let theme = theme();
+    let mut counts = BTreeMap::<_, usize>::new();
+    for route in manifest.routes() {
+        let destination = form.apply(route.destination());
+        let directory = destination.parent().unwrap_or_else(|| Path::new(""));
+        let name = match destination.extension() {
+            Some(extension) => format!("*.{}", extension.to_string_lossy()),
+            None => "*".to_owned(),
+        };
+        *counts.entry(directory.join(name)).or_default() += 1;
+    }
+    let margin = counts
+        .values()
+        .map(|count| separated(*count).len())
+        .max()
+        .unwrap_or(0);
+    for (destination, count) in &counts {
+        writeln!(
+            output,
+            "{: >width$} {}",
+            theme.index.apply_to(separated(*count)),
+            theme.destination.apply_to(destination.to_string_lossy()),
+            width = margin,
+        )?;
+    }
+    let directories: HashSet<_> = counts.keys().map(|path| path.parent()).collect();
+    writeln!(
+        output,
+        "{} {} into {} {}",
+        separated(manifest.routes().len()),
+        if manifest.routes().len() == 1 {
+            "route"
+        }
+        else {
+            "routes"
+        },
+        separated(directories.len()),
+        if directories.len() == 1 {
+            "directory"
+        }
+        else {
+            "directories"
+        },
+    )
+}
+
+/// Gets the byte ranges in a destination path that differ from its source path.
+fn changes(source: &str, destination: &str) -> Vec<Range<usize>> {
+    let mut changes: Vec<Range<usize>> = Vec::new();
+    let mut position = 0;
+    for change in TextDiff::from_chars(source, destination).iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Delete => {}
+            ChangeTag::Equal => {
+                position += change.value().len();
+            }
+            ChangeTag::Insert => {
+                let end = position + change.value().len();
+                match changes.last_mut() {
+                    Some(range) if range.end == position => {
+                        range.end = end;
+                    }
+                    _ => {
+                        changes.push(position..end);
+                    }
+                }
+                position = end;
+            }
+        }
+    }
+    changes
+}
+
+/// Styles a line of a destination path beginning at the given byte offset,
+/// emphasizing changes.
+fn stylize_destination(
+    theme: &Theme,
+    line: &str,
+    offset: usize,
+    changes: &[Range<usize>],
+) -> String {
+    let clamp = |index: usize| index.saturating_sub(offset).min(line.len());
+    let mut text = String::new();
+    let mut start = 0;
+    for range in changes {
+        let (first, last) = (clamp(range.start).max(start), clamp(range.end));
+        if first < last {
+            text.push_str(&theme.destination.apply_to(&line[start..first]).to_string());
+            text.push_str(&theme.change.apply_to(&line[first..last]).to_string());
+            start = last;
+        }
+    }
+    text.push_str(&theme.destination.apply_to(&line[start..]).to_string());
+    text
+}
+
+// NOTE: `indicatif` applies styles in templates according to the color output
+//       of standard output rather than standard error. Progress bars are
+//       drawn to standard error and so are never styled, which ensures that
+//       they are colorless when color is disabled for standard error.
+pub fn progress(len: usize) -> ProgressBar {
+    let n = u64::try_from(len).expect("length overflow");
+    ProgressBar::with_draw_target(n, ProgressDrawTarget::stderr())
+}
+
+/// Gets a progress bar measured in bytes that shows throughput and the
+/// estimated time remaining.
+pub fn progress_bytes(len: u64) -> ProgressBar {
+    ProgressBar::with_draw_target(len, ProgressDrawTarget::stderr()).with_style(
+        ProgressStyle::default_bar()
+            .template("{wide_bar} {bytes}/{total_bytes} ({bytes_per_sec}, {eta} remaining)"),
+    )
+}
+
+/// Prints a path quoted for a shell, such that it is a single word.
+///
+/// POSIX paths are left as is if they contain only characters that are never
+/// special to shells and are otherwise enclosed in single quotes. PowerShell
+/// paths are always enclosed in single quotes. Within single quotes, any
+/// quotes in the path are escaped.
+pub fn print_shell_quoted(
+    path: &Path,
+    quoting: Quoting,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    fn is_posix_safe(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || b"@%+=:,./-_".contains(&byte)
+    }
+
+    #[cfg(unix)]
+    fn bytes(path: &Path) -> Cow<'_, [u8]> {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        path.as_os_str().as_bytes().into()
+    }
+
+    #[cfg(not(unix))]
+    fn bytes(path: &Path) -> Cow<'_, [u8]> {
+        path.to_string_lossy().into_owned().into_bytes().into()
+    }
+
+    match quoting {
+        Quoting::Posix => {
+            let bytes = bytes(path);
+            if !bytes.is_empty() && bytes.iter().copied().all(is_posix_safe) {
+                output.write_all(bytes.as_ref())?;
+            }
+            else {
+                output.write_all(b"'")?;
+                for (n, part) in bytes.split(|byte| *byte == b'\'').enumerate() {
+                    if n > 0 {
+                        output.write_all(b"'\\''")?;
+                    }
+                    output.write_all(part)?;
+                }
+                output.write_all(b"'")?;
+            }
+        }
+        Quoting::PowerShell => {
+            // PowerShell also accepts typographic single quotes as delimiters,
+            // so these are escaped (doubled) too.
+            let text = path.to_string_lossy();
+            output.write_all(b"'")?;
+            for c in text.chars() {
+                if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+                    write!(output, "{}", c)?;
+                }
+                write!(output, "{}", c)?;
+            }
+            output.write_all(b"'")?;
+        }
+    }
+    writeln!(output)
+}
+
+pub fn print_null_terminated(path: &Path, output: &mut impl Write) -> io::Result<()> {
+    #[cfg(unix)]
+    fn bytes(path: &Path) -> Cow<'_, [u8]> {
+        use std::os::unix::ffi::OsStrExt as _;
+
+        path.as_os_str().as_bytes().into()
+    }
+
+    #[cfg(not(unix))]
+    fn bytes(path: &Path) -> Cow<'_, [u8]> {
+        path.to_string_lossy().into_owned().into_bytes().into()
+    }
+
+    output.write_all(bytes(path).as_ref())?;
+    output.write_all(b"\0")
+}
+
+/// Prints the file type, size, and modification time of a matched file
+/// followed by its path.
+pub fn print_long(
+    entry: &WalkEntry<'_>,
+    form: &PathForm,
+    output: &mut (impl Page + Write),
+) -> io::Result<()> {
+    let metadata = entry.metadata().ok();
+    let len = metadata.as_ref().map_or_else(
+        || "-".to_owned(),
+        |metadata| HumanBytes(metadata.len()).to_string(),
+    );
+    let modified = metadata
+        .and_then(|metadata| metadata.modified().ok())
+        .map_or_else(
+            || "-".to_owned(),
+            |time| {
+                DateTime::<Local>::from(time)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            },
+        );
+    write!(
+        output,
+        "{: <9} {: >10} {: >16} ",
+        file_type_name(entry.file_type()),
+        len,
+        modified,
+    )?;
+    form.apply(entry.path()).as_ref().print(output)
+}
+
+pub fn print_json(
+    entry: &WalkEntry<'_>,
+    form: &PathForm,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let file_type = file_type_name(entry.file_type());
+    let captures: Vec<_> = entry
+        .captures()
+        .iter()
+        .map(|capture| capture.map(String::from_utf8_lossy))
+        .collect();
+    writeln!(
+        output,
+        "{}",
+        json!({
+            "path": form.apply(entry.path()).to_string_lossy(),
+            "depth": entry.depth(),
+            "file_type": file_type,
+            "captures": captures,
+        }),
+    )
+}
+
+pub fn print_manifest_json<M>(manifest: &Manifest<M>, output: &mut impl Write) -> io::Result<()>
+where
+    M: Routing,
+{
+    let routes: Vec<_> = manifest
+        .routes()
+        .map(|route| {
+            let sources: Vec<_> = route
+                .sources()
+                .map(|source| source.to_string_lossy())
+                .collect();
+            json!({
+                "sources": sources,
+                "destination": route.destination().to_string_lossy(),
+            })
+        })
+        .collect();
+    writeln!(output, "{}", json!({ "routes": routes }))
+}
+
+/// Event that occurs while writing a route.
+#[derive(Clone, Copy, Debug)]
+pub enum Event<'e> {
+    /// The route is in the final manifest.
+    Planned,
+    /// The route is being written.
+    Started,
+    /// The route has been written.
+    Finished { bytes: u64 },
+    /// The route could not be written.
+    Failed { error: &'e io::Error },
+    /// The route was not written, because a previous route failed.
+    Skipped,
+    /// The route was not written, because a source changed after the
+    /// manifest was read.
+    Changed,
+}
+
+pub fn print_event_json<M>(
+    event: Event<'_>,
+    route: &Route<M, &Path>,
+    output: &mut impl Write,
+) -> io::Result<()>
+where
+    M: Routing,
+{
+    let sources: Vec<_> = route
+        .sources()
+        .map(|source| source.to_string_lossy())
+        .collect();
+    let destination = route.destination().to_string_lossy();
+    let mut event = match event {
+        Event::Planned => json!({ "event": "planned" }),
+        Event::Started => json!({ "event": "started" }),
+        Event::Finished { bytes } => json!({ "event": "finished", "bytes": bytes }),
+        Event::Failed { error } => json!({ "event": "failed", "error": error.to_string() }),
+        Event::Skipped => json!({ "event": "skipped" }),
+        Event::Changed => json!({ "event": "changed" }),
+    };
+    event["sources"] = json!(sources);
+    event["destination"] = json!(destination);
+    writeln!(output, "{}", event)
+}
+
+/// Summary of writing a manifest.
+#[derive(Clone, Copy, Debug)]
+pub struct Summary {
+    pub label: &'static str,
+    pub written: usize,
+    pub failed: usize,
+    /// Number of routes that were copied rather than linked.
+    pub copied: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+pub fn print_summary(summary: Summary) -> io::Result<()> {
+    let mut output = Terminal::from(Term::stderr());
+    write!(
+        output,
+        "{} {} {}",
+        summary.label,
+        summary.written,
+        if summary.written == 1 {
+            "file"
+        }
+        else {
+            "files"
+        },
+    )?;
+    if summary.copied > 0 {
+        write!(output, " ({} copied instead)", summary.copied)?;
+    }
+    if summary.failed > 0 {
+        write!(output, ", failed {}", summary.failed)?;
+    }
+    writeln!(
+        output,
+        ", {} in {}",
+        HumanBytes(summary.bytes),
+        HumanDuration(summary.elapsed),
+    )
+}
+
+/// Prints the destinations of routes that were copied rather than linked.
+pub fn print_copies(destinations: &[PathBuf]) -> io::Result<()> {
+    let mut output = Terminal::from(Term::stderr());
+    for destination in destinations {
+        writeln!(output, "  copied: {}", destination.display())?;
+    }
+    Ok(())
 }
 
 pub fn warning(warning: impl AsRef<str>) -> io::Result<()> {
     const HEADER: &str = "Warning";
 
+    let theme = theme();
     let mut output = Terminal::from(Term::stderr());
     let margin = HEADER.len() + 2;
     for line in textwrap::wrap(warning.as_ref(), width(&output, margin))
@@ -377,15 +1050,15 @@ pub fn warning(warning: impl AsRef<str>) -> io::Result<()> {
             Position::First(line) | Position::Only(line) => writeln!(
                 output,
                 "{}{} {}",
-                STYLE_WARNING_HEADER.apply_to(HEADER),
-                STYLE_WARNING.apply_to(":"),
-                STYLE_WARNING.apply_to(line),
+                theme.warning_header.apply_to(HEADER),
+                theme.warning.apply_to(":"),
+                theme.warning.apply_to(line),
             ),
             Position::Middle(line) | Position::Last(line) => writeln!(
                 output,
                 "{: <width$}{}",
                 "",
-                STYLE_WARNING.apply_to(line),
+                theme.warning.apply_to(line),
                 width = margin,
             ),
         }?;
@@ -397,7 +1070,7 @@ pub fn warning(warning: impl AsRef<str>) -> io::Result<()> {
 //       redirects from bypassing confirmation prompts, but means that
 //       redirecting `stderr` requires the `--force` flag.
 pub fn confirm(prompt: impl AsRef<str>) -> io::Result<bool> {
-    Confirm::with_theme(&ColorfulTheme::default())
+    Confirm::with_theme(prompt_theme().as_ref())
         .with_prompt(prompt.as_ref())
         .default(false)
         .show_default(true)
@@ -405,6 +1078,36 @@ pub fn confirm(prompt: impl AsRef<str>) -> io::Result<bool> {
         .interact()
 }
 
+/// Prompts for the given count (or "yes") to be typed to continue.
+///
+/// The count may be typed with or without thousands separators. Any other
+/// input declines. Like `confirm`, this fails with an unattended terminal.
+pub fn confirm_count(prompt: impl AsRef<str>, count: usize) -> io::Result<bool> {
+    let text: String = Input::with_theme(prompt_theme().as_ref())
+        .with_prompt(format!(
+            "{} Type {} (or \"yes\") to continue",
+            prompt.as_ref(),
+            count,
+        ))
+        .allow_empty(true)
+        .interact_text()?;
+    let text = text.trim();
+    Ok(text == "yes" || text == count.to_string() || text == separated(count))
+}
+
+/// Gets the theme of interactive prompts.
+///
+/// Prompts are written to standard error and are only styled if color is
+/// enabled for standard error (see `toggle_color_output`).
+pub fn prompt_theme() -> Box<dyn PromptTheme> {
+    if console::colors_enabled_stderr() {
+        Box::new(ColorfulTheme::default())
+    }
+    else {
+        Box::new(SimpleTheme)
+    }
+}
+
 pub fn is_interactive(toggle: Toggle) -> bool {
     match toggle {
         Toggle::Always => true,
@@ -413,23 +1116,83 @@ pub fn is_interactive(toggle: Toggle) -> bool {
     }
 }
 
+/// Enables or disables color and style in output and prompts.
+///
+/// When automatic, colors are enabled based on the CLI colors specification
+/// (https://bixense.com/clicolors/) and `NO_COLOR` (https://no-color.org/):
+/// `CLICOLOR_FORCE` (if set and not `0`) enables colors, `NO_COLOR` (if set
+/// and not empty) or `CLICOLOR=0` disables colors, and otherwise colors are
+/// enabled for outputs that are attended terminals. Standard output and
+/// standard error are considered separately, so prompts, warnings, and
+/// progress bars written to standard error are colored independently of
+/// piped or redirected standard output.
 pub fn toggle_color_output(toggle: Toggle) {
+    fn is_color_enabled(term: &Term) -> bool {
+        let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+        let is_forced = matches!(var("CLICOLOR_FORCE"), Some(value) if value != "0");
+        let is_disabled =
+            var("NO_COLOR").is_some() || matches!(var("CLICOLOR"), Some(value) if value == "0");
+        is_forced || (!is_disabled && term.features().is_attended())
+    }
+
     let (output, error) = match toggle {
         Toggle::Always => (true, true),
-        Toggle::Automatic => {
-            // TODO: `console` does not provide a way to re-enable its
-            //       heuristics for detecting color support. At the time of this
-            //       writing, terminal features always report that color output
-            //       is supported, so this case does nothing. Note that any
-            //       subsequent calls to this function with `Toggle::Automatic`
-            //       will not behave as expected if previously called with
-            //       `Toggle::Always` or `Toggle::Never`.
-            return;
-        }
+        Toggle::Automatic => (
+            is_color_enabled(&Term::stdout()),
+            is_color_enabled(&Term::stderr()),
+        ),
         Toggle::Never => (false, false),
     };
-    console::set_colors_enabled(output);
-    console::set_colors_enabled_stderr(error);
+    console::set_colors_enabled(output && enable_virtual_terminal(&Term::stdout()));
+    console::set_colors_enabled_stderr(error && enable_virtual_terminal(&Term::stderr()));
+}
+
+/// Enables ANSI escape sequences for standard output.
+///
+/// Returns `false` if standard output is an attended console that cannot
+/// interpret escape sequences, in which case output should be plain text.
+pub fn enable_virtual_terminal_output() -> bool {
+    enable_virtual_terminal(&Term::stdout())
+}
+
+// NOTE: On Windows, querying color support enables virtual terminal processing
+//       for the console, which is required to interpret ANSI escape sequences.
+//       This fails for legacy consoles. Unattended streams are not consoles
+//       and are left as is.
+#[cfg(windows)]
+fn enable_virtual_terminal(terminal: &Term) -> bool {
+    let features = terminal.features();
+    !features.is_attended() || features.colors_supported()
+}
+
+#[cfg(not(windows))]
+fn enable_virtual_terminal(_: &Term) -> bool {
+    true
+}
+
+fn file_type_name(file_type: FileType) -> &'static str {
+    if file_type.is_dir() {
+        "directory"
+    }
+    else if file_type.is_symlink() {
+        "symlink"
+    }
+    else {
+        "file"
+    }
+}
+
+/// Formats a number with commas separating groups of thousands.
+fn separated(n: usize) -> String {
+    let digits = n.to_string();
+    let mut text = String::with_capacity(digits.len() + (digits.len() / 3));
+    for (position, digit) in digits.chars().enumerate() {
+        if position > 0 && (digits.len() - position).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
 }
 
 fn width(output: &impl Page, margin: usize) -> usize {
@@ -0,0 +1,133 @@
+use console::Term;
+use dialoguer::{Input, Select};
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use nym::manifest::{Manifest, ManifestError, Routing};
+
+use crate::terminal;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TuiError {
+    #[error("failed to interact with terminal: {0}")]
+    Io(io::Error),
+    #[error("failed to insert route: {0}")]
+    RouteInsertion(ManifestError),
+}
+
+impl From<io::Error> for TuiError {
+    fn from(error: io::Error) -> Self {
+        TuiError::Io(error)
+    }
+}
+
+struct Entry {
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+    is_included: bool,
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        format!(
+            "[{}] {} → {}",
+            if self.is_included { "x" } else { " " },
+            self.sources
+                .iter()
+                .map(|source| source.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.destination.display(),
+        )
+    }
+}
+
+/// Presents the routes of a manifest in an interactive list.
+///
+/// The list is scrollable and selecting a route toggles it or edits its
+/// destination. Returns a manifest of the included routes once the list is
+/// applied or `None` if it is cancelled (including via `Esc` or `q`).
+pub fn select<M>(manifest: &Manifest<M>) -> Result<Option<Manifest<M>>, TuiError>
+where
+    M: Routing,
+{
+    let term = Term::stderr();
+    let theme = terminal::prompt_theme();
+    let mut entries: Vec<_> = manifest
+        .routes()
+        .map(|route| Entry {
+            sources: route.sources().map(|source| source.to_path_buf()).collect(),
+            destination: route.destination().to_path_buf(),
+            is_included: true,
+        })
+        .collect();
+    let mut cursor = 0;
+    loop {
+        let included = entries.iter().filter(|entry| entry.is_included).count();
+        let mut items: Vec<_> = entries.iter().map(Entry::label).collect();
+        items.push(format!("Apply ({} of {} routes)", included, entries.len()));
+        items.push("Cancel".to_owned());
+        let selection = Select::with_theme(theme.as_ref())
+            .with_prompt("Select a route to toggle or edit it")
+            .items(&items)
+            .default(cursor)
+            .paged(true)
+            .interact_on_opt(&term)?;
+        let index = match selection {
+            Some(index) if index < entries.len() => index,
+            Some(index) if index == entries.len() => {
+                return into_manifest(&entries).map(Some);
+            }
+            _ => {
+                return Ok(None);
+            }
+        };
+        cursor = index;
+        let entry = &mut entries[index];
+        let action = Select::with_theme(theme.as_ref())
+            .with_prompt(entry.label())
+            .items(&[
+                if entry.is_included {
+                    "Exclude"
+                }
+                else {
+                    "Include"
+                },
+                "Edit destination",
+                "Back",
+            ])
+            .default(0)
+            .interact_on_opt(&term)?;
+        match action {
+            Some(0) => {
+                entry.is_included = !entry.is_included;
+            }
+            Some(1) => {
+                let destination: String = Input::with_theme(theme.as_ref())
+                    .with_prompt("Destination")
+                    .with_initial_text(entry.destination.to_string_lossy())
+                    .interact_text_on(&term)?;
+                entry.destination = destination.into();
+                entry.is_included = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn into_manifest<M>(entries: &[Entry]) -> Result<Manifest<M>, TuiError>
+where
+    M: Routing,
+{
+    let mut manifest = Manifest::default();
+    for entry in entries.iter().filter(|entry| entry.is_included) {
+        for source in &entry.sources {
+            manifest
+                .insert(source, &entry.destination)
+                .map_err(TuiError::RouteInsertion)?;
+        }
+    }
+    Ok(manifest)
+}
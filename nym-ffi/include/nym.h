@@ -0,0 +1,77 @@
+/* C interface to Nym patterns.
+ *
+ * Functions return a `NymStatus`. Errors are negative and a message describing
+ * the last error on the calling thread can be read via
+ * `nym_last_error_message`. Strings are null-terminated and UTF-8.
+ */
+
+#ifndef NYM_H
+#define NYM_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum NymStatus {
+    NYM_OK = 0,
+    /* A path does not match a glob. */
+    NYM_NO_MATCH = 1,
+    /* A required pointer is null. */
+    NYM_ERROR_NULL = -1,
+    /* Text is not valid UTF-8 or a resolved path contains a null character. */
+    NYM_ERROR_ENCODING = -2,
+    /* A glob or to-pattern could not be parsed or compiled. */
+    NYM_ERROR_PARSE = -3,
+    /* A glob is parsed but violates a rule, such as adjacent tree wildcards. */
+    NYM_ERROR_RULE = -4,
+    /* A to-pattern could not be resolved, such as when a property of a file
+     * cannot be read. */
+    NYM_ERROR_RESOLVE = -5,
+    /* An unexpected error occurred. */
+    NYM_ERROR_PANIC = -6,
+} NymStatus;
+
+/* Glob parsed via `nym_glob_parse`. Must be freed via `nym_glob_free`. */
+typedef struct NymGlob NymGlob;
+
+/* Parses a glob. On success, `glob` is set to a glob that must be freed via
+ * `nym_glob_free`. */
+NymStatus nym_glob_parse(const char *text, NymGlob **glob);
+
+/* Frees a glob. Does nothing if `glob` is null. */
+void nym_glob_free(NymGlob *glob);
+
+/* Matches a path against a glob. Returns `NYM_OK` if the path matches,
+ * `NYM_NO_MATCH` if it does not, and `NYM_ERROR_PARSE` if the glob fails to
+ * compile. */
+NymStatus nym_glob_match(const NymGlob *glob, const char *path);
+
+/* Resolves a to-pattern using the captures of a path matched by a glob.
+ *
+ * `position` is the output of the `{!n}` property. Properties that read files,
+ * such as `{!b3sum}`, read the file at `path` relative to the working
+ * directory. Returns `NYM_NO_MATCH` if the path does not match the glob. On
+ * success, `output` is set to the resolved path, which must be freed via
+ * `nym_string_free`. */
+NymStatus nym_topattern_resolve(
+    const char *to,
+    const NymGlob *glob,
+    const char *path,
+    size_t position,
+    char **output);
+
+/* Frees a string returned by this interface. Does nothing if `text` is null. */
+void nym_string_free(char *text);
+
+/* Gets a message describing the last error on the calling thread or null if
+ * the last call succeeded. The message is valid until the next call on the
+ * same thread and must not be freed. */
+const char *nym_last_error_message(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NYM_H */
@@ -0,0 +1,321 @@
+//! C interface to Nym patterns.
+//!
+//! This crate exposes parsing and matching of globs and resolution of
+//! to-patterns to other languages, so that their semantics need not be
+//! approximated. See `include/nym.h` for the corresponding declarations.
+//!
+//! Functions return a `NymStatus` and never unwind into callers. When a
+//! function fails, a message describing the error can be read via
+//! `nym_last_error_message` on the same thread.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, NulError};
+use std::os::raw::c_char;
+use std::panic::{self, UnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::str::Utf8Error;
+use thiserror::Error;
+
+use nym::glob::{BytePath, Glob, GlobError};
+use nym::pattern::{PatternError, Resolution, ToPattern};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Result of a function in the C interface.
+///
+/// Errors are negative.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub enum NymStatus {
+    Ok = 0,
+    /// A path does not match a glob.
+    NoMatch = 1,
+    /// A required pointer is null.
+    ErrorNull = -1,
+    /// Text is not valid UTF-8 or a resolved path contains a null character.
+    ErrorEncoding = -2,
+    /// A glob or to-pattern could not be parsed or compiled.
+    ErrorParse = -3,
+    /// A glob is parsed but violates a rule, such as adjacent tree wildcards.
+    ErrorRule = -4,
+    /// A to-pattern could not be resolved, such as when a property of a file
+    /// cannot be read.
+    ErrorResolve = -5,
+    /// An unexpected error occurred.
+    ErrorPanic = -6,
+}
+
+#[derive(Debug, Error)]
+enum FfiError {
+    #[error("unexpected null pointer")]
+    Null,
+    #[error("text is not valid UTF-8: {0}")]
+    Encoding(Utf8Error),
+    #[error("resolved path contains a null character: {0}")]
+    Nul(NulError),
+    #[error("{0}")]
+    Glob(GlobError),
+    #[error("{0}")]
+    Pattern(PatternError),
+}
+
+impl FfiError {
+    fn status(&self) -> NymStatus {
+        match *self {
+            FfiError::Null => NymStatus::ErrorNull,
+            FfiError::Encoding(_) | FfiError::Nul(_) => NymStatus::ErrorEncoding,
+            FfiError::Glob(GlobError::Rule(_)) => NymStatus::ErrorRule,
+            FfiError::Glob(_) | FfiError::Pattern(PatternError::Parse(_)) => NymStatus::ErrorParse,
+            FfiError::Pattern(_) => NymStatus::ErrorResolve,
+        }
+    }
+}
+
+/// Glob that has been parsed via `nym_glob_parse`.
+///
+/// This type is opaque to C and must be freed via `nym_glob_free`.
+#[derive(Debug)]
+pub struct NymGlob(Glob<'static>);
+
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', " "))
+        .expect("failed to encode error message");
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Calls a function and converts its output, errors, and panics into a status.
+fn call<F>(f: F) -> NymStatus
+where
+    F: FnOnce() -> Result<NymStatus, FfiError> + UnwindSafe,
+{
+    match panic::catch_unwind(f) {
+        Ok(Ok(status)) => {
+            LAST_ERROR.with(|error| error.borrow_mut().take());
+            status
+        }
+        Ok(Err(error)) => {
+            set_last_error(&error);
+            error.status()
+        }
+        Err(_) => {
+            set_last_error("unexpected panic");
+            NymStatus::ErrorPanic
+        }
+    }
+}
+
+unsafe fn to_str<'a>(text: *const c_char) -> Result<&'a str, FfiError> {
+    if text.is_null() {
+        Err(FfiError::Null)
+    }
+    else {
+        CStr::from_ptr(text).to_str().map_err(FfiError::Encoding)
+    }
+}
+
+unsafe fn to_glob<'a>(glob: *const NymGlob) -> Result<&'a Glob<'static>, FfiError> {
+    glob.as_ref().map(|glob| &glob.0).ok_or(FfiError::Null)
+}
+
+/// Parses a glob.
+///
+/// On success, `glob` is set to a glob that must be freed via `nym_glob_free`.
+///
+/// # Safety
+///
+/// `text` must be null or a null-terminated string and `glob` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nym_glob_parse(text: *const c_char, glob: *mut *mut NymGlob) -> NymStatus {
+    call(|| {
+        let text = to_str(text)?;
+        if glob.is_null() {
+            return Err(FfiError::Null);
+        }
+        let parsed = Glob::new(text)
+            .map(Glob::into_owned)
+            .map_err(FfiError::Glob)?;
+        *glob = Box::into_raw(Box::new(NymGlob(parsed)));
+        Ok(NymStatus::Ok)
+    })
+}
+
+/// Frees a glob. Does nothing if `glob` is null.
+///
+/// # Safety
+///
+/// `glob` must be null or a glob from `nym_glob_parse` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn nym_glob_free(glob: *mut NymGlob) {
+    if !glob.is_null() {
+        drop(Box::from_raw(glob));
+    }
+}
+
+/// Matches a path against a glob.
+///
+/// Returns `NYM_OK` if the path matches, `NYM_NO_MATCH` if it does not, and
+/// `NYM_ERROR_PARSE` if the glob fails to compile.
+///
+/// # Safety
+///
+/// `glob` must be null or a glob from `nym_glob_parse` and `path` must be null
+/// or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nym_glob_match(glob: *const NymGlob, path: *const c_char) -> NymStatus {
+    call(|| {
+        let glob = to_glob(glob)?;
+        let path = to_str(path)?;
+        Ok(if glob.is_match(path).map_err(FfiError::Glob)? {
+            NymStatus::Ok
+        }
+        else {
+            NymStatus::NoMatch
+        })
+    })
+}
+
+/// Resolves a to-pattern using the captures of a path matched by a glob.
+///
+/// `position` is the position of the path among all matched paths and is the
+/// output of the `{!n}` property. Properties that read files, such as
+/// `{!b3sum}`, read the file at `path` relative to the working directory.
+/// Returns `NYM_NO_MATCH` if the path does not match the glob. On success,
+/// `output` is set to the resolved path, which must be freed via
+/// `nym_string_free`.
+///
+/// # Safety
+///
+/// `to` and `path` must be null or null-terminated strings, `glob` must be null
+/// or a glob from `nym_glob_parse`, and `output` must be null or valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn nym_topattern_resolve(
+    to: *const c_char,
+    glob: *const NymGlob,
+    path: *const c_char,
+    position: usize,
+    output: *mut *mut c_char,
+) -> NymStatus {
+    call(|| {
+        let to = ToPattern::new(to_str(to)?).map_err(FfiError::Pattern)?;
+        let glob = to_glob(glob)?;
+        let path = to_str(path)?;
+        if output.is_null() {
+            return Err(FfiError::Null);
+        }
+        let source = BytePath::from_path(path);
+        let captures = match glob.captures(&source).map_err(FfiError::Glob)? {
+            Some(captures) => captures,
+            None => {
+                return Ok(NymStatus::NoMatch);
+            }
+        };
+        let resolved = to
+            .resolve(Resolution {
+                source: Path::new(path),
+                directory: Path::new(""),
+                captures: &captures,
+                position,
+            })
+            .map_err(FfiError::Pattern)?;
+        *output = CString::new(resolved).map_err(FfiError::Nul)?.into_raw();
+        Ok(NymStatus::Ok)
+    })
+}
+
+/// Frees a string returned by this interface. Does nothing if `text` is null.
+///
+/// # Safety
+///
+/// `text` must be null or a string from `nym_topattern_resolve` that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn nym_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Gets a message describing the last error on the calling thread.
+///
+/// Returns null if the last call on the calling thread succeeded. The message
+/// is owned by this interface and is valid until the next call on the same
+/// thread.
+#[no_mangle]
+pub extern "C" fn nym_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use crate::{
+        nym_glob_free, nym_glob_match, nym_glob_parse, nym_last_error_message, nym_string_free,
+        nym_topattern_resolve, NymStatus,
+    };
+
+    #[test]
+    fn match_glob() {
+        unsafe {
+            let text = CString::new("src/**/*.rs").unwrap();
+            let mut glob = ptr::null_mut();
+            assert_eq!(nym_glob_parse(text.as_ptr(), &mut glob), NymStatus::Ok);
+
+            let path = CString::new("src/glob/mod.rs").unwrap();
+            assert_eq!(nym_glob_match(glob, path.as_ptr()), NymStatus::Ok);
+            let path = CString::new("doc/nym.svg").unwrap();
+            assert_eq!(nym_glob_match(glob, path.as_ptr()), NymStatus::NoMatch);
+            assert!(nym_last_error_message().is_null());
+
+            nym_glob_free(glob);
+        }
+    }
+
+    #[test]
+    fn resolve_to_pattern() {
+        unsafe {
+            let text = CString::new("*.*").unwrap();
+            let mut glob = ptr::null_mut();
+            assert_eq!(nym_glob_parse(text.as_ptr(), &mut glob), NymStatus::Ok);
+
+            let to = CString::new("{#2}.{#1}").unwrap();
+            let path = CString::new("a.b").unwrap();
+            let mut output = ptr::null_mut();
+            assert_eq!(
+                nym_topattern_resolve(to.as_ptr(), glob, path.as_ptr(), 1, &mut output),
+                NymStatus::Ok,
+            );
+            assert_eq!(CStr::from_ptr(output).to_str().unwrap(), "b.a");
+
+            nym_string_free(output);
+            nym_glob_free(glob);
+        }
+    }
+
+    #[test]
+    fn reject_glob_with_error() {
+        unsafe {
+            let text = CString::new("a/{b,c").unwrap();
+            let mut glob = ptr::null_mut();
+            assert_eq!(
+                nym_glob_parse(text.as_ptr(), &mut glob),
+                NymStatus::ErrorParse
+            );
+            assert!(glob.is_null());
+            assert!(!nym_last_error_message().is_null());
+
+            assert_eq!(nym_glob_parse(ptr::null(), &mut glob), NymStatus::ErrorNull);
+        }
+    }
+}
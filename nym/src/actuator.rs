@@ -1,19 +1,37 @@
+use filetime::FileTime;
 use itertools::Itertools as _;
-use std::fs;
-use std::io::{self, Error, ErrorKind};
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read, Write};
 use std::path::Path;
+use tracing::{debug, warn};
 
+use crate::cancel::Cancellation;
 use crate::environment::Environment;
 use crate::manifest::{Bijective, Route, Routing};
+use crate::report::Reporter;
 
 #[derive(Clone, Debug)]
 pub struct Actuator<'e> {
     environment: &'e Environment,
+    cancellation: Option<Cancellation>,
 }
 
 impl<'e> Actuator<'e> {
     pub(in crate) fn new(environment: &'e Environment) -> Self {
-        Actuator { environment }
+        Actuator {
+            environment,
+            cancellation: None,
+        }
+    }
+
+    /// Refuses to write routes when a `Cancellation` is cancelled.
+    ///
+    /// Routes that are being written when cancelled are completed. Once
+    /// cancelled, writes fail with `io::ErrorKind::Interrupted` without
+    /// modifying any files.
+    pub fn with_cancellation(mut self, cancellation: &Cancellation) -> Self {
+        self.cancellation = Some(cancellation.clone());
+        self
     }
 
     pub fn write<A, P>(&self, route: Route<A::Routing, P>) -> io::Result<()>
@@ -21,27 +39,134 @@ impl<'e> Actuator<'e> {
         A: Operation,
         P: AsRef<Path>,
     {
+        self.write_route::<A, P, fn(u64), _>(route, None, &())
+    }
+
+    /// Writes a route and calls a closure with the number of bytes written as
+    /// file data is written.
+    ///
+    /// Progress is only reported by operations that write file data (see
+    /// `Operation::IS_DATA_WRITTEN`). Such operations write file data in
+    /// chunks to report progress, which forgoes platform features like
+    /// copy-on-write, so prefer `write` when progress is not needed.
+    pub fn write_with_progress<A, P, F>(&self, route: Route<A::Routing, P>, f: F) -> io::Result<()>
+    where
+        A: Operation,
+        P: AsRef<Path>,
+        F: FnMut(u64),
+    {
+        self.write_route::<A, P, _, _>(route, Some(f), &())
+    }
+
+    /// Writes a route and reports progress and the outcome to a `Reporter`.
+    ///
+    /// Progress is only reported by operations that write file data (see
+    /// `Operation::IS_DATA_WRITTEN`) and only if the reporter requests it (see
+    /// `Reporter::is_progress_reported`).
+    pub fn write_with_reporter<A, P, R>(
+        &self,
+        route: Route<A::Routing, P>,
+        reporter: &R,
+    ) -> io::Result<()>
+    where
+        A: Operation,
+        P: AsRef<Path>,
+        R: Reporter + ?Sized,
+    {
+        let destination = route.destination().as_ref().to_path_buf();
+        let f = reporter
+            .is_progress_reported()
+            .then_some(|n| reporter.progressed(&destination, n));
+        self.write_route::<A, P, _, _>(route, f, reporter)
+    }
+
+    fn write_route<A, P, F, R>(
+        &self,
+        route: Route<A::Routing, P>,
+        f: Option<F>,
+        reporter: &R,
+    ) -> io::Result<()>
+    where
+        A: Operation,
+        P: AsRef<Path>,
+        F: FnMut(u64),
+        R: Reporter + ?Sized,
+    {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+        {
+            return Err(Error::new(
+                ErrorKind::Interrupted,
+                "actuation has been cancelled",
+            ));
+        }
         let policy = self.environment.policy();
         if policy.parents {
-            let parent = route
-                .destination()
-                .as_ref()
-                .parent()
-                .expect("destination path has no parent");
+            let parent = route.destination().as_ref().parent().ok_or_else(|| {
+                Error::new(ErrorKind::InvalidInput, "destination path has no parent")
+            })?;
             if !parent.exists() {
                 fs::create_dir_all(parent)?;
             }
         }
-        A::write(route)
+        let destination = route.destination().as_ref().to_path_buf();
+        let source = if policy.preserve && A::IS_METADATA_COPIED {
+            Some(exactly_one_source(&route)?.as_ref().to_path_buf())
+        }
+        else {
+            None
+        };
+        let result = match f {
+            Some(f) => A::write_with_progress(route, f),
+            None => A::write(route),
+        }
+        .and_then(|_| match source {
+            Some(ref source) => copy_metadata(source, &destination),
+            None => Ok(()),
+        });
+        match result {
+            Ok(_) => debug!(destination = %destination.display(), "wrote route"),
+            Err(ref error) => {
+                warn!(destination = %destination.display(), %error, "failed to write route")
+            }
+        }
+        reporter.written(&destination, result.as_ref().map(|_| ()));
+        result
     }
 }
 
 pub trait Operation {
     type Routing: Routing;
 
+    /// Determines if metadata is copied when preserved by the policy.
+    ///
+    /// Operations like moves and links share metadata with their sources, so
+    /// this is only meaningful for operations that write new files.
+    const IS_METADATA_COPIED: bool = false;
+
+    /// Determines if file data is written, in which case progress can be
+    /// measured in bytes.
+    const IS_DATA_WRITTEN: bool = false;
+
     fn write<P>(route: Route<Self::Routing, P>) -> io::Result<()>
     where
         P: AsRef<Path>;
+
+    /// Writes a route and calls a closure with the number of bytes written as
+    /// file data is written.
+    ///
+    /// By default, this writes the route via `write` and never calls the
+    /// closure.
+    fn write_with_progress<P, F>(route: Route<Self::Routing, P>, f: F) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64),
+    {
+        let _ = f;
+        Self::write(route)
+    }
 }
 
 // TODO: How useful is appending? Perhaps this need not be supported at all.
@@ -52,12 +177,48 @@ pub enum Copy {}
 impl Operation for Copy {
     type Routing = Bijective;
 
+    const IS_METADATA_COPIED: bool = true;
+
+    const IS_DATA_WRITTEN: bool = true;
+
     fn write<P>(route: Route<Self::Routing, P>) -> io::Result<()>
     where
         P: AsRef<Path>,
     {
         fs::copy(exactly_one_source(&route)?, route.destination()).map(|_| ())
     }
+
+    // Unlike `write`, this copies file data in chunks and so cannot take
+    // advantage of platform features like copy-on-write.
+    fn write_with_progress<P, F>(route: Route<Self::Routing, P>, mut f: F) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64),
+    {
+        const CHUNK_SIZE: usize = 128 * 1024;
+
+        let mut source = File::open(exactly_one_source(&route)?)?;
+        let permissions = source.metadata()?.permissions();
+        let mut destination = File::create(route.destination())?;
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            let n = match source.read(&mut chunk) {
+                Ok(0) => {
+                    break;
+                }
+                Ok(n) => n,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {
+                    continue;
+                }
+                Err(error) => {
+                    return Err(error);
+                }
+            };
+            destination.write_all(&chunk[..n])?;
+            f(n as u64);
+        }
+        destination.set_permissions(permissions)
+    }
 }
 
 pub enum HardLink {}
@@ -73,6 +234,34 @@ impl Operation for HardLink {
     }
 }
 
+/// Hard links or, if the destination is on a different file system than its
+/// source (where hard links are impossible), copies.
+pub enum HardLinkOrCopy {}
+
+impl Operation for HardLinkOrCopy {
+    type Routing = Bijective;
+
+    // Copying metadata to a hard link has no effect, because it shares
+    // metadata with its source.
+    const IS_METADATA_COPIED: bool = true;
+
+    fn write<P>(route: Route<Self::Routing, P>) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        match fs::hard_link(exactly_one_source(&route)?, route.destination()) {
+            Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+                debug!(
+                    destination = %route.destination().as_ref().display(),
+                    "hard link crosses file systems; copying instead",
+                );
+                Copy::write(route)
+            }
+            result => result,
+        }
+    }
+}
+
 pub enum SoftLink {}
 
 #[cfg(unix)]
@@ -116,6 +305,24 @@ impl Operation for Move {
     }
 }
 
+fn copy_metadata(source: &Path, destination: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+    #[cfg(unix)]
+    for name in xattr::list(source)? {
+        if let Some(value) = xattr::get(source, &name)? {
+            xattr::set(destination, &name, &value)?;
+        }
+    }
+    // Permissions are set after extended attributes, which may not be
+    // writable if the source is read-only.
+    fs::set_permissions(destination, metadata.permissions())?;
+    filetime::set_file_times(
+        destination,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+}
+
 fn exactly_one_source<R, P>(route: &Route<R, P>) -> io::Result<&P>
 where
     R: Routing,
@@ -124,5 +331,5 @@ where
     route
         .sources()
         .exactly_one()
-        .map_err(|_| Error::new(ErrorKind::Other, "no source paths"))
+        .map_err(|_| Error::other("no source paths"))
 }
@@ -0,0 +1,31 @@
+//! Cooperative cancellation of reads and writes.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Token that cancels walks, transforms, and actuators.
+///
+/// Clones share the same state, so a clone can be cancelled from another
+/// thread (such as a GUI event loop) while work proceeds. Work stops at the
+/// next safe point: walks stop yielding entries, transforms return a manifest
+/// of the routes that have been read so far, and actuators refuse to write any
+/// further routes. Routes are never interrupted while being written.
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Cancellation::default()
+    }
+
+    /// Cancels work. This cannot be undone.
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Relaxed)
+    }
+}
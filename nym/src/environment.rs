@@ -1,11 +1,61 @@
 use crate::actuator::Actuator;
+use crate::manifest::{Manifest, Routing};
 use crate::pattern::{FromPattern, ToPattern};
-use crate::transform::Transform;
+use crate::transform::{self, Transform, TransformBuilder, TransformError};
+
+/// Determines if and when existing destination files are overwritten.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Overwrite {
+    /// Existing destination files are always overwritten.
+    Always,
+    /// Existing destination files are never overwritten and routes to them
+    /// are rejected.
+    Never,
+    /// Existing destination files are overwritten if they have been modified
+    /// less recently than their sources. Other routes to existing files are
+    /// skipped.
+    Newer,
+    /// Existing destination files are overwritten if their sizes differ from
+    /// their sources. Other routes to existing files are skipped.
+    SizeDiffers,
+}
 
 #[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
 pub struct Policy {
     pub parents: bool,
-    pub overwrite: bool,
+    pub overwrite: Overwrite,
+    /// Determines if metadata is copied from sources to destinations.
+    ///
+    /// Metadata includes access and modification times, permissions, and (on
+    /// Unix) extended attributes. This only applies to operations that write
+    /// new files, such as copies.
+    pub preserve: bool,
+    /// Determines if routes to destinations that are identical to their
+    /// sources are skipped.
+    ///
+    /// Destinations are identical if they have the same size and content as
+    /// their sources. Such routes are skipped regardless of `overwrite`.
+    pub skip_identical: bool,
+    /// Determines if sources are merged into existing destination
+    /// directories.
+    ///
+    /// If a destination is an existing directory, then the source is routed
+    /// into that directory with its file name and `overwrite` applies to the
+    /// resulting path. Otherwise, such routes are rejected.
+    pub merge: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            parents: false,
+            overwrite: Overwrite::Never,
+            preserve: false,
+            skip_identical: false,
+            merge: false,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -26,6 +76,16 @@ impl Environment {
         Transform::new(self, from, to)
     }
 
+    /// Constructs a `TransformBuilder`, which configures the traversal of a
+    /// transform along with the transform itself.
+    pub fn transform_builder<'f, 't>(
+        &self,
+        from: FromPattern<'f>,
+        to: ToPattern<'t>,
+    ) -> TransformBuilder<'_, 'f, 't> {
+        TransformBuilder::new(self, from, to)
+    }
+
     pub fn actuator(&self) -> Actuator<'_> {
         Actuator::new(self)
     }
@@ -33,4 +93,28 @@ impl Environment {
     pub fn policy(&self) -> &Policy {
         &self.policy
     }
+
+    /// Verifies that the routes in a manifest are consistent with the policy.
+    ///
+    /// Manifests read by a `Transform` are always verified. This function is
+    /// useful for manifests that are constructed by other means or that may be
+    /// stale, such as manifests that have been read from a file.
+    ///
+    /// Routes that a `Transform` would skip (such as routes to newer files
+    /// when using `Overwrite::Newer`) are rejected.
+    pub fn verify<M>(&self, manifest: &Manifest<M>) -> Result<(), TransformError>
+    where
+        M: Routing,
+    {
+        for route in manifest.routes() {
+            for source in route.sources() {
+                if !transform::verify_route_policy(&self.policy, source, route.destination())? {
+                    return Err(TransformError::DestinationAlreadyExists(
+                        route.destination().into(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,50 @@
+//! Categories of errors.
+//!
+//! Each error type provides a `kind` function that gets its `ErrorKind` and a
+//! `path` function that gets the path that it concerns (if any), so that
+//! errors can be handled by category rather than by their variants or
+//! messages.
+
+use std::io;
+
+/// Category of an error.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Text could not be parsed, such as a glob or to-pattern.
+    Parse,
+    /// A pattern or path is well-formed but invalid, such as a glob that
+    /// violates a rule, a to-pattern that refers to a capture that does not
+    /// exist, or a destination that has no file name.
+    Invalid,
+    /// Text or a path is not valid UTF-8.
+    Encoding,
+    /// A file or directory does not exist.
+    NotFound,
+    /// Permission to read or write a file or directory is denied.
+    PermissionDenied,
+    /// A destination already exists.
+    AlreadyExists,
+    /// More than one source is routed to the same destination.
+    Collision,
+    /// A path is a directory where a file is expected.
+    NotAFile,
+    /// A symbolic link loop has been detected.
+    LinkLoop,
+    /// An I/O error that is not otherwise categorized.
+    Io,
+    /// An error that is not otherwise categorized.
+    Other,
+}
+
+impl ErrorKind {
+    pub(in crate) fn from_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => ErrorKind::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            io::ErrorKind::InvalidData => ErrorKind::Encoding,
+            _ => ErrorKind::Io,
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Predicates that filter matched files.
+//!
+//! Filters are applied to the metadata of files as they are matched by a
+//! from-pattern, such as via `WalkBehavior::filter`.
+
+use chrono::offset::Local;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+use std::fs::Metadata;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+use crate::error::ErrorKind;
+
+#[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
+#[non_exhaustive]
+pub enum FilterError {
+    #[error("failed to parse size: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::filter::size),
+            help("sizes are signed numbers with an optional unit, like `+10M` or `-500k`")
+        )
+    )]
+    Size(String),
+    #[error("failed to parse time: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::filter::time),
+            help(
+                "times are durations before now, like `30d` or `1h30m`, or timestamps, like \
+                 `2021-06-01` or `2021-06-01T12:00:00`"
+            )
+        )
+    )]
+    Time(String),
+}
+
+impl FilterError {
+    /// Gets the category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            FilterError::Size(_) | FilterError::Time(_) => ErrorKind::Parse,
+        }
+    }
+}
+
+/// Bound on the size of files in bytes.
+///
+/// Size filters are parsed from text like `+10M` (larger than ten mebibytes)
+/// and `-500k` (smaller than five hundred kibibytes). Units are powers of
+/// 1024 and are case insensitive: `k`, `M`, `G`, and `T`. Sizes without a
+/// unit are in bytes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SizeFilter {
+    /// Matches files that are larger than the given size.
+    Larger(u64),
+    /// Matches files that are smaller than the given size.
+    Smaller(u64),
+}
+
+impl SizeFilter {
+    /// Determines if a size is within the bound.
+    pub fn contains(&self, size: u64) -> bool {
+        match *self {
+            SizeFilter::Larger(bound) => size > bound,
+            SizeFilter::Smaller(bound) => size < bound,
+        }
+    }
+
+    /// Determines if the size in the given metadata is within the bound.
+    pub fn is_match(&self, metadata: &Metadata) -> bool {
+        self.contains(metadata.len())
+    }
+}
+
+impl FromStr for SizeFilter {
+    type Err = FilterError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || FilterError::Size(text.into());
+        let (filter, size): (fn(u64) -> SizeFilter, _) = match text.bytes().next() {
+            Some(b'+') => (SizeFilter::Larger, &text[1..]),
+            Some(b'-') => (SizeFilter::Smaller, &text[1..]),
+            _ => {
+                return Err(error());
+            }
+        };
+        let (size, exponent) = match size.chars().last().map(|unit| unit.to_ascii_lowercase()) {
+            Some('k') => (&size[..size.len() - 1], 1),
+            Some('m') => (&size[..size.len() - 1], 2),
+            Some('g') => (&size[..size.len() - 1], 3),
+            Some('t') => (&size[..size.len() - 1], 4),
+            _ => (size, 0),
+        };
+        if size.is_empty() || !size.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(error());
+        }
+        size.parse::<u64>()
+            .ok()
+            .and_then(|size| size.checked_mul(1024u64.pow(exponent)))
+            .map(filter)
+            .ok_or_else(error)
+    }
+}
+
+/// Bound on the modification time of files.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TimeFilter {
+    /// Matches files that have been modified more recently than the given
+    /// time.
+    Newer(SystemTime),
+    /// Matches files that have been modified less recently than the given
+    /// time.
+    Older(SystemTime),
+}
+
+impl TimeFilter {
+    /// Determines if a time is within the bound.
+    pub fn contains(&self, time: SystemTime) -> bool {
+        match *self {
+            TimeFilter::Newer(bound) => time > bound,
+            TimeFilter::Older(bound) => time < bound,
+        }
+    }
+
+    /// Determines if the modification time in the given metadata is within
+    /// the bound.
+    ///
+    /// Returns `false` if the modification time is not available.
+    pub fn is_match(&self, metadata: &Metadata) -> bool {
+        metadata.modified().is_ok_and(|time| self.contains(time))
+    }
+}
+
+/// Bound on the ownership of files.
+///
+/// Owners are given by user and group IDs rather than names.
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum OwnerFilter {
+    /// Matches files that are owned by the given user ID.
+    User(u32),
+    /// Matches files that are owned by the given group ID.
+    Group(u32),
+}
+
+#[cfg(unix)]
+impl OwnerFilter {
+    /// Determines if the owner in the given metadata is within the bound.
+    pub fn is_match(&self, metadata: &Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt as _;
+
+        match *self {
+            OwnerFilter::User(id) => metadata.uid() == id,
+            OwnerFilter::Group(id) => metadata.gid() == id,
+        }
+    }
+}
+
+/// Parses a duration before now or a timestamp into a time.
+///
+/// Durations are sequences of numbers and units, like `30d` or `1h30m`. Units
+/// are `s` (seconds), `m` (minutes), `h` (hours), `d` (days), and `w` (weeks).
+/// Durations are relative to the time at which they are parsed.
+///
+/// Timestamps are dates like `2021-06-01`, local date times like
+/// `2021-06-01T12:00:00` or `2021-06-01 12:00`, or RFC 3339 date times with
+/// offsets like `2021-06-01T12:00:00Z`. Dates are interpreted as midnight in
+/// the local time zone.
+pub fn parse_time(text: &str) -> Result<SystemTime, FilterError> {
+    parse_time_at(text, SystemTime::now())
+}
+
+fn parse_time_at(text: &str, now: SystemTime) -> Result<SystemTime, FilterError> {
+    let error = || FilterError::Time(text.into());
+    if let Some(duration) = parse_duration(text) {
+        return now.checked_sub(duration).ok_or_else(error);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.into());
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .and_then(|time| Local.from_local_datetime(&time).earliest())
+    .map(SystemTime::from)
+    .ok_or_else(error)
+}
+
+fn parse_duration(text: &str) -> Option<Duration> {
+    if text.is_empty() {
+        return None;
+    }
+    let mut seconds = 0u64;
+    let mut text = text;
+    while !text.is_empty() {
+        let end = text
+            .find(|x: char| !x.is_ascii_digit())
+            .filter(|end| *end > 0)?;
+        let (n, rest) = text.split_at(end);
+        let mut units = rest.chars();
+        let unit = match units.next()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => {
+                return None;
+            }
+        };
+        seconds = n
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(unit)
+            .and_then(|n| seconds.checked_add(n))?;
+        text = units.as_str();
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::offset::Local;
+    use chrono::TimeZone;
+    use filetime::FileTime;
+    use std::fs::{self, Metadata};
+    use std::time::{Duration, SystemTime};
+
+    use crate::error::ErrorKind;
+    #[cfg(unix)]
+    use crate::filter::OwnerFilter;
+    use crate::filter::{self, SizeFilter, TimeFilter};
+    use crate::fixture::TreeFixture;
+
+    fn metadata(tree: &TreeFixture, path: &str) -> Metadata {
+        fs::metadata(tree.join(path)).unwrap()
+    }
+
+    #[test]
+    fn parse_size_filter() {
+        assert_eq!("+10".parse::<SizeFilter>().unwrap(), SizeFilter::Larger(10));
+        assert_eq!(
+            "+10M".parse::<SizeFilter>().unwrap(),
+            SizeFilter::Larger(10 * 1024 * 1024),
+        );
+        assert_eq!(
+            "-500k".parse::<SizeFilter>().unwrap(),
+            SizeFilter::Smaller(500 * 1024),
+        );
+        assert_eq!(
+            "-1g".parse::<SizeFilter>().unwrap(),
+            SizeFilter::Smaller(1024 * 1024 * 1024),
+        );
+    }
+
+    #[test]
+    fn reject_size_filter() {
+        for text in [
+            "10M",
+            "+",
+            "-k",
+            "+1.5M",
+            "+10X",
+            "+-10",
+            "é10",
+            "+99999999999T",
+        ] {
+            assert_eq!(
+                text.parse::<SizeFilter>().unwrap_err().kind(),
+                ErrorKind::Parse,
+                "parsed `{}`",
+                text,
+            );
+        }
+    }
+
+    #[test]
+    fn match_size_filter() {
+        let tree = TreeFixture::new()
+            .file("small.txt", "a")
+            .file("large.txt", [0u8; 2048]);
+        let (small, large) = (metadata(&tree, "small.txt"), metadata(&tree, "large.txt"));
+        let larger = SizeFilter::Larger(1024);
+        let smaller = SizeFilter::Smaller(1024);
+        assert!(larger.is_match(&large));
+        assert!(!larger.is_match(&small));
+        assert!(smaller.is_match(&small));
+        assert!(!smaller.is_match(&large));
+    }
+
+    #[test]
+    fn parse_time_filter_duration() {
+        let now = SystemTime::now();
+        let ago = |seconds| now - Duration::from_secs(seconds);
+        assert_eq!(filter::parse_time_at("30s", now).unwrap(), ago(30));
+        assert_eq!(filter::parse_time_at("1h30m", now).unwrap(), ago(90 * 60));
+        assert_eq!(
+            filter::parse_time_at("30d", now).unwrap(),
+            ago(30 * 24 * 60 * 60),
+        );
+        assert_eq!(
+            filter::parse_time_at("2w", now).unwrap(),
+            ago(14 * 24 * 60 * 60),
+        );
+    }
+
+    #[test]
+    fn parse_time_filter_timestamp() {
+        let midnight = SystemTime::from(Local.with_ymd_and_hms(2021, 6, 1, 0, 0, 0).unwrap());
+        let noon = SystemTime::from(Local.with_ymd_and_hms(2021, 6, 1, 12, 0, 0).unwrap());
+        assert_eq!(filter::parse_time("2021-06-01").unwrap(), midnight);
+        assert_eq!(filter::parse_time("2021-06-01T12:00:00").unwrap(), noon);
+        assert_eq!(filter::parse_time("2021-06-01 12:00").unwrap(), noon);
+        assert_eq!(
+            filter::parse_time("2021-06-01T12:00:00Z").unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_622_548_800),
+        );
+    }
+
+    #[test]
+    fn reject_time_filter() {
+        for text in [
+            "",
+            "30",
+            "d",
+            "30x",
+            "1h30",
+            "-30d",
+            "2021-13-01",
+            "yesterday",
+        ] {
+            assert_eq!(
+                filter::parse_time(text).unwrap_err().kind(),
+                ErrorKind::Parse,
+                "parsed `{}`",
+                text,
+            );
+        }
+    }
+
+    #[test]
+    fn match_time_filter() {
+        let tree = TreeFixture::new().file("old.txt", "").file("new.txt", "");
+        let now = SystemTime::now();
+        filetime::set_file_mtime(
+            tree.join("old.txt"),
+            FileTime::from_system_time(now - Duration::from_secs(60 * 60)),
+        )
+        .unwrap();
+        let cutoff = now - Duration::from_secs(60);
+        let (old, new) = (metadata(&tree, "old.txt"), metadata(&tree, "new.txt"));
+        let newer = TimeFilter::Newer(cutoff);
+        let older = TimeFilter::Older(cutoff);
+        assert!(newer.is_match(&new));
+        assert!(!newer.is_match(&old));
+        assert!(older.is_match(&old));
+        assert!(!older.is_match(&new));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn match_owner_filter() {
+        use std::os::unix::fs::MetadataExt as _;
+
+        let tree = TreeFixture::new().file("a.txt", "");
+        let metadata = metadata(&tree, "a.txt");
+        assert!(OwnerFilter::User(metadata.uid()).is_match(&metadata));
+        assert!(OwnerFilter::Group(metadata.gid()).is_match(&metadata));
+        assert!(!OwnerFilter::User(metadata.uid().wrapping_add(1)).is_match(&metadata));
+        assert!(!OwnerFilter::Group(metadata.gid().wrapping_add(1)).is_match(&metadata));
+    }
+}
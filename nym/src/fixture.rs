@@ -0,0 +1,154 @@
+//! Temporary directory trees for tests.
+//!
+//! This module is available with the `test-util` feature. A `TreeFixture`
+//! builds a directory tree in a temporary directory that is removed when the
+//! fixture is dropped, and provides assertions against the tree and the
+//! manifests read from it.
+//!
+//! Functions in this module panic rather than return errors, because they are
+//! intended for use in tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::manifest::{Manifest, Routing};
+
+/// Directory tree in a temporary directory.
+///
+/// Paths given to a fixture are relative to its root directory. Trees are
+/// built by chaining functions, such as
+/// `TreeFixture::new().file("a/b.txt", "text").dir("c")`.
+#[derive(Debug)]
+pub struct TreeFixture {
+    root: TempDir,
+}
+
+impl TreeFixture {
+    /// Creates an empty tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the temporary directory cannot be created.
+    pub fn new() -> Self {
+        TreeFixture {
+            root: tempfile::Builder::new()
+                .prefix("nym-fixture-")
+                .tempdir()
+                .expect("failed to create fixture directory"),
+        }
+    }
+
+    /// Writes a file and any missing parent directories.
+    pub fn file(self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Self {
+        let path = self.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("failed to create fixture directory");
+        }
+        fs::write(&path, contents).expect("failed to write fixture file");
+        self
+    }
+
+    /// Creates a directory and any missing parent directories.
+    pub fn dir(self, path: impl AsRef<Path>) -> Self {
+        fs::create_dir_all(self.join(path)).expect("failed to create fixture directory");
+        self
+    }
+
+    /// Gets the root directory of the tree.
+    pub fn path(&self) -> &Path {
+        self.root.path()
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path().join(path)
+    }
+
+    /// Reads the contents of a file as a string.
+    pub fn read(&self, path: impl AsRef<Path>) -> String {
+        fs::read_to_string(self.join(path)).expect("failed to read fixture file")
+    }
+
+    /// Gets the paths of all files in the tree, relative to its root and in
+    /// sorted order.
+    pub fn files(&self) -> Vec<PathBuf> {
+        fn push(root: &Path, directory: &Path, paths: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(directory).expect("failed to read fixture directory") {
+                let path = entry.expect("failed to read fixture directory").path();
+                if path.is_dir() {
+                    push(root, &path, paths);
+                }
+                else {
+                    paths.push(path.strip_prefix(root).unwrap().to_path_buf());
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        push(self.path(), self.path(), &mut paths);
+        paths.sort();
+        paths
+    }
+
+    /// Asserts that the tree contains exactly the given files.
+    pub fn assert_files<I>(&self, paths: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
+    {
+        let mut expected: Vec<_> = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        expected.sort();
+        assert_eq!(self.files(), expected, "unexpected files in fixture tree");
+    }
+
+    /// Asserts that a file has the given contents.
+    pub fn assert_contents(&self, path: impl AsRef<Path>, contents: &str) {
+        let path = path.as_ref();
+        assert_eq!(
+            self.read(path),
+            contents,
+            "unexpected contents of `{}`",
+            path.display(),
+        );
+    }
+
+    /// Asserts that a manifest contains exactly the given routes.
+    ///
+    /// Routes are given as pairs of source and destination paths relative to
+    /// the root of the tree. Routes with more than one source are given as a
+    /// pair for each source. Order is not considered.
+    pub fn assert_routes<M, I, S, D>(&self, manifest: &Manifest<M>, routes: I)
+    where
+        M: Routing,
+        I: IntoIterator<Item = (S, D)>,
+        S: AsRef<Path>,
+        D: AsRef<Path>,
+    {
+        let mut expected: Vec<_> = routes
+            .into_iter()
+            .map(|(source, destination)| (self.join(source), self.join(destination)))
+            .collect();
+        expected.sort();
+        let mut routes: Vec<_> = manifest
+            .routes()
+            .flat_map(|route| {
+                let destination = route.destination().to_path_buf();
+                route
+                    .sources()
+                    .map(|source| (source.to_path_buf(), destination.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        routes.sort();
+        assert_eq!(routes, expected, "unexpected routes in manifest");
+    }
+}
+
+impl Default for TreeFixture {
+    fn default() -> Self {
+        TreeFixture::new()
+    }
+}
@@ -1,4 +1,7 @@
-use regex::bytes::Captures as BorrowedCaptures;
+use regex::bytes::{Captures as BorrowedCaptures, Regex};
+use std::sync::Arc;
+
+use crate::glob::BytePath;
 
 #[derive(Debug)]
 enum MaybeOwnedCaptures<'t> {
@@ -41,6 +44,10 @@ struct OwnedCaptures {
 }
 
 impl OwnedCaptures {
+    pub fn len(&self) -> usize {
+        self.ranges.len() + 1
+    }
+
     pub fn get(&self, index: usize) -> Option<&[u8]> {
         if index == 0 {
             Some(self.matched.as_ref())
@@ -48,8 +55,7 @@ impl OwnedCaptures {
         else {
             self.ranges
                 .get(index - 1)
-                .map(|range| range.map(|range| &self.matched[range.0..range.1]))
-                .flatten()
+                .and_then(|range| range.map(|range| &self.matched[range.0..range.1]))
         }
     }
 }
@@ -72,22 +78,73 @@ impl<'c, 't> From<&'c BorrowedCaptures<'t>> for OwnedCaptures {
     }
 }
 
+/// Names of captures paired with their indices.
+pub(in crate) type Names = Arc<[(String, usize)]>;
+
+/// Gets the names of the captures of a regular expression.
+///
+/// Returns `None` if the regular expression has no named captures.
+pub(in crate) fn names(regex: &Regex) -> Option<Names> {
+    let names: Vec<_> = regex
+        .capture_names()
+        .enumerate()
+        .filter_map(|(index, name)| name.map(|name| (name.to_owned(), index)))
+        .collect();
+    (!names.is_empty()).then(|| names.into())
+}
+
 #[derive(Debug)]
 pub struct Captures<'t> {
     inner: MaybeOwnedCaptures<'t>,
+    names: Option<Names>,
+}
+
+/// Empty captures, as if matching an empty path with no capturing tokens.
+impl Default for Captures<'static> {
+    fn default() -> Self {
+        Captures {
+            inner: OwnedCaptures {
+                matched: Vec::new(),
+                ranges: Vec::new(),
+            }
+            .into(),
+            names: None,
+        }
+    }
+}
+
+impl Captures<'static> {
+    /// Gets captures that only include the full text of a match, as if
+    /// matching a path with no capturing tokens.
+    pub(in crate) fn from_matched(path: BytePath<'_>) -> Self {
+        Captures {
+            inner: OwnedCaptures {
+                matched: path.path.into_owned(),
+                ranges: Vec::new(),
+            }
+            .into(),
+            names: None,
+        }
+    }
 }
 
 impl<'t> Captures<'t> {
+    pub(in crate) fn with_names(self, names: Option<Names>) -> Self {
+        Captures { names, ..self }
+    }
+
     pub fn into_owned(self) -> Captures<'static> {
-        let Captures { inner } = self;
+        let Captures { inner, names } = self;
         Captures {
             inner: inner.into_owned(),
+            names,
         }
     }
 
     pub fn to_owned(&self) -> Captures<'static> {
         Captures {
             inner: self.inner.to_owned(),
+            names: self.names.clone(),
         }
     }
 
@@ -103,6 +160,38 @@ impl<'t> Captures<'t> {
             MaybeOwnedCaptures::Owned(ref captures) => captures.get(index),
         }
     }
+
+    /// Gets a named capture, such as `{<name>*}` in a glob.
+    ///
+    /// Returns `None` if there is no capture with the given name or if the
+    /// capture does not participate in the match.
+    pub fn name(&self, name: &str) -> Option<&[u8]> {
+        self.names
+            .as_ref()?
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .and_then(|(_, index)| self.get(*index))
+    }
+
+    /// Gets the number of captures, including the full text of the match.
+    ///
+    /// Note that this is never zero, because the full text of the match is
+    /// always present at index zero.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match self.inner {
+            MaybeOwnedCaptures::Borrowed(ref captures) => captures.len(),
+            MaybeOwnedCaptures::Owned(ref captures) => captures.len(),
+        }
+    }
+
+    /// Gets an iterator over all captures in order, including the full text of
+    /// the match.
+    ///
+    /// Captures that do not participate in a match are `None`.
+    pub fn iter(&self) -> impl '_ + ExactSizeIterator<Item = Option<&'_ [u8]>> {
+        (0..self.len()).map(move |index| self.get(index))
+    }
 }
 
 // TODO: Maybe this shouldn't be part of the public API.
@@ -110,6 +199,7 @@ impl<'t> From<BorrowedCaptures<'t>> for Captures<'t> {
     fn from(captures: BorrowedCaptures<'t>) -> Self {
         Captures {
             inner: captures.into(),
+            names: None,
         }
     }
 }
@@ -118,6 +208,7 @@ impl From<OwnedCaptures> for Captures<'static> {
     fn from(captures: OwnedCaptures) -> Self {
         Captures {
             inner: captures.into(),
+            names: None,
         }
     }
 }
@@ -0,0 +1,138 @@
+//! Ignore files like `.gitignore` that exclude paths from a directory tree.
+//!
+//! This module provides `Ignores`, which tracks the ignore files that apply to
+//! entries as a directory tree is traversed depth-first.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+use std::sync::Arc;
+use walkdir::DirEntry;
+
+// Ignore files are read in order of increasing precedence.
+const IGNORE_FILE_NAMES: [&str; 3] = [".git/info/exclude", ".gitignore", ".ignore"];
+
+/// Matches entries against ignore files in a directory tree.
+///
+/// Ignore files are read from each directory as it is traversed. Ignore files
+/// in the root directory and its ancestors up to the root of any enclosing Git
+/// repository also apply, as does the `.git/info/exclude` file of any
+/// repository.
+#[derive(Clone, Debug)]
+pub struct Ignores {
+    // The root as given and as a canonical path. Ancestors are found via the
+    // canonical path, because the root may be relative, such as `.`.
+    root: Arc<Path>,
+    canonical: Arc<Path>,
+    // Matchers for the ancestors of the root. These apply to all entries and
+    // are matched against paths beneath the canonical root.
+    ancestors: Arc<[Gitignore]>,
+    // Matchers for the directories in the path of the current entry. The index
+    // is the depth of the directory relative to the root. Matchers are shared
+    // so that `Ignores` can be cloned cheaply for each directory in a parallel
+    // traversal.
+    stack: Vec<Option<Arc<Gitignore>>>,
+}
+
+impl Ignores {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let is_repository = |path: &Path| path.join(".git").exists();
+        let mut ancestors = Vec::new();
+        // Ancestors are only considered within a Git repository and only if
+        // the root is not itself the root of the repository.
+        if !is_repository(&canonical) && canonical.ancestors().skip(1).any(is_repository) {
+            for path in canonical.ancestors().skip(1) {
+                ancestors.extend(read(path));
+                if is_repository(path) {
+                    break;
+                }
+            }
+        }
+        Ignores {
+            root: root.into(),
+            canonical: canonical.into(),
+            ancestors: ancestors.into(),
+            stack: vec![read(root).map(Arc::new)],
+        }
+    }
+
+    /// Determines if an entry is ignored.
+    ///
+    /// If the entry is a directory that is not ignored, then its ignore files
+    /// are read and apply to subsequent entries beneath it. Entries must be
+    /// given in depth-first order (i.e., the order of a `walkdir` traversal).
+    pub fn is_ignored(&mut self, entry: &DirEntry) -> bool {
+        // Discard matchers for directories that are not ancestors of the
+        // entry. The parent of the entry is always the most recently pushed
+        // directory with a depth one less than the entry.
+        self.stack.truncate(entry.depth());
+        let is_dir = entry.file_type().is_dir();
+        let is_ignored = self.is_match(entry.path(), is_dir);
+        if is_dir && !is_ignored {
+            self.stack.push(read(entry.path()).map(Arc::new));
+        }
+        is_ignored
+    }
+
+    /// Determines if a path within the most recently read directory is
+    /// ignored.
+    ///
+    /// Unlike `is_ignored`, this does not read any ignore files.
+    pub fn is_match(&self, path: &Path, is_dir: bool) -> bool {
+        let matched = self
+            .stack
+            .iter()
+            .flatten()
+            .rev()
+            .map(|gitignore| gitignore.matched(path, is_dir))
+            .find(|matched| !matched.is_none());
+        if let Some(matched) = matched {
+            return matched.is_ignore();
+        }
+        if self.ancestors.is_empty() {
+            return false;
+        }
+        let path = match path.strip_prefix(&self.root) {
+            Ok(path) => self.canonical.join(path),
+            Err(_) => path.to_path_buf(),
+        };
+        self.ancestors
+            .iter()
+            .map(|gitignore| gitignore.matched(&path, is_dir))
+            .find(|matched| !matched.is_none())
+            .map(|matched| matched.is_ignore())
+            .unwrap_or(false)
+    }
+
+    /// Reads the ignore files of a directory within the most recently read
+    /// directory.
+    ///
+    /// The ignore files apply to paths given to `is_match` on the returned
+    /// `Ignores`, which is independent of `self`. This allows directories to be
+    /// traversed in any order, such as in parallel.
+    pub fn descend(&self, directory: &Path) -> Self {
+        let mut ignores = self.clone();
+        ignores.stack.push(read(directory).map(Arc::new));
+        ignores
+    }
+}
+
+fn read(directory: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(directory);
+    let mut is_empty = true;
+    for name in IGNORE_FILE_NAMES.iter() {
+        let path = directory.join(name);
+        if path.is_file() {
+            // Invalid lines in ignore files are discarded.
+            let _ = builder.add(path);
+            is_empty = false;
+        }
+    }
+    if is_empty {
+        None
+    }
+    else {
+        builder.build().ok()
+    }
+}
@@ -1,26 +1,45 @@
 mod capture;
+#[cfg(feature = "fs")]
+mod gitignore;
 mod rule;
+mod set;
 mod token;
+#[cfg(feature = "fs")]
+mod walk;
 
 use bstr::ByteVec;
-use itertools::{EitherOrBoth, Itertools as _, Position};
-use nom::error::ErrorKind;
+use itertools::{Itertools as _, Position};
+use nom::error::ErrorKind as NomErrorKind;
 use os_str_bytes::OsStrBytes as _;
 use regex::bytes::Regex;
 use std::borrow::{Borrow, Cow};
 use std::convert::TryFrom;
 use std::ffi::OsStr;
-use std::fs::{FileType, Metadata};
+#[cfg(feature = "fs")]
+use std::io;
 use std::iter::Fuse;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::OnceLock;
 use thiserror::Error;
-use walkdir::{self, DirEntry, WalkDir};
 
-use crate::glob::token::{Token, Wildcard};
+use crate::error::ErrorKind;
+use crate::glob::token::Token;
 
 pub use crate::glob::capture::Captures;
 pub use crate::glob::rule::RuleError;
+pub use crate::glob::set::GlobSet;
+pub use crate::glob::token::{
+    Archetype, Evaluation, GlobToken, NumericRange, PosixClass, Prefix, TokenKind, Wildcard,
+};
+#[cfg(feature = "fs")]
+pub(in crate) use crate::glob::walk::walk_regex;
+#[cfg(feature = "async")]
+pub use crate::glob::walk::WalkStream;
+#[cfg(feature = "fs")]
+pub use crate::glob::walk::{
+    MetadataFilter, ParallelWalk, Walk, WalkBehavior, WalkEntry, WalkOrder,
+};
 
 trait IteratorExt: Iterator + Sized {
     fn adjacent(self) -> Adjacent<Self>
@@ -93,7 +112,7 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         let next = self.input.next();
-        self.adjacency.take().map(|adjacency| {
+        self.adjacency.take().inspect(|adjacency| {
             self.adjacency = match adjacency.clone() {
                 Adjacency::First {
                     item: left,
@@ -113,7 +132,6 @@ where
                 }
                 Adjacency::Only { .. } | Adjacency::Last { .. } => None,
             };
-            adjacency
         })
     }
 }
@@ -173,25 +191,114 @@ enum Terminals<T> {
 }
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
 #[non_exhaustive]
 pub enum GlobError {
     #[error("failed to parse glob: {0}")]
-    Parse(nom::Err<(String, ErrorKind)>),
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::parse),
+            help("wildcards, classes, and alternatives must be well-formed")
+        )
+    )]
+    Parse(nom::Err<(String, NomErrorKind)>),
     #[error("invalid glob: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(transparent))]
     Rule(RuleError),
+    #[error("failed to compile pattern: {0}")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::compile),
+            help("the pattern may be too large; try simplifying it")
+        )
+    )]
+    Compile(regex::Error),
+    #[cfg(feature = "fs")]
     #[error("failed to walk directory tree: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::glob::walk)))]
     Walk(walkdir::Error),
+    #[cfg(feature = "fs")]
+    #[error("failed to read `{0}` in directory tree: {1}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::glob::read)))]
+    Read(PathBuf, io::Error),
+    #[cfg(feature = "fs")]
+    #[error("detected symbolic link loop: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::link_loop),
+            help("avoid following symbolic links or break the loop")
+        )
+    )]
+    LinkLoop(PathBuf),
+    #[cfg(feature = "fs")]
+    #[error("path is not valid UTF-8: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::encoding),
+            help("rename the file or directory or exclude it")
+        )
+    )]
+    Encoding(PathBuf),
 }
 
-impl<'i> From<nom::Err<(&'i str, ErrorKind)>> for GlobError {
-    fn from(error: nom::Err<(&'i str, ErrorKind)>) -> Self {
-        GlobError::Parse(error.to_owned())
+impl GlobError {
+    /// Gets the category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            GlobError::Parse(_) => ErrorKind::Parse,
+            GlobError::Rule(_) | GlobError::Compile(_) => ErrorKind::Invalid,
+            #[cfg(feature = "fs")]
+            GlobError::Walk(ref error) => {
+                if error.loop_ancestor().is_some() {
+                    ErrorKind::LinkLoop
+                }
+                else {
+                    error.io_error().map_or(ErrorKind::Io, ErrorKind::from_io)
+                }
+            }
+            #[cfg(feature = "fs")]
+            GlobError::Read(_, ref error) => ErrorKind::from_io(error),
+            #[cfg(feature = "fs")]
+            GlobError::LinkLoop(_) => ErrorKind::LinkLoop,
+            #[cfg(feature = "fs")]
+            GlobError::Encoding(_) => ErrorKind::Encoding,
+        }
+    }
+
+    /// Gets the path at which the error occurred, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            #[cfg(feature = "fs")]
+            GlobError::Walk(ref error) => error.path(),
+            #[cfg(feature = "fs")]
+            GlobError::Read(ref path, _)
+            | GlobError::LinkLoop(ref path)
+            | GlobError::Encoding(ref path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Gets the byte offset in the given glob text at which parsing failed.
+    ///
+    /// Returns `None` if the error is not a parsing error.
+    pub fn parse_offset(&self, text: &str) -> Option<usize> {
+        match self {
+            GlobError::Parse(nom::Err::Error((ref remaining, _)))
+            | GlobError::Parse(nom::Err::Failure((ref remaining, _))) => {
+                Some(text.len().saturating_sub(remaining.len()))
+            }
+            _ => None,
+        }
     }
 }
 
-impl From<walkdir::Error> for GlobError {
-    fn from(error: walkdir::Error) -> Self {
-        GlobError::Walk(error)
+impl<'i> From<nom::Err<(&'i str, NomErrorKind)>> for GlobError {
+    fn from(error: nom::Err<(&'i str, NomErrorKind)>) -> Self {
+        GlobError::Parse(error.to_owned())
     }
 }
 
@@ -250,9 +357,18 @@ impl<'b> BytePath<'b> {
         }
     }
 
-    pub fn path(&self) -> Option<Cow<Path>> {
+    pub fn path(&self) -> Option<Cow<'_, Path>> {
         Path::from_raw_bytes(self.path.as_ref()).ok()
     }
+
+    /// Gets the non-empty components of the path, which are delimited by
+    /// separators.
+    #[cfg(feature = "fs")]
+    fn components(&self) -> impl '_ + Iterator<Item = &[u8]> {
+        self.path
+            .split(|byte| *byte == b'/')
+            .filter(|component| !component.is_empty())
+    }
 }
 
 impl<'b> AsRef<[u8]> for BytePath<'b> {
@@ -261,61 +377,40 @@ impl<'b> AsRef<[u8]> for BytePath<'b> {
     }
 }
 
-/// Describes a file matching a `Glob` in a directory tree.
-#[derive(Debug)]
-pub struct WalkEntry<'e> {
-    entry: Cow<'e, DirEntry>,
-    captures: Captures<'e>,
-}
-
-impl<'e> WalkEntry<'e> {
-    pub fn into_owned(self) -> WalkEntry<'static> {
-        let WalkEntry { entry, captures } = self;
-        WalkEntry {
-            entry: Cow::Owned(entry.into_owned()),
-            captures: captures.into_owned(),
-        }
-    }
-
-    pub fn into_path(self) -> PathBuf {
-        match self.entry {
-            Cow::Borrowed(entry) => entry.path().to_path_buf(),
-            Cow::Owned(entry) => entry.into_path(),
-        }
-    }
-
-    pub fn path(&self) -> &Path {
-        self.entry.path()
-    }
-
-    pub fn file_type(&self) -> FileType {
-        self.entry.file_type()
-    }
-
-    // TODO: On some platforms, traversing a directory tree also yields file
-    //       metadata (e.g., Windows). Forward this metadata to path printing
-    //       using `lscolors` in `nym-cli` to avoid unnecessary reads.
-    pub fn metadata(&self) -> Result<Metadata, GlobError> {
-        self.entry.metadata().map_err(From::from)
-    }
-
-    pub fn depth(&self) -> usize {
-        self.entry.depth()
-    }
-
-    pub fn captures(&self) -> &Captures<'e> {
-        &self.captures
-    }
+/// Compiled regular expression of a glob and the names of its captures.
+#[derive(Clone, Debug)]
+struct Compiled {
+    regex: Regex,
+    names: Option<capture::Names>,
 }
 
+/// Glob pattern.
+///
+/// The regular expression of a glob is compiled lazily when it is first
+/// matched against a path, so globs that are only inspected (such as via
+/// `partitioned` or `token_tree`) are never compiled. Matching functions
+/// return an error if the regular expression fails to compile.
 #[derive(Clone, Debug)]
 pub struct Glob<'t> {
     tokens: Vec<Token<'t>>,
-    regex: Regex,
+    pattern: String,
+    regex: OnceLock<Result<Compiled, regex::Error>>,
+    is_case_insensitive: bool,
 }
 
 impl<'t> Glob<'t> {
-    fn compile<T>(tokens: impl IntoIterator<Item = T>) -> Regex
+    #[cfg(feature = "fs")]
+    fn compile_tokens<T>(
+        tokens: impl IntoIterator<Item = T>,
+        is_case_insensitive: bool,
+    ) -> Result<Regex, regex::Error>
+    where
+        T: Borrow<Token<'t>>,
+    {
+        Regex::new(&Glob::encode(tokens, is_case_insensitive))
+    }
+
+    fn encode<T>(tokens: impl IntoIterator<Item = T>, is_case_insensitive: bool) -> String
     where
         T: Borrow<Token<'t>>,
     {
@@ -363,9 +458,11 @@ impl<'t> Glob<'t> {
         {
             use itertools::Position::{First, Last, Middle, Only};
 
-            use crate::glob::token::Archetype::{Character, Range};
+            use crate::glob::token::Archetype::{Character, Posix, Range};
             use crate::glob::token::Evaluation::{Eager, Lazy};
-            use crate::glob::token::Token::{Alternative, Class, Literal, Separator, Wildcard};
+            use crate::glob::token::Token::{
+                Alternative, Class, Literal, NumericRange, Prefix, Repetition, Separator, Wildcard,
+            };
             use crate::glob::token::Wildcard::{One, Tree, ZeroOrMore};
 
             for token in tokens.into_iter().with_position() {
@@ -376,6 +473,11 @@ impl<'t> Glob<'t> {
                         }
                     }
                     (_, Separator) => pattern.push_str(&escape(b'/')),
+                    (_, Prefix(prefix)) => {
+                        for &byte in prefix.to_string().as_bytes() {
+                            pattern.push_str(&escape(byte));
+                        }
+                    }
                     (_, Alternative(alternative)) => {
                         let encodings: Vec<_> = alternative
                             .branches()
@@ -388,7 +490,14 @@ impl<'t> Glob<'t> {
                                 pattern
                             })
                             .collect();
-                        grouping.push_str(pattern, &encodings.join("|"));
+                        match (grouping, alternative.name()) {
+                            (Grouping::Capture, Some(name)) => {
+                                pattern.push_str(&format!("(?P<{}>", name));
+                                pattern.push_str(&encodings.join("|"));
+                                pattern.push(')');
+                            }
+                            _ => grouping.push_str(pattern, &encodings.join("|")),
+                        }
                     }
                     (
                         _,
@@ -398,11 +507,11 @@ impl<'t> Glob<'t> {
                         },
                     ) => {
                         grouping.push_with(pattern, || {
+                            // Negated classes are nested, because negation
+                            // applies to the entire class including any
+                            // intersection. Classes never match separators.
                             let mut pattern = String::new();
-                            pattern.push('[');
-                            if *is_negated {
-                                pattern.push('^');
-                            }
+                            pattern.push_str(if *is_negated { "[[^" } else { "[" });
                             for archetype in archetypes {
                                 match archetype {
                                     Character(literal) => {
@@ -417,12 +526,43 @@ impl<'t> Glob<'t> {
                                         pattern.push('-');
                                         pattern.push(*right);
                                     }
+                                    // ASCII classes are supported by regular
+                                    // expressions using the same syntax.
+                                    Posix(class) => {
+                                        pattern.push_str(&format!("[:{}:]", class.name()));
+                                    }
                                 }
                             }
+                            if *is_negated {
+                                pattern.push(']');
+                            }
                             pattern.push_str("&&[^/]]");
                             pattern.into()
                         });
                     }
+                    (_, NumericRange(range)) => grouping.push_str(pattern, &range.regex()),
+                    (_, Repetition(repetition)) => {
+                        grouping.push_with(pattern, || {
+                            let mut pattern = String::new();
+                            pattern.push_str("(?:");
+                            encode(
+                                Grouping::NonCapture,
+                                &mut pattern,
+                                repetition.tokens().iter(),
+                            );
+                            pattern.push(')');
+                            match repetition.bounds() {
+                                (lower, Some(upper)) if lower == upper => {
+                                    pattern.push_str(&format!("{{{}}}", lower));
+                                }
+                                (lower, Some(upper)) => {
+                                    pattern.push_str(&format!("{{{},{}}}", lower, upper));
+                                }
+                                (lower, None) => pattern.push_str(&format!("{{{},}}", lower)),
+                            }
+                            pattern.into()
+                        });
+                    }
                     (_, Wildcard(One)) => grouping.push_str(pattern, "[^/]"),
                     (_, Wildcard(ZeroOrMore(Eager))) => grouping.push_str(pattern, "[^/]*"),
                     (_, Wildcard(ZeroOrMore(Lazy))) => grouping.push_str(pattern, "[^/]*?"),
@@ -447,27 +587,50 @@ impl<'t> Glob<'t> {
         }
 
         let mut pattern = String::new();
-        pattern.push_str("(?-u)^");
+        pattern.push_str(if is_case_insensitive {
+            "(?i-u)^"
+        }
+        else {
+            "(?-u)^"
+        });
         encode(Grouping::Capture, &mut pattern, tokens);
         pattern.push('$');
-        Regex::new(&pattern).expect("glob compilation failed")
+        pattern
+    }
+
+    fn from_tokens(tokens: Vec<Token<'t>>, is_case_insensitive: bool) -> Self {
+        let pattern = Glob::encode(tokens.iter(), is_case_insensitive);
+        Glob {
+            tokens,
+            pattern,
+            regex: OnceLock::new(),
+            is_case_insensitive,
+        }
     }
 
     pub fn new(text: &'t str) -> Result<Self, GlobError> {
         let tokens: Vec<_> = token::optimize(token::parse(text)?).collect();
-        let regex = Glob::compile(tokens.iter());
-        Ok(Glob { tokens, regex })
+        Ok(Glob::from_tokens(tokens, false))
     }
 
     pub fn partitioned(text: &'t str) -> Result<(PathBuf, Self), GlobError> {
-        pub fn literal_prefix_upper_bound(tokens: &[Token]) -> usize {
+        Glob::new(text).map(|glob| glob.partition().into_parts())
+    }
+
+    /// Partitions the glob into its invariant literal path prefix and variant
+    /// remainder.
+    ///
+    /// This is like `Glob::partitioned`, but partitions a glob that has
+    /// already been parsed.
+    pub fn partition(self) -> Partition<'t> {
+        fn literal_prefix_upper_bound(tokens: &[Token]) -> usize {
             let mut index = 0;
             for (n, token) in tokens.iter().enumerate() {
                 match token {
                     Token::Separator => {
                         index = n;
                     }
-                    Token::Literal(_) => {
+                    Token::Literal(_) | Token::Prefix(_) => {
                         continue;
                     }
                     Token::Wildcard(Wildcard::Tree) => {
@@ -481,17 +644,222 @@ impl<'t> Glob<'t> {
             tokens.len()
         }
 
-        let mut tokens: Vec<_> = token::optimize(token::parse(text)?).collect();
+        let Glob {
+            mut tokens,
+            is_case_insensitive,
+            ..
+        } = self;
         let prefix = token::literal_path_prefix(tokens.iter()).unwrap_or_else(PathBuf::new);
         tokens.drain(0..literal_prefix_upper_bound(&tokens));
-        let regex = Glob::compile(tokens.iter());
-        Ok((prefix, Glob { tokens, regex }))
+        Partition {
+            prefix,
+            variant: Glob::from_tokens(tokens, is_case_insensitive),
+        }
+    }
+
+    /// Matches paths without regard to (ASCII) case.
+    ///
+    /// This applies to both matching and walking: relative literal components
+    /// like `photos` in `photos/*.jpg` are matched against directory entries
+    /// rather than read as paths when walking. Note that this does not apply
+    /// to rooted prefixes nor literal path prefixes partitioned from a glob by
+    /// `Glob::partitioned`, which are used as paths verbatim.
+    pub fn case_insensitive(self) -> Self {
+        let Glob { tokens, .. } = self;
+        Glob::from_tokens(tokens, true)
     }
 
     pub fn into_owned(self) -> Glob<'static> {
-        let Glob { tokens, regex } = self;
+        let Glob {
+            tokens,
+            pattern,
+            regex,
+            is_case_insensitive,
+        } = self;
         let tokens = tokens.into_iter().map(|token| token.into_owned()).collect();
-        Glob { tokens, regex }
+        Glob {
+            tokens,
+            pattern,
+            regex,
+            is_case_insensitive,
+        }
+    }
+
+    /// Compiles the regular expression of the glob if it has not yet been
+    /// compiled.
+    ///
+    /// Globs are compiled when first matched and matching fails if the glob
+    /// cannot be compiled. This function can be used to detect such failures
+    /// up front.
+    pub fn compile(&self) -> Result<(), GlobError> {
+        self.compiled().map(|_| ())
+    }
+
+    /// Gets the regular expression to which the glob is compiled.
+    ///
+    /// This does not compile the regular expression.
+    pub fn regex(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Gets a read-only view of the tokens of the glob.
+    ///
+    /// Literal path prefixes partitioned from the glob by `Glob::partitioned`
+    /// are not included. Captures are formed by top-level tokens that are not
+    /// literal (see `GlobToken::is_literal`), in order.
+    pub fn tokens(&self) -> impl '_ + ExactSizeIterator<Item = GlobToken<'_>> {
+        self.tokens.iter().map(GlobToken::from)
+    }
+
+    /// Describes the tokens of the glob as an indented tree.
+    ///
+    /// Each token is described on its own line and the branches of
+    /// alternatives and the tokens of repetitions are indented beneath them.
+    /// Captured tokens are labeled with the index of their capture as used in
+    /// to-patterns (e.g., `{#1}`).
+    pub fn token_tree(&self) -> String {
+        use crate::glob::token::Archetype::{Character, Posix, Range};
+        use crate::glob::token::Evaluation::{Eager, Lazy};
+        use crate::glob::token::Wildcard::{One, Tree, ZeroOrMore};
+
+        fn describe(token: &Token<'_>) -> String {
+            match token {
+                Token::Alternative(ref alternative) => match alternative.name() {
+                    Some(name) => format!("alternative `{}`", name),
+                    None => "alternative".into(),
+                },
+                Token::Class {
+                    is_negated,
+                    archetypes,
+                } => {
+                    let mut text = String::from("class `[");
+                    if *is_negated {
+                        text.push('!');
+                    }
+                    for archetype in archetypes {
+                        match archetype {
+                            Character(literal) => text.push(*literal),
+                            Range(left, right) => {
+                                text.push(*left);
+                                text.push('-');
+                                text.push(*right);
+                            }
+                            Posix(class) => text.push_str(&format!("[:{}:]", class.name())),
+                        }
+                    }
+                    text.push_str("]`");
+                    text
+                }
+                Token::Literal(ref literal) => format!("literal `{}`", literal),
+                Token::NumericRange(ref range) => format!("range `{}`", range),
+                Token::Prefix(ref prefix) => format!("prefix `{}`", prefix),
+                Token::Repetition(ref repetition) => match repetition.bounds() {
+                    (lower, Some(upper)) if lower == upper => {
+                        format!("repetition (exactly {} times)", lower)
+                    }
+                    (lower, Some(upper)) => {
+                        format!("repetition ({} to {} times)", lower, upper)
+                    }
+                    (lower, None) => format!("repetition ({} or more times)", lower),
+                },
+                Token::Separator => "separator `/`".into(),
+                Token::Wildcard(One) => "wildcard `?` (exactly one character)".into(),
+                Token::Wildcard(ZeroOrMore(Eager)) => {
+                    "wildcard `*` (zero or more characters)".into()
+                }
+                Token::Wildcard(ZeroOrMore(Lazy)) => {
+                    "wildcard `$` (zero or more characters, lazily)".into()
+                }
+                Token::Wildcard(Tree) => "wildcard `**` (zero or more directories)".into(),
+            }
+        }
+
+        fn push(text: &mut String, tokens: &[Token<'_>], depth: usize, mut index: Option<usize>) {
+            for token in tokens {
+                text.push_str(&"  ".repeat(depth));
+                text.push_str(&describe(token));
+                if let Some(ref mut index) = index {
+                    if !matches!(
+                        token,
+                        Token::Literal(_) | Token::Prefix(_) | Token::Separator
+                    ) {
+                        *index += 1;
+                        text.push_str(&format!(" #{}", index));
+                    }
+                }
+                text.push('\n');
+                match token {
+                    Token::Alternative(ref alternative) => {
+                        for (n, branch) in alternative.branches().iter().enumerate() {
+                            text.push_str(&"  ".repeat(depth + 1));
+                            text.push_str(&format!("branch {}\n", n + 1));
+                            push(text, branch, depth + 2, None);
+                        }
+                    }
+                    Token::Repetition(ref repetition) => {
+                        push(text, repetition.tokens(), depth + 1, None);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut text = String::new();
+        push(&mut text, &self.tokens, 0, Some(0));
+        text
+    }
+
+    /// Gets the number of captures of the glob, including the implicit capture
+    /// of the entire match (`{#0}`).
+    pub fn captures_len(&self) -> usize {
+        1 + self
+            .tokens
+            .iter()
+            .filter(|token| {
+                !matches!(
+                    token,
+                    Token::Literal(_) | Token::Prefix(_) | Token::Separator
+                )
+            })
+            .count()
+    }
+
+    /// Gets the index of the capture with the given name.
+    ///
+    /// Captures are named via alternatives, such as `{<name>*}`. Named captures
+    /// are also indexed like any other capture.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        self.tokens
+            .iter()
+            .filter(|token| {
+                !matches!(
+                    token,
+                    Token::Literal(_) | Token::Prefix(_) | Token::Separator
+                )
+            })
+            .position(|token| {
+                matches!(
+                    token,
+                    Token::Alternative(ref alternative) if alternative.name() == Some(name)
+                )
+            })
+            .map(|index| index + 1)
+    }
+
+    /// Determines if the glob matches entire directory trees.
+    ///
+    /// A glob is exhaustive if it matches every path beneath any directory
+    /// that it matches, such as `src/**` or `{a,b}/**`. Note that this is not
+    /// the case for globs like `src/**/*.rs`.
+    pub fn is_exhaustive(&self) -> bool {
+        token::is_exhaustive(self.tokens.iter())
+    }
+
+    /// Gets the rules that reject variants of the glob.
+    ///
+    /// See `RuleError::example`.
+    pub fn constraints(&self) -> Vec<RuleError> {
+        rule::constraints(self.tokens.iter())
     }
 
     pub fn is_absolute(&self) -> bool {
@@ -521,196 +889,164 @@ impl<'t> Glob<'t> {
         false
     }
 
-    pub fn is_match(&self, path: impl AsRef<Path>) -> bool {
+    /// Determines if the glob matches a path.
+    ///
+    /// Returns an error if the glob fails to compile. See `Glob::compile`.
+    pub fn is_match(&self, path: impl AsRef<Path>) -> Result<bool, GlobError> {
         let path = BytePath::from_path(path.as_ref());
-        self.regex.is_match(&path.path)
-    }
-
-    pub fn captures<'p>(&self, path: &'p BytePath<'_>) -> Option<Captures<'p>> {
-        self.regex.captures(path.as_ref()).map(From::from)
-    }
-
-    pub fn walk(&self, directory: impl AsRef<Path>, depth: usize) -> Walk {
-        // The directory tree is traversed from `root`, which may include a path
-        // prefix from the glob pattern. `Walk` patterns are only applied to
-        // path components following the `prefix` in `root`.
-        let (prefix, root) = if let Some(prefix) = token::literal_path_prefix(self.tokens.iter()) {
-            let root: Cow<'_, Path> = directory.as_ref().join(&prefix).into();
-            if prefix.is_absolute() {
-                // Note that absolute paths replace paths with which they are
-                // joined, so there is no prefix.
-                (PathBuf::new().into(), root)
-            }
-            else {
-                (directory.as_ref().into(), root)
-            }
-        }
-        else {
-            let root: Cow<'_, Path> = directory.as_ref().into();
-            (root.clone(), root)
-        };
-        let regexes = Walk::compile(self.tokens.iter());
-        Walk {
-            glob: self,
-            regexes,
-            prefix: prefix.into_owned(),
-            walk: WalkDir::new(root)
-                .follow_links(false)
-                .min_depth(1)
-                .max_depth(depth)
-                .into_iter(),
+        if let Some(is_match) = self.match_literal(&path) {
+            return Ok(is_match);
         }
+        self.compiled()
+            .map(|compiled| compiled.regex.is_match(&path.path))
     }
-}
 
-impl<'t> TryFrom<&'t str> for Glob<'t> {
-    type Error = GlobError;
-
-    fn try_from(text: &'t str) -> Result<Self, Self::Error> {
-        Glob::new(text)
+    /// Gets the captures of the glob in a path, if any.
+    ///
+    /// Returns an error if the glob fails to compile. See `Glob::compile`.
+    pub fn captures<'p>(&self, path: &'p BytePath<'_>) -> Result<Option<Captures<'p>>, GlobError> {
+        if let Some(is_match) = self.match_literal(path) {
+            return Ok(is_match.then(|| Captures::from_matched(path.clone())));
+        }
+        let Compiled { regex, names } = self.compiled()?;
+        Ok(regex
+            .captures(path.as_ref())
+            .map(|captures| Captures::from(captures).with_names(names.clone())))
     }
-}
-
-impl FromStr for Glob<'static> {
-    type Err = GlobError;
 
-    fn from_str(text: &str) -> Result<Self, Self::Err> {
-        Glob::new(text).map(|glob| glob.into_owned())
+    /// Matches a list of paths and yields the paths that match along with
+    /// their captures.
+    ///
+    /// Paths are matched as given and the file system is never read, so this
+    /// can be used to match paths produced elsewhere, such as by `git
+    /// ls-files`. Returns an error if the glob fails to compile.
+    pub fn matched<'g, 'p, I>(
+        &'g self,
+        paths: I,
+    ) -> Result<impl 'g + Iterator<Item = (&'p Path, Captures<'static>)>, GlobError>
+    where
+        I: IntoIterator<Item = &'p Path>,
+        I::IntoIter: 'g,
+    {
+        self.compile()?;
+        Ok(paths.into_iter().filter_map(move |path| {
+            let bytes = BytePath::from_path(path);
+            // The glob has already been compiled, so matching cannot fail.
+            self.captures(&bytes)
+                .ok()
+                .flatten()
+                .map(|captures| (path, captures.into_owned()))
+        }))
+    }
+
+    /// Determines if the glob is composed only of literals and separators.
+    ///
+    /// Literal globs are matched by comparing paths directly rather than via
+    /// regular expressions.
+    fn is_literal(&self) -> bool {
+        self.tokens
+            .iter()
+            .all(|token| matches!(token, Token::Literal(_) | Token::Separator))
     }
-}
 
-/// Traverses a directory tree via a `Walk` instance.
-///
-/// This macro emits an interruptable loop that executes a block of code
-/// whenever a `WalkEntry` or error is encountered while traversing a directory
-/// tree. The block may return from its function or otherwise interrupt and
-/// subsequently resume the loop.
-///
-/// Note that if the block attempts to emit a `WalkEntry` across a function
-/// boundary that the entry must copy its contents via `into_owned`.
-macro_rules! walk {
-    ($walk:expr => |$entry:ident| $f:block) => {
-        // `while-let` avoids a mutable borrow of `walk`, which would prevent a
-        // subsequent call to `skip_current_dir` within the loop body.
-        #[allow(clippy::while_let_on_iterator)]
-        #[allow(unreachable_code)]
-        'walk: while let Some(entry) = $walk.walk.next() {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(error) => {
-                    let $entry = Err(error.into());
-                    $f
-                    continue 'walk; // May be unreachable.
-                }
+    /// Matches a path against a literal glob.
+    ///
+    /// Returns `None` if the glob is not literal.
+    fn match_literal(&self, path: &BytePath<'_>) -> Option<bool> {
+        if !self.is_literal() {
+            return None;
+        }
+        let mut path = path.as_ref();
+        for token in &self.tokens {
+            let text = match *token {
+                Token::Literal(ref literal) => literal.as_bytes(),
+                _ => b"/",
             };
-            let path = entry
-                .path()
-                .strip_prefix(&$walk.prefix)
-                .expect("path is not in tree");
-            for candidate in path
-                .components()
-                .filter_map(|component| match component {
-                    Component::Normal(text) => Some(text.to_str().unwrap().as_bytes()),
-                    _ => None,
-                })
-                .zip_longest($walk.regexes.iter())
-            {
-                match candidate {
-                    EitherOrBoth::Both(component, regex) => {
-                        if regex.is_match(component) {
-                            let bytes = BytePath::from_path(&path);
-                            if let Some(captures) = $walk.glob.captures(&bytes) {
-                                let $entry = Ok(WalkEntry {
-                                    entry: Cow::Borrowed(&entry),
-                                    captures,
-                                });
-                                $f
-                                continue 'walk; // May be unreachable.
-                            }
-                        }
-                        else {
-                            // Do not descend into directories that do not
-                            // match the corresponding component regex.
-                            if entry.file_type().is_dir() {
-                                $walk.walk.skip_current_dir();
-                            }
-                            continue 'walk;
-                        }
-                    }
-                    EitherOrBoth::Left(_) => {
-                        let bytes = BytePath::from_path(&path);
-                        if let Some(captures) = $walk.glob.captures(&bytes) {
-                            let $entry = Ok(WalkEntry {
-                                entry: Cow::Borrowed(&entry),
-                                captures,
-                            });
-                            $f
-                            continue 'walk; // May be unreachable.
-                        }
-                    }
-                    EitherOrBoth::Right(_) => {
-                        continue 'walk;
-                    }
+            let is_match = path.get(..text.len()).is_some_and(|prefix| {
+                if self.is_case_insensitive {
+                    prefix.eq_ignore_ascii_case(text)
                 }
+                else {
+                    prefix == text
+                }
+            });
+            if !is_match {
+                return Some(false);
             }
+            path = &path[text.len()..];
         }
+        Some(path.is_empty())
+    }
+
+    fn compiled(&self) -> Result<&Compiled, GlobError> {
+        self.regex
+            .get_or_init(|| {
+                Regex::new(&self.pattern).map(|regex| Compiled {
+                    names: capture::names(&regex),
+                    regex,
+                })
+            })
+            .as_ref()
+            .map_err(|error| GlobError::Compile(error.clone()))
     }
 }
 
-/// Iterator over files matching a `Glob` in a directory tree.
-pub struct Walk<'g, 't> {
-    glob: &'g Glob<'t>,
-    regexes: Vec<Regex>,
+/// Glob partitioned into an invariant literal path prefix and a variant glob.
+///
+/// See `Glob::partition`.
+#[derive(Clone, Debug)]
+pub struct Partition<'t> {
     prefix: PathBuf,
-    walk: walkdir::IntoIter,
+    variant: Glob<'t>,
 }
 
-impl<'g, 't> Walk<'g, 't> {
-    fn compile<I>(tokens: I) -> Vec<Regex>
-    where
-        I: IntoIterator<Item = &'t Token<'t>>,
-        I::IntoIter: Clone,
-    {
-        let mut regexes = Vec::new();
-        for component in token::components(tokens) {
-            if component.tokens().iter().any(|token| match token {
-                Token::Alternative(ref alternative) => alternative.has_component_boundary(),
-                token => token.is_component_boundary(),
-            }) {
-                // NOTE: `token::components` omits any separators outside of
-                //       alternatives, so this will not stop at top-level
-                //       separators.
-                // Stop at component boundaries, such as tree wildcards or any
-                // boundary within an alternative token.
-                break;
-            }
-            else {
-                regexes.push(Glob::compile(component.tokens().iter().cloned()));
-            }
-        }
-        regexes
+impl<'t> Partition<'t> {
+    pub fn into_parts(self) -> (PathBuf, Glob<'t>) {
+        let Partition { prefix, variant } = self;
+        (prefix, variant)
     }
 
-    /// Calls a closure on each matched file or error.
+    /// Gets the invariant literal path prefix.
     ///
-    /// This function does not copy the contents of paths and captures when
-    /// emitting entries and so may be more efficient than external iteration
-    /// via `Iterator` (and `Iterator::for_each`).
-    pub fn for_each(mut self, mut f: impl FnMut(Result<WalkEntry, GlobError>)) {
-        walk!(self => |entry| {
-            f(entry);
-        });
+    /// The prefix is empty if the glob begins with a variant component.
+    pub fn prefix(&self) -> &Path {
+        &self.prefix
+    }
+
+    /// Gets the variant glob, which is matched against paths beneath the
+    /// prefix.
+    pub fn variant(&self) -> &Glob<'t> {
+        &self.variant
+    }
+
+    /// Determines if the glob is entirely invariant, in which case it only
+    /// matches its prefix.
+    pub fn is_invariant(&self) -> bool {
+        self.variant.tokens.is_empty()
+    }
+
+    /// Determines if the glob matches the entire directory tree beneath its
+    /// prefix.
+    ///
+    /// See `Glob::is_exhaustive`.
+    pub fn is_exhaustive(&self) -> bool {
+        self.variant.is_exhaustive()
     }
 }
 
-impl<'g, 't> Iterator for Walk<'g, 't> {
-    type Item = Result<WalkEntry<'static>, GlobError>;
+impl<'t> TryFrom<&'t str> for Glob<'t> {
+    type Error = GlobError;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        walk!(self => |entry| {
-            return Some(entry.map(|entry: WalkEntry| entry.into_owned()));
-        });
-        None
+    fn try_from(text: &'t str) -> Result<Self, Self::Error> {
+        Glob::new(text)
+    }
+}
+
+impl FromStr for Glob<'static> {
+    type Err = GlobError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Glob::new(text).map(|glob| glob.into_owned())
     }
 }
 
@@ -718,7 +1054,10 @@ impl<'g, 't> Iterator for Walk<'g, 't> {
 mod tests {
     use std::path::Path;
 
-    use crate::glob::{Adjacency, BytePath, Glob, IteratorExt as _};
+    use crate::error::ErrorKind;
+    use crate::glob::{
+        Adjacency, Archetype, BytePath, Evaluation, Glob, IteratorExt as _, TokenKind, Wildcard,
+    };
 
     #[test]
     fn adjacent() {
@@ -913,6 +1252,21 @@ mod tests {
         assert!(Glob::new("{**/okay,prefix{**/error}}postfix").is_err());
     }
 
+    #[test]
+    fn reject_glob_with_invalid_named_alternative_tokens() {
+        assert!(Glob::new("{a,{<name>b}}").is_err());
+        assert!(Glob::new("{<name>a}/{<name>b}").is_err());
+    }
+
+    #[test]
+    fn reject_glob_with_invalid_repetition_tokens() {
+        assert!(Glob::new("a/<**/:2>").is_err());
+        assert!(Glob::new("<{a,**/b}:2>").is_err());
+        assert!(Glob::new("<a:3,1>").is_err());
+        assert!(Glob::new("<a:>").is_err());
+        assert!(Glob::new("<a>").is_err());
+    }
+
     #[test]
     fn reject_glob_with_invalid_separator_tokens() {
         assert!(Glob::new("//a").is_err());
@@ -924,16 +1278,17 @@ mod tests {
     fn match_glob_with_tree_tokens() {
         let glob = Glob::new("a/**/b").unwrap();
 
-        assert!(glob.is_match(Path::new("a/b")));
-        assert!(glob.is_match(Path::new("a/x/b")));
-        assert!(glob.is_match(Path::new("a/x/y/z/b")));
+        assert!(glob.is_match(Path::new("a/b")).unwrap());
+        assert!(glob.is_match(Path::new("a/x/b")).unwrap());
+        assert!(glob.is_match(Path::new("a/x/y/z/b")).unwrap());
 
-        assert!(!glob.is_match(Path::new("a")));
-        assert!(!glob.is_match(Path::new("b/a")));
+        assert!(!glob.is_match(Path::new("a")).unwrap());
+        assert!(!glob.is_match(Path::new("b/a")).unwrap());
 
         assert_eq!(
             b"x/y/z/",
             glob.captures(&BytePath::from_path(Path::new("a/x/y/z/b")))
+                .unwrap()
                 .unwrap()
                 .get(1)
                 .unwrap(),
@@ -944,12 +1299,12 @@ mod tests {
     fn match_glob_with_tree_and_zom_tokens() {
         let glob = Glob::new("**/*.ext").unwrap();
 
-        assert!(glob.is_match(Path::new("file.ext")));
-        assert!(glob.is_match(Path::new("a/file.ext")));
-        assert!(glob.is_match(Path::new("a/b/file.ext")));
+        assert!(glob.is_match(Path::new("file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/b/file.ext")).unwrap());
 
         let path = BytePath::from_path(Path::new("a/file.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"a/", captures.get(1).unwrap());
         assert_eq!(b"file", captures.get(2).unwrap());
     }
@@ -958,43 +1313,82 @@ mod tests {
     fn match_glob_with_eager_and_lazy_zom_tokens() {
         let glob = Glob::new("$-*.*").unwrap();
 
-        assert!(glob.is_match(Path::new("prefix-file.ext")));
-        assert!(glob.is_match(Path::new("a-b-c.ext")));
+        assert!(glob.is_match(Path::new("prefix-file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a-b-c.ext")).unwrap());
 
         let path = BytePath::from_path(Path::new("a-b-c.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"a", captures.get(1).unwrap());
         assert_eq!(b"b-c", captures.get(2).unwrap());
         assert_eq!(b"ext", captures.get(3).unwrap());
     }
 
+    #[test]
+    fn match_glob_and_iterate_captures() {
+        let glob = Glob::new("{a,x}/**/*.ext").unwrap();
+
+        let path = BytePath::from_path(Path::new("a/file.ext"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        assert_eq!(4, captures.len());
+        assert_eq!(
+            vec![
+                Some(b"a/file.ext".as_ref()),
+                Some(b"a".as_ref()),
+                None,
+                Some(b"file".as_ref()),
+            ],
+            captures.iter().collect::<Vec<_>>(),
+        );
+        assert_eq!(captures.len(), captures.to_owned().iter().len());
+    }
+
     #[test]
     fn match_glob_with_class_tokens() {
         let glob = Glob::new("a/[xyi-k]/**").unwrap();
 
-        assert!(glob.is_match(Path::new("a/x/file.ext")));
-        assert!(glob.is_match(Path::new("a/y/file.ext")));
-        assert!(glob.is_match(Path::new("a/j/file.ext")));
+        assert!(glob.is_match(Path::new("a/x/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/y/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/j/file.ext")).unwrap());
 
-        assert!(!glob.is_match(Path::new("a/b/file.ext")));
+        assert!(!glob.is_match(Path::new("a/b/file.ext")).unwrap());
 
         let path = BytePath::from_path(Path::new("a/i/file.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"i", captures.get(1).unwrap());
     }
 
+    #[test]
+    fn match_glob_with_posix_class_tokens() {
+        let glob = Glob::new("a/[[:digit:]_]<[[:alpha:]]:2>.ext").unwrap();
+
+        assert!(glob.is_match(Path::new("a/1xy.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/_XY.ext")).unwrap());
+
+        assert!(!glob.is_match(Path::new("a/x12.ext")).unwrap());
+        assert!(!glob.is_match(Path::new("a/-xy.ext")).unwrap());
+
+        let glob = Glob::new("a/[![:space:]]").unwrap();
+
+        assert!(glob.is_match(Path::new("a/b")).unwrap());
+
+        assert!(!glob.is_match(Path::new("a/ ")).unwrap());
+        assert!(!glob.is_match(Path::new("a//")).unwrap());
+
+        assert!(Glob::new("a/[[:bogus:]]").is_err());
+    }
+
     #[test]
     fn match_glob_with_literal_escaped_class_tokens() {
         let glob = Glob::new("a/[\\[\\]\\-]/**").unwrap();
 
-        assert!(glob.is_match(Path::new("a/[/file.ext")));
-        assert!(glob.is_match(Path::new("a/]/file.ext")));
-        assert!(glob.is_match(Path::new("a/-/file.ext")));
+        assert!(glob.is_match(Path::new("a/[/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/]/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/-/file.ext")).unwrap());
 
-        assert!(!glob.is_match(Path::new("a/b/file.ext")));
+        assert!(!glob.is_match(Path::new("a/b/file.ext")).unwrap());
 
         let path = BytePath::from_path(Path::new("a/[/file.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"[", captures.get(1).unwrap());
     }
 
@@ -1002,36 +1396,165 @@ mod tests {
     fn match_glob_with_alternative_tokens() {
         let glob = Glob::new("a/{x?z,y$}b/*").unwrap();
 
-        assert!(glob.is_match(Path::new("a/xyzb/file.ext")));
-        assert!(glob.is_match(Path::new("a/yb/file.ext")));
+        assert!(glob.is_match(Path::new("a/xyzb/file.ext")).unwrap());
+        assert!(glob.is_match(Path::new("a/yb/file.ext")).unwrap());
 
-        assert!(!glob.is_match(Path::new("a/xyz/file.ext")));
-        assert!(!glob.is_match(Path::new("a/y/file.ext")));
-        assert!(!glob.is_match(Path::new("a/xyzub/file.ext")));
+        assert!(!glob.is_match(Path::new("a/xyz/file.ext")).unwrap());
+        assert!(!glob.is_match(Path::new("a/y/file.ext")).unwrap());
+        assert!(!glob.is_match(Path::new("a/xyzub/file.ext")).unwrap());
 
         let path = BytePath::from_path(Path::new("a/xyzb/file.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"xyz", captures.get(1).unwrap());
     }
 
+    #[test]
+    fn match_glob_with_named_alternative_tokens() {
+        let glob = Glob::new("{<year>[0-9][0-9]*}/{<name>*}.{jpg,png}").unwrap();
+
+        assert!(glob.is_match(Path::new("2024/photo.jpg")).unwrap());
+        assert!(!glob.is_match(Path::new("photos/photo.jpg")).unwrap());
+        assert_eq!(glob.capture_index("year"), Some(1));
+        assert_eq!(glob.capture_index("name"), Some(2));
+        assert_eq!(glob.capture_index("missing"), None);
+
+        let path = BytePath::from_path(Path::new("2024/photo.png"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        assert_eq!(b"2024", captures.name("year").unwrap());
+        assert_eq!(b"photo", captures.name("name").unwrap());
+        assert_eq!(b"photo", captures.get(2).unwrap());
+        assert_eq!(b"png", captures.get(3).unwrap());
+        assert!(captures.name("missing").is_none());
+        assert_eq!(b"photo", captures.into_owned().name("name").unwrap());
+    }
+
+    #[test]
+    fn match_glob_with_colons_in_alternative_tokens() {
+        let glob = Glob::new("{a:b,c}").unwrap();
+
+        assert!(glob.is_match(Path::new("a:b")).unwrap());
+        assert!(glob.is_match(Path::new("c")).unwrap());
+        assert_eq!(glob.capture_index("a"), None);
+
+        let glob = Glob::new("{C:/x,D:/y}").unwrap();
+
+        assert!(glob.is_match(Path::new("C:/x")).unwrap());
+        assert!(glob.is_match(Path::new("D:/y")).unwrap());
+        assert_eq!(glob.capture_index("C"), None);
+    }
+
     #[test]
     fn match_glob_with_nested_alternative_tokens() {
         let glob = Glob::new("a/{y$,{x?z,?z}}b/*").unwrap();
 
         let path = BytePath::from_path(Path::new("a/xyzb/file.ext"));
-        let captures = glob.captures(&path).unwrap();
+        let captures = glob.captures(&path).unwrap().unwrap();
         assert_eq!(b"xyz", captures.get(1).unwrap());
     }
 
+    #[test]
+    fn match_glob_against_path_list() {
+        let glob = Glob::new("src/{<name>*}.rs").unwrap();
+        let paths = [
+            Path::new("src/lib.rs"),
+            Path::new("src/glob/mod.rs"),
+            Path::new("README.md"),
+            Path::new("src/main.rs"),
+        ];
+        let matched: Vec<_> = glob
+            .matched(paths.iter().copied())
+            .unwrap()
+            .map(|(path, captures)| (path, captures.name("name").unwrap().to_vec()))
+            .collect();
+
+        assert_eq!(
+            matched,
+            [
+                (Path::new("src/lib.rs"), b"lib".to_vec()),
+                (Path::new("src/main.rs"), b"main".to_vec()),
+            ],
+        );
+    }
+
+    #[test]
+    fn match_glob_with_numeric_range_tokens() {
+        let glob = Glob::new("track{01..20}.flac").unwrap();
+
+        assert!(glob.is_match(Path::new("track01.flac")).unwrap());
+        assert!(glob.is_match(Path::new("track09.flac")).unwrap());
+        assert!(glob.is_match(Path::new("track20.flac")).unwrap());
+
+        assert!(!glob.is_match(Path::new("track00.flac")).unwrap());
+        assert!(!glob.is_match(Path::new("track1.flac")).unwrap());
+        assert!(!glob.is_match(Path::new("track21.flac")).unwrap());
+
+        let path = BytePath::from_path(Path::new("track17.flac"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        assert_eq!(b"17", captures.get(1).unwrap());
+
+        let glob = Glob::new("{<n>7..1234}").unwrap();
+        assert_eq!(glob.capture_index("n"), Some(1));
+        for n in 0..2000 {
+            assert_eq!(
+                glob.is_match(Path::new(&n.to_string())).unwrap(),
+                (7..=1234).contains(&n),
+                "{}",
+                n,
+            );
+            assert!(!glob.is_match(Path::new(&format!("0{}", n))).unwrap());
+        }
+        // Bounds may be given in either order and are padded to the width of
+        // the widest bound.
+        let glob = Glob::new("{0950..3}").unwrap();
+        for n in 0..2000 {
+            assert_eq!(
+                glob.is_match(Path::new(&format!("{:04}", n))).unwrap(),
+                (3..=950).contains(&n),
+                "{}",
+                n,
+            );
+        }
+    }
+
+    #[test]
+    fn match_glob_with_repetition_tokens() {
+        let glob = Glob::new("img<[0-9]:3>.png").unwrap();
+
+        assert!(glob.is_match(Path::new("img042.png")).unwrap());
+
+        assert!(!glob.is_match(Path::new("img42.png")).unwrap());
+        assert!(!glob.is_match(Path::new("img0042.png")).unwrap());
+        assert!(!glob.is_match(Path::new("imgabc.png")).unwrap());
+
+        let path = BytePath::from_path(Path::new("img042.png"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        assert_eq!(b"042", captures.get(1).unwrap());
+
+        let glob = Glob::new("a/<*/:1,2>b").unwrap();
+
+        assert!(glob.is_match(Path::new("a/x/b")).unwrap());
+        assert!(glob.is_match(Path::new("a/x/y/b")).unwrap());
+
+        assert!(!glob.is_match(Path::new("a/b")).unwrap());
+        assert!(!glob.is_match(Path::new("a/x/y/z/b")).unwrap());
+
+        let glob = Glob::new("<{ab,c}:2,>\\:").unwrap();
+
+        assert!(glob.is_match(Path::new("abc:")).unwrap());
+        assert!(glob.is_match(Path::new("cabab:")).unwrap());
+
+        assert!(!glob.is_match(Path::new("ab:")).unwrap());
+    }
+
     #[test]
     fn match_glob_with_alternative_tree_tokens() {
         let glob = Glob::new("a/{foo,bar,**/baz}/qux").unwrap();
 
-        assert!(glob.is_match(Path::new("a/foo/qux")));
-        assert!(glob.is_match(Path::new("a/foo/baz/qux")));
-        assert!(glob.is_match(Path::new("a/foo/bar/baz/qux")));
+        assert!(glob.is_match(Path::new("a/foo/qux")).unwrap());
+        assert!(glob.is_match(Path::new("a/foo/baz/qux")).unwrap());
+        assert!(glob.is_match(Path::new("a/foo/bar/baz/qux")).unwrap());
 
-        assert!(!glob.is_match(Path::new("a/foo/bar/qux")));
+        assert!(!glob.is_match(Path::new("a/foo/bar/qux")).unwrap());
     }
 
     #[test]
@@ -1040,8 +1563,76 @@ mod tests {
 
         assert_eq!(prefix, Path::new("a/b"));
 
-        assert!(glob.is_match(Path::new("xyz/file.ext")));
-        assert!(glob.is_match(Path::new("a/b/xyz/file.ext").strip_prefix(prefix).unwrap()));
+        assert!(glob.is_match(Path::new("xyz/file.ext")).unwrap());
+        assert!(glob
+            .is_match(Path::new("a/b/xyz/file.ext").strip_prefix(prefix).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn match_glob_case_insensitive() {
+        let glob = Glob::new("photos/**/*.jpg").unwrap();
+
+        assert!(!glob.is_match(Path::new("photos/a/b.JPG")).unwrap());
+
+        let glob = glob.case_insensitive();
+
+        assert!(glob.is_match(Path::new("photos/a/b.JPG")).unwrap());
+        assert!(glob.is_match(Path::new("Photos/a/b.Jpg")).unwrap());
+        assert!(!glob.is_match(Path::new("photos/a/b.png")).unwrap());
+    }
+
+    #[test]
+    fn match_literal_glob_without_compiling() {
+        let glob = Glob::new("a/b.txt").unwrap();
+
+        assert!(glob.is_match(Path::new("a/b.txt")).unwrap());
+        assert!(!glob.is_match(Path::new("a/b.txt/c")).unwrap());
+        assert!(!glob.is_match(Path::new("a/b.tx")).unwrap());
+        assert!(!glob.is_match(Path::new("a/B.txt")).unwrap());
+        assert_eq!(
+            glob.captures(&BytePath::from_path(Path::new("a/b.txt")))
+                .unwrap()
+                .unwrap()
+                .matched(),
+            b"a/b.txt"
+        );
+        assert!(glob.regex.get().is_none());
+
+        let glob = glob.case_insensitive();
+
+        assert!(glob.is_match(Path::new("A/b.TXT")).unwrap());
+        assert!(glob.regex.get().is_none());
+    }
+
+    #[test]
+    fn compile_glob_lazily() {
+        let glob = Glob::new("a/{b,c}/**/*.txt").unwrap();
+        assert!(glob.regex.get().is_none());
+
+        assert!(glob.is_match(Path::new("a/b/x/y.txt")).unwrap());
+        assert!(glob.regex.get().is_some());
+    }
+
+    #[test]
+    fn match_glob_that_fails_to_compile() {
+        let glob = Glob::new("<<<[a-z]:1,100>:1,100>:1,100>").unwrap();
+        let path = BytePath::from_path(Path::new("abc"));
+
+        assert!(glob.compile().is_err());
+        assert!(glob.is_match(Path::new("abc")).is_err());
+        assert!(glob.captures(&path).is_err());
+        assert!(glob.matched([Path::new("abc")]).is_err());
+    }
+
+    #[test]
+    fn count_glob_captures_without_compiling() {
+        for text in ["a/*.txt", "**/{a,b}/?[xy]", "literal/path", "*"] {
+            let glob = Glob::new(text).unwrap();
+            let len = glob.captures_len();
+            assert!(glob.regex.get().is_none());
+            assert_eq!(len, glob.compiled().unwrap().regex.captures_len());
+        }
     }
 
     #[test]
@@ -1050,8 +1641,10 @@ mod tests {
 
         assert_eq!(prefix, Path::new(""));
 
-        assert!(glob.is_match(Path::new("xyz/file.ext")));
-        assert!(glob.is_match(Path::new("xyz/file.ext").strip_prefix(prefix).unwrap()));
+        assert!(glob.is_match(Path::new("xyz/file.ext")).unwrap());
+        assert!(glob
+            .is_match(Path::new("xyz/file.ext").strip_prefix(prefix).unwrap())
+            .unwrap());
     }
 
     #[test]
@@ -1060,8 +1653,48 @@ mod tests {
 
         assert_eq!(prefix, Path::new("a/b"));
 
-        assert!(glob.is_match(Path::new("")));
-        assert!(glob.is_match(Path::new("a/b").strip_prefix(prefix).unwrap()));
+        assert!(glob.is_match(Path::new("")).unwrap());
+        assert!(glob
+            .is_match(Path::new("a/b").strip_prefix(prefix).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn partition_parsed_glob() {
+        let partition = Glob::new("a/b/x?z/**").unwrap().partition();
+
+        assert_eq!(partition.prefix(), Path::new("a/b"));
+        assert!(!partition.is_invariant());
+        assert!(partition.is_exhaustive());
+        assert!(partition
+            .variant()
+            .is_match(Path::new("xyz/file.ext"))
+            .unwrap());
+
+        let partition = Glob::new("a/b").unwrap().partition();
+
+        assert_eq!(partition.prefix(), Path::new("a/b"));
+        assert!(partition.is_invariant());
+        assert!(!partition.is_exhaustive());
+
+        let partition = Glob::new("*.txt").unwrap().partition();
+
+        assert_eq!(partition.prefix(), Path::new(""));
+        assert!(!partition.is_exhaustive());
+    }
+
+    #[test]
+    fn exhaustive_glob() {
+        assert!(Glob::new("**").unwrap().is_exhaustive());
+        assert!(Glob::new("a/**").unwrap().is_exhaustive());
+        assert!(Glob::new("a/*/**").unwrap().is_exhaustive());
+        assert!(Glob::new("{a,b}/**").unwrap().is_exhaustive());
+        assert!(Glob::new("{a/**,b/**}").unwrap().is_exhaustive());
+
+        assert!(!Glob::new("a").unwrap().is_exhaustive());
+        assert!(!Glob::new("a/*").unwrap().is_exhaustive());
+        assert!(!Glob::new("a/**/*.txt").unwrap().is_exhaustive());
+        assert!(!Glob::new("{a/**,b}").unwrap().is_exhaustive());
     }
 
     #[test]
@@ -1070,7 +1703,123 @@ mod tests {
 
         assert_eq!(prefix, Path::new(".."));
 
-        assert!(glob.is_match(Path::new("xyz/file.ext")));
-        assert!(glob.is_match(Path::new("../xyz/file.ext").strip_prefix(prefix).unwrap()));
+        assert!(glob.is_match(Path::new("xyz/file.ext")).unwrap());
+        assert!(glob
+            .is_match(Path::new("../xyz/file.ext").strip_prefix(prefix).unwrap())
+            .unwrap());
+    }
+
+    #[test]
+    fn partition_glob_with_windows_prefixes() {
+        let (prefix, glob) = Glob::partitioned("C:/music/**/*.flac").unwrap();
+
+        assert_eq!(prefix, Path::new("C:/music"));
+        assert!(glob.is_match(Path::new("a/b.flac")).unwrap());
+
+        #[cfg(windows)]
+        {
+            let (prefix, glob) = Glob::partitioned("//server/share/**").unwrap();
+
+            assert_eq!(prefix, Path::new("//server/share/"));
+            assert!(glob.is_match(Path::new("a/b")).unwrap());
+            assert!(prefix.is_absolute());
+        }
+
+        let glob = Glob::new("C:/*/{<name>*}.flac").unwrap();
+
+        assert!(glob.is_match(Path::new("C:/music/a.flac")).unwrap());
+        assert!(!glob.is_match(Path::new("D:/music/a.flac")).unwrap());
+        assert_eq!(glob.captures_len(), 3);
+        #[cfg(windows)]
+        assert!(glob.is_absolute());
+
+        // Prefixes are only recognized at the beginning of a glob.
+        assert!(Glob::new("a//server/share").is_err());
+        // Prefixes are only recognized on Windows.
+        #[cfg(not(windows))]
+        assert!(!Glob::new("x:/foo").unwrap().has_root());
+    }
+
+    #[test]
+    fn describe_glob_token_tree_with_captures() {
+        let glob = Glob::new("a/{b,c*}/*.txt").unwrap();
+
+        assert_eq!(
+            glob.token_tree(),
+            "literal `a`\n\
+             separator `/`\n\
+             alternative #1\n\
+             \x20 branch 1\n\
+             \x20   literal `b`\n\
+             \x20 branch 2\n\
+             \x20   literal `c`\n\
+             \x20   wildcard `*` (zero or more characters)\n\
+             separator `/`\n\
+             wildcard `*` (zero or more characters) #2\n\
+             literal `.txt`\n",
+        );
+    }
+
+    #[test]
+    fn inspect_glob_tokens() {
+        let glob = Glob::new("a/{<x>b,c*}/[!0-9]<?:1,3>.txt").unwrap();
+        let tokens: Vec<_> = glob.tokens().collect();
+
+        assert_eq!(tokens.len(), 7);
+        assert!(matches!(tokens[0].kind(), TokenKind::Literal("a")));
+        assert!(matches!(tokens[1].kind(), TokenKind::Separator));
+        assert!(matches!(
+            tokens[2].kind(),
+            TokenKind::Alternative { name: Some("x") }
+        ));
+        assert!(matches!(
+            tokens[4].kind(),
+            TokenKind::Class {
+                is_negated: true,
+                archetypes: [Archetype::Range('0', '9')],
+            }
+        ));
+        assert!(matches!(
+            tokens[5].kind(),
+            TokenKind::Repetition {
+                lower: 1,
+                upper: Some(3),
+            }
+        ));
+        assert!(matches!(tokens[6].kind(), TokenKind::Literal(".txt")));
+        assert_eq!(
+            tokens.iter().filter(|token| !token.is_literal()).count() + 1,
+            glob.captures_len(),
+        );
+
+        let branches = tokens[2].branches();
+        assert_eq!(branches.len(), 2);
+        assert!(matches!(branches[0][0].kind(), TokenKind::Literal("b")));
+        assert!(matches!(
+            branches[1][1].kind(),
+            TokenKind::Wildcard(Wildcard::ZeroOrMore(Evaluation::Eager))
+        ));
+        let branches = tokens[5].branches();
+        assert_eq!(branches.len(), 1);
+        assert!(matches!(
+            branches[0][0].kind(),
+            TokenKind::Wildcard(Wildcard::One)
+        ));
+        assert!(tokens[0].branches().is_empty());
+    }
+
+    #[test]
+    fn parse_glob_error_offset() {
+        let text = "a/{b,c";
+        let error = Glob::new(text).unwrap_err();
+
+        assert_eq!(error.parse_offset(text), Some(2));
+    }
+
+    #[test]
+    fn categorize_glob_errors() {
+        assert_eq!(Glob::new("a/{b,c").unwrap_err().kind(), ErrorKind::Parse);
+        assert_eq!(Glob::new("a/**/**").unwrap_err().kind(), ErrorKind::Invalid);
+        assert!(Glob::new("a/**/**").unwrap_err().path().is_none());
     }
 }
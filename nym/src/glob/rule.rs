@@ -10,22 +10,133 @@
 //! neighboring tokens.
 
 use itertools::Itertools as _;
+use std::collections::HashSet;
 use thiserror::Error;
 
-use crate::glob::token::{self, Component, Token};
+use crate::glob::token::{self, Component, Token, Wildcard};
 use crate::glob::{IteratorExt as _, SliceExt as _, Terminals};
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
 #[non_exhaustive]
 pub enum RuleError {
     #[error("invalid separator `/` in alternative")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::alternative_separator),
+            help("move separators out of alternatives, such as `foo/{{bar,baz}}`")
+        )
+    )]
     AlternativeSeparator,
     #[error("invalid tree wildcard `**` in alternative")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::alternative_tree),
+            help("move tree wildcards out of alternatives, such as `foo/**/{{bar,baz}}`")
+        )
+    )]
     AlternativeTree,
     #[error("invalid zero-or-more wildcard `*` or `$` in alternative")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::alternative_zero_or_more),
+            help("zero-or-more wildcards cannot be adjacent across alternatives")
+        )
+    )]
     AlternativeZeroOrMore,
+    #[error("invalid named capture in alternative")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::alternative_name),
+            help("move named captures out of alternatives, such as `{{<name>foo,bar}}`")
+        )
+    )]
+    AlternativeName,
+    #[error("duplicate capture name")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::name_duplicate),
+            help("give each named capture a distinct name")
+        )
+    )]
+    NameDuplicate,
     #[error("adjacent component boundaries `/` or `**`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::boundary_adjacent),
+            help("remove repeated separators and tree wildcards")
+        )
+    )]
     BoundaryAdjacent,
+    #[error("invalid tree wildcard `**` in repetition")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::glob::rule::repetition_tree),
+            help("repeat components instead of tree wildcards, such as `<*/:0,3>`")
+        )
+    )]
+    RepetitionTree,
+}
+
+impl RuleError {
+    /// Gets an example of a glob that violates the rule.
+    pub fn example(&self) -> &'static str {
+        match self {
+            RuleError::AlternativeSeparator => "foo/{bar,/baz}",
+            RuleError::AlternativeTree => "foo{bar,**/baz}",
+            RuleError::AlternativeZeroOrMore => "foo*{bar,*baz}",
+            RuleError::AlternativeName => "foo{bar,{<name>baz}}",
+            RuleError::NameDuplicate => "{<name>foo}/{<name>bar}",
+            RuleError::BoundaryAdjacent => "foo/**/**",
+            RuleError::RepetitionTree => "foo/<**/:2>",
+        }
+    }
+}
+
+/// Gets the rules that apply to a token sequence.
+///
+/// Applicable rules reject variants of the token sequence that differ only
+/// slightly, such as by moving a separator into an alternative. Note that
+/// this does not check the token sequence; see `check`.
+pub fn constraints<'t, I>(tokens: I) -> Vec<RuleError>
+where
+    I: IntoIterator<Item = &'t Token<'t>>,
+{
+    let mut constraints = Vec::new();
+    let tokens: Vec<_> = tokens.into_iter().collect();
+    if tokens
+        .iter()
+        .any(|token| matches!(token, Token::Alternative(_)))
+    {
+        constraints.extend([
+            RuleError::AlternativeSeparator,
+            RuleError::AlternativeTree,
+            RuleError::AlternativeZeroOrMore,
+            RuleError::AlternativeName,
+        ]);
+    }
+    if tokens.iter().any(|token| {
+        matches!(token, Token::Alternative(ref alternative) if alternative.name().is_some())
+    }) {
+        constraints.push(RuleError::NameDuplicate);
+    }
+    if tokens.iter().any(|token| token.is_component_boundary()) {
+        constraints.push(RuleError::BoundaryAdjacent);
+    }
+    if tokens
+        .iter()
+        .any(|token| matches!(token, Token::Repetition(_)))
+    {
+        constraints.push(RuleError::RepetitionTree);
+    }
+    constraints
 }
 
 pub fn check<'t, I>(tokens: I) -> Result<(), RuleError>
@@ -35,7 +146,9 @@ where
 {
     let tokens = tokens.into_iter();
     alternative(tokens.clone())?;
-    boundary(tokens)?;
+    name(tokens.clone())?;
+    boundary(tokens.clone())?;
+    repetition(tokens)?;
     Ok(())
 }
 
@@ -66,6 +179,16 @@ where
                 let left = left.cloned().or(parent.0);
                 let right = right.cloned().or(parent.1);
                 for tokens in alternative.branches() {
+                    if tokens.iter().any(|token| match token {
+                        Alternative(ref alternative) => alternative.name().is_some(),
+                        _ => false,
+                    }) {
+                        // Only top-level alternatives are captured, so
+                        // disallow named captures within alternatives.
+                        //
+                        // For example, `foo{bar,{<name>baz}}`.
+                        return Err(RuleError::AlternativeName);
+                    }
                     if let Some(terminals) = tokens.terminals() {
                         // Check branch terminals against the tokens adjacent to
                         // their corresponding alternative token.
@@ -173,6 +296,23 @@ where
     recurse(token::components(tokens), (None, None))
 }
 
+fn name<'t, I>(tokens: I) -> Result<(), RuleError>
+where
+    I: IntoIterator<Item = &'t Token<'t>>,
+{
+    let mut names = HashSet::new();
+    for token in tokens {
+        if let Token::Alternative(ref alternative) = token {
+            if let Some(name) = alternative.name() {
+                if !names.insert(name) {
+                    return Err(RuleError::NameDuplicate);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 fn boundary<'t, I>(tokens: I) -> Result<(), RuleError>
 where
     I: IntoIterator<Item = &'t Token<'t>>,
@@ -189,3 +329,40 @@ where
         Ok(())
     }
 }
+
+fn repetition<'t, I>(tokens: I) -> Result<(), RuleError>
+where
+    I: IntoIterator<Item = &'t Token<'t>>,
+{
+    fn recurse<'t>(
+        tokens: impl IntoIterator<Item = &'t Token<'t>>,
+        is_repeated: bool,
+    ) -> Result<(), RuleError> {
+        for token in tokens {
+            match token {
+                // Tree wildcards compile differently depending on their
+                // position in the glob, so they cannot be repeated.
+                //
+                // For example, `foo/<**/:2>`.
+                Token::Wildcard(Wildcard::Tree) if is_repeated => {
+                    return Err(RuleError::RepetitionTree);
+                }
+                Token::Alternative(ref alternative) => {
+                    for tokens in alternative.branches() {
+                        recurse(tokens, is_repeated)?;
+                    }
+                }
+                Token::Repetition(ref repetition) => {
+                    // Check alternatives within the repetition as if they were
+                    // top-level.
+                    check(repetition.tokens().iter())?;
+                    recurse(repetition.tokens(), true)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    recurse(tokens, false)
+}
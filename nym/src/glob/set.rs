@@ -0,0 +1,215 @@
+#[cfg(feature = "fs")]
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::glob::{BytePath, Captures, Glob, GlobError};
+#[cfg(feature = "fs")]
+use crate::glob::{Walk, WalkBehavior, WalkEntry};
+
+/// Union of globs.
+///
+/// Paths match a set if they match any of its globs. The first glob in the set
+/// that matches a path determines its captures, so globs are ordered by
+/// precedence.
+#[derive(Clone, Debug)]
+pub struct GlobSet<'t> {
+    globs: Vec<(PathBuf, Glob<'t>)>,
+}
+
+impl<'t> GlobSet<'t> {
+    pub fn new(globs: impl IntoIterator<Item = Glob<'t>>) -> Self {
+        GlobSet {
+            globs: globs
+                .into_iter()
+                .map(|glob| (PathBuf::new(), glob))
+                .collect(),
+        }
+    }
+
+    /// Constructs a set from globs partitioned from their literal path
+    /// prefixes (see `Glob::partitioned`).
+    ///
+    /// When walking, each glob is matched against paths beneath its prefix.
+    /// Otherwise, paths are matched against the globs as is, like the globs of
+    /// `Glob::partitioned`.
+    pub fn partitioned(globs: impl IntoIterator<Item = (PathBuf, Glob<'t>)>) -> Self {
+        GlobSet {
+            globs: globs.into_iter().collect(),
+        }
+    }
+
+    pub fn into_owned(self) -> GlobSet<'static> {
+        GlobSet {
+            globs: self
+                .globs
+                .into_iter()
+                .map(|(prefix, glob)| (prefix, glob.into_owned()))
+                .collect(),
+        }
+    }
+
+    /// Matches paths without regard to (ASCII) case.
+    ///
+    /// See `Glob::case_insensitive`.
+    pub fn case_insensitive(self) -> Self {
+        GlobSet {
+            globs: self
+                .globs
+                .into_iter()
+                .map(|(prefix, glob)| (prefix, glob.case_insensitive()))
+                .collect(),
+        }
+    }
+
+    /// Gets the number of globs in the set.
+    pub fn len(&self) -> usize {
+        self.globs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    /// Gets the globs in the set in order of precedence.
+    pub fn globs(&self) -> impl '_ + ExactSizeIterator<Item = &'_ Glob<'t>> {
+        self.globs.iter().map(|(_, glob)| glob)
+    }
+
+    /// Gets the index of the capture with the given name.
+    ///
+    /// Returns `None` if no glob has a capture with the given name or if the
+    /// globs that do disagree on its index.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        let mut indices = self.globs().filter_map(|glob| glob.capture_index(name));
+        let index = indices.next()?;
+        indices.all(|other| other == index).then_some(index)
+    }
+
+    /// Determines if any glob in the set matches a path.
+    ///
+    /// Returns an error if a glob fails to compile. See `Glob::compile`.
+    pub fn is_match(&self, path: impl AsRef<Path>) -> Result<bool, GlobError> {
+        let path = path.as_ref();
+        for glob in self.globs() {
+            if glob.is_match(path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Gets the index and captures of the first glob in the set that matches
+    /// a path.
+    ///
+    /// Returns an error if a glob fails to compile. See `Glob::compile`.
+    pub fn captures<'p>(
+        &self,
+        path: &'p BytePath<'_>,
+    ) -> Result<Option<(usize, Captures<'p>)>, GlobError> {
+        for (index, glob) in self.globs().enumerate() {
+            if let Some(captures) = glob.captures(path)? {
+                return Ok(Some((index, captures)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Walks a directory tree and yields files matching any glob in the set
+    /// along with the index of the glob that matched.
+    ///
+    /// Each glob is walked in turn, so entries are ordered by the globs that
+    /// match them. Entries are yielded at most once, so an entry that matches
+    /// more than one glob is yielded with the index and captures of the first
+    /// glob that it matches.
+    #[cfg(feature = "fs")]
+    pub fn walk<'a>(
+        &'a self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+    ) -> impl 'a + Iterator<Item = Result<(usize, WalkEntry<'static>), GlobError>> {
+        let behavior = behavior.into();
+        let limit = behavior.limit;
+        let walks = self.walks(
+            directory,
+            WalkBehavior {
+                limit: usize::MAX,
+                ..behavior
+            },
+        );
+        let mut paths = HashSet::new();
+        walks
+            .enumerate()
+            .flat_map(|(index, walk)| walk.map(move |entry| entry.map(|entry| (index, entry))))
+            .filter(move |entry| {
+                entry
+                    .as_ref()
+                    .map_or(true, |(_, entry)| paths.insert(entry.path().to_path_buf()))
+            })
+            .take(limit)
+    }
+
+    /// Gets a walk for each glob in the set in order of precedence.
+    ///
+    /// Walks may yield the same entries. See `walk`.
+    #[cfg(feature = "fs")]
+    pub(in crate) fn walks<'a>(
+        &'a self,
+        directory: impl AsRef<Path>,
+        behavior: WalkBehavior,
+    ) -> impl 'a + Iterator<Item = Walk<'a>> {
+        let directory = directory.as_ref().to_path_buf();
+        self.globs.iter().map(move |(prefix, glob)| {
+            glob.walk(directory.join(prefix), behavior.clone())
+                .beneath(&directory)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::glob::{BytePath, Glob, GlobSet};
+
+    #[test]
+    fn match_glob_set() {
+        let set = GlobSet::new([
+            Glob::new("{<name>*}.flac").unwrap(),
+            Glob::new("*/{<name>*}.mp3").unwrap(),
+        ]);
+
+        assert!(set.is_match(Path::new("a.flac")).unwrap());
+        assert!(set.is_match(Path::new("album/a.mp3")).unwrap());
+        assert!(!set.is_match(Path::new("a.mp3")).unwrap());
+        assert_eq!(set.capture_index("name"), None);
+
+        let path = BytePath::from_path(Path::new("album/a.mp3"));
+        let (index, captures) = set.captures(&path).unwrap().unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(b"a", captures.name("name").unwrap());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn walk_glob_set() {
+        use crate::fixture::TreeFixture;
+
+        let tree = TreeFixture::new()
+            .file("a.flac", "")
+            .file("b.mp3", "")
+            .file("c.txt", "");
+        let set = GlobSet::new([
+            Glob::new("*.flac").unwrap(),
+            Glob::new("*.{flac,mp3}").unwrap(),
+        ]);
+        let mut entries: Vec<_> = set
+            .walk(tree.path(), usize::MAX)
+            .map(|entry| {
+                let (index, entry) = entry.unwrap();
+                (index, entry.matched().to_vec())
+            })
+            .collect();
+        entries.sort();
+        assert_eq!(entries, [(0, b"a.flac".to_vec()), (1, b"b.mp3".to_vec())],);
+    }
+}
@@ -1,32 +1,52 @@
 use itertools::Itertools as _;
 use smallvec::{smallvec, SmallVec};
 use std::borrow::Cow;
-use std::path::{PathBuf, MAIN_SEPARATOR};
+use std::fmt::{self, Display};
+use std::path::{PathBuf, MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
 
 use crate::glob::rule;
 use crate::glob::GlobError;
 
 #[derive(Clone, Debug)]
-pub struct Alternative<'t>(pub Vec<Vec<Token<'t>>>);
+pub struct Alternative<'t> {
+    name: Option<Cow<'t, str>>,
+    branches: Vec<Vec<Token<'t>>>,
+}
 
 impl<'t> Alternative<'t> {
+    /// Constructs an alternative with a named capture, such as `{<name>*}`.
+    pub fn named(name: impl Into<Cow<'t, str>>, branches: Vec<Vec<Token<'t>>>) -> Self {
+        Alternative {
+            name: Some(name.into()),
+            branches,
+        }
+    }
+
     pub fn into_owned(self) -> Alternative<'static> {
-        Alternative(
-            self.0
+        let Alternative { name, branches } = self;
+        Alternative {
+            name: name.map(|name| name.into_owned().into()),
+            branches: branches
                 .into_iter()
                 .map(|tokens| tokens.into_iter().map(|token| token.into_owned()).collect())
                 .collect(),
-        )
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
     }
 
     pub fn branches(&self) -> &Vec<Vec<Token<'t>>> {
-        &self.0
+        &self.branches
     }
 
+    #[cfg(feature = "fs")]
     pub fn has_component_boundary(&self) -> bool {
-        self.0.iter().any(|tokens| {
+        self.branches.iter().any(|tokens| {
             tokens.iter().any(|token| match token {
                 Token::Alternative(ref alternative) => alternative.has_component_boundary(),
+                Token::Repetition(ref repetition) => repetition.has_component_boundary(),
                 _ => token.is_component_boundary(),
             })
         })
@@ -34,15 +54,90 @@ impl<'t> Alternative<'t> {
 }
 
 impl<'t> From<Vec<Vec<Token<'t>>>> for Alternative<'t> {
-    fn from(alternatives: Vec<Vec<Token<'t>>>) -> Self {
-        Alternative(alternatives)
+    fn from(branches: Vec<Vec<Token<'t>>>) -> Self {
+        Alternative {
+            name: None,
+            branches,
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Member of a character class, such as `a`, `a-z`, or `[:digit:]` in
+/// `[_a-z[:digit:]]`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Archetype {
     Character(char),
     Range(char, char),
+    Posix(PosixClass),
+}
+
+/// POSIX character class, such as `[:alpha:]`.
+///
+/// POSIX classes only match ASCII characters.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PosixClass {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    XDigit,
+}
+
+impl PosixClass {
+    const ALL: [PosixClass; 12] = [
+        PosixClass::Alnum,
+        PosixClass::Alpha,
+        PosixClass::Blank,
+        PosixClass::Cntrl,
+        PosixClass::Digit,
+        PosixClass::Graph,
+        PosixClass::Lower,
+        PosixClass::Print,
+        PosixClass::Punct,
+        PosixClass::Space,
+        PosixClass::Upper,
+        PosixClass::XDigit,
+    ];
+
+    /// Gets the name of the class, such as `alpha`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PosixClass::Alnum => "alnum",
+            PosixClass::Alpha => "alpha",
+            PosixClass::Blank => "blank",
+            PosixClass::Cntrl => "cntrl",
+            PosixClass::Digit => "digit",
+            PosixClass::Graph => "graph",
+            PosixClass::Lower => "lower",
+            PosixClass::Print => "print",
+            PosixClass::Punct => "punct",
+            PosixClass::Space => "space",
+            PosixClass::Upper => "upper",
+            PosixClass::XDigit => "xdigit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        PosixClass::ALL
+            .iter()
+            .copied()
+            .find(|class| class.name() == name)
+    }
+}
+
+impl From<PosixClass> for Archetype {
+    fn from(class: PosixClass) -> Archetype {
+        Archetype::Posix(class)
+    }
 }
 
 impl From<char> for Archetype {
@@ -57,16 +152,286 @@ impl From<(char, char)> for Archetype {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Range of non-negative integers, such as `01..20`.
+///
+/// If either bound has a leading zero, then integers are padded with zeros to
+/// the width of the widest bound. Bounds may be given in either order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NumericRange {
+    start: u64,
+    end: u64,
+    width: usize,
+}
+
+impl NumericRange {
+    pub(in crate) fn new(start: u64, end: u64, width: usize) -> Self {
+        NumericRange {
+            start: start.min(end),
+            end: start.max(end),
+            width,
+        }
+    }
+
+    /// Gets the inclusive lower and upper bounds of the range.
+    pub fn bounds(&self) -> (u64, u64) {
+        (self.start, self.end)
+    }
+
+    /// Gets the width to which integers are padded with zeros.
+    ///
+    /// The width is zero if integers are not padded.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn is_padded(&self) -> bool {
+        self.width > 0
+    }
+
+    /// Encodes the range as a regular expression that matches the decimal
+    /// text of exactly the integers in the range.
+    ///
+    /// The expression is an alternation that is not grouped.
+    pub(in crate) fn regex(&self) -> String {
+        fn digits(n: u64, width: usize) -> Vec<u8> {
+            format!("{:01$}", n, width).into_bytes()
+        }
+
+        // Encodes a range with bounds of the same number of digits.
+        fn encode(start: &[u8], end: &[u8], encodings: &mut Vec<String>, prefix: String) {
+            let (first, rest) = match (start.split_first(), end.split_first()) {
+                (Some((first, start)), Some((last, end))) => ((*first, *last), (start, end)),
+                _ => {
+                    encodings.push(prefix);
+                    return;
+                }
+            };
+            let (start, end) = rest;
+            let any = "[0-9]".repeat(start.len());
+            if first.0 == first.1 {
+                encode(
+                    start,
+                    end,
+                    encodings,
+                    format!("{}{}", prefix, first.0 as char),
+                );
+                return;
+            }
+            let (mut low, mut high) = (first.0, first.1);
+            if start.iter().any(|&digit| digit != b'0') {
+                let nines = vec![b'9'; start.len()];
+                encode(
+                    start,
+                    &nines,
+                    encodings,
+                    format!("{}{}", prefix, low as char),
+                );
+                low += 1;
+            }
+            let is_high_partial = end.iter().any(|&digit| digit != b'9');
+            if is_high_partial {
+                high -= 1;
+            }
+            if low <= high {
+                let class = if low == high {
+                    (low as char).to_string()
+                }
+                else {
+                    format!("[{}-{}]", low as char, high as char)
+                };
+                encodings.push(format!("{}{}{}", prefix, class, any));
+            }
+            if is_high_partial {
+                let zeros = vec![b'0'; end.len()];
+                encode(
+                    &zeros,
+                    end,
+                    encodings,
+                    format!("{}{}", prefix, first.1 as char),
+                );
+            }
+        }
+
+        let mut encodings = Vec::new();
+        if self.is_padded() {
+            encode(
+                &digits(self.start, self.width),
+                &digits(self.end, self.width),
+                &mut encodings,
+                String::new(),
+            );
+        }
+        else {
+            // Split the range into ranges of integers with the same number of
+            // digits, which do not have leading zeros.
+            let mut start = self.start;
+            while start <= self.end {
+                let len = digits(start, 0).len();
+                let end = 10u64
+                    .checked_pow(len as u32)
+                    .map_or(u64::MAX, |bound| bound - 1)
+                    .min(self.end);
+                encode(
+                    &digits(start, 0),
+                    &digits(end, 0),
+                    &mut encodings,
+                    String::new(),
+                );
+                match end.checked_add(1) {
+                    Some(next) => start = next,
+                    None => break,
+                }
+            }
+        }
+        encodings.join("|")
+    }
+}
+
+impl Display for NumericRange {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{:0width$}..{:0width$}",
+            self.start,
+            self.end,
+            width = self.width
+        )
+    }
+}
+
+/// A Windows path prefix at the beginning of a glob, such as `C:` in
+/// `C:/music/**`.
+///
+/// Prefixes are always followed by a separator or tree wildcard, so they
+/// always root the glob.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Prefix<'t> {
+    /// Disk prefix, such as `C:`.
+    Disk(char),
+    /// UNC prefix, such as `//server/share`.
+    Unc {
+        server: Cow<'t, str>,
+        share: Cow<'t, str>,
+    },
+}
+
+impl<'t> Prefix<'t> {
+    pub(in crate) fn into_owned(self) -> Prefix<'static> {
+        match self {
+            Prefix::Disk(letter) => Prefix::Disk(letter),
+            Prefix::Unc { server, share } => Prefix::Unc {
+                server: server.into_owned().into(),
+                share: share.into_owned().into(),
+            },
+        }
+    }
+
+    /// Gets the components of the prefix as they appear in a path with
+    /// normalized separators.
+    ///
+    /// For example, the components of `//server/share` are `server` and
+    /// `share`.
+    #[cfg(feature = "fs")]
+    pub(in crate) fn components(&self) -> SmallVec<[Cow<'_, str>; 2]> {
+        match self {
+            Prefix::Disk(letter) => smallvec![format!("{}:", letter).into()],
+            Prefix::Unc { server, share } => {
+                smallvec![server.as_ref().into(), share.as_ref().into()]
+            }
+        }
+    }
+
+    /// Gets the prefix as a native path with a trailing root separator.
+    pub fn to_native_root(&self) -> String {
+        match self {
+            Prefix::Disk(letter) => format!("{}:{}", letter, MAIN_SEPARATOR),
+            Prefix::Unc { server, share } => {
+                format!("{0}{0}{1}{0}{2}{0}", MAIN_SEPARATOR, server, share)
+            }
+        }
+    }
+}
+
+impl<'t> Display for Prefix<'t> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prefix::Disk(letter) => write!(formatter, "{}:", letter),
+            Prefix::Unc { server, share } => write!(formatter, "//{}/{}", server, share),
+        }
+    }
+}
+
+/// A bounded repetition of a token sequence, such as `<[0-9]:3>`.
+///
+/// The sequence is matched at least `lower` times and at most `upper` times.
+/// If there is no upper bound, then the sequence is matched any number of
+/// times beyond the lower bound.
+#[derive(Clone, Debug)]
+pub struct Repetition<'t> {
+    tokens: Vec<Token<'t>>,
+    lower: usize,
+    upper: Option<usize>,
+}
+
+impl<'t> Repetition<'t> {
+    pub fn new(tokens: Vec<Token<'t>>, lower: usize, upper: Option<usize>) -> Self {
+        Repetition {
+            tokens,
+            lower,
+            upper,
+        }
+    }
+
+    pub fn into_owned(self) -> Repetition<'static> {
+        let Repetition {
+            tokens,
+            lower,
+            upper,
+        } = self;
+        Repetition {
+            tokens: tokens.into_iter().map(|token| token.into_owned()).collect(),
+            lower,
+            upper,
+        }
+    }
+
+    pub fn tokens(&self) -> &Vec<Token<'t>> {
+        &self.tokens
+    }
+
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        (self.lower, self.upper)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn has_component_boundary(&self) -> bool {
+        self.tokens.iter().any(|token| match token {
+            Token::Alternative(ref alternative) => alternative.has_component_boundary(),
+            Token::Repetition(ref repetition) => repetition.has_component_boundary(),
+            _ => token.is_component_boundary(),
+        })
+    }
+}
+
+/// Evaluation of a zero-or-more wildcard, which is eager for `*` and lazy for
+/// `$`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Evaluation {
     Eager,
     Lazy,
 }
 
-#[derive(Clone, Debug)]
+/// Wildcard, such as `?`, `*`, `$`, or `**`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Wildcard {
+    /// Exactly one character `?`.
     One,
+    /// Zero or more characters `*` or `$`.
     ZeroOrMore(Evaluation),
+    /// Zero or more directories `**`.
     Tree,
 }
 
@@ -78,6 +443,9 @@ pub enum Token<'t> {
         archetypes: Vec<Archetype>,
     },
     Literal(Cow<'t, str>),
+    NumericRange(NumericRange),
+    Prefix(Prefix<'t>),
+    Repetition(Repetition<'t>),
     Separator,
     Wildcard(Wildcard),
 }
@@ -94,6 +462,9 @@ impl<'t> Token<'t> {
                 archetypes,
             },
             Token::Literal(literal) => literal.into_owned().into(),
+            Token::NumericRange(range) => Token::NumericRange(range),
+            Token::Prefix(prefix) => Token::Prefix(prefix.into_owned()),
+            Token::Repetition(repetition) => Token::Repetition(repetition.into_owned()),
             Token::Separator => Token::Separator,
             Token::Wildcard(wildcard) => Token::Wildcard(wildcard),
         }
@@ -128,6 +499,111 @@ impl From<Wildcard> for Token<'static> {
     }
 }
 
+/// Kind of a token in a glob.
+///
+/// See `GlobToken::kind`.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum TokenKind<'g> {
+    /// Alternative, such as `{a,b}` or `{<name>a,b}`.
+    ///
+    /// The branches of the alternative are given by `GlobToken::branches`.
+    Alternative {
+        name: Option<&'g str>,
+    },
+    /// Character class, such as `[a-z]` or `[!_]`.
+    Class {
+        is_negated: bool,
+        archetypes: &'g [Archetype],
+    },
+    /// Literal text with any escapes resolved, such as `.txt`.
+    Literal(&'g str),
+    /// Numeric range, such as `01..20` in `{01..20}`.
+    NumericRange(NumericRange),
+    /// Windows path prefix, such as `C:` in `C:/music`.
+    Prefix(&'g Prefix<'g>),
+    /// Bounded repetition, such as `<[0-9]:3>`.
+    ///
+    /// The repeated tokens are given by `GlobToken::branches`.
+    Repetition {
+        lower: usize,
+        upper: Option<usize>,
+    },
+    /// Path separator `/`.
+    Separator,
+    Wildcard(Wildcard),
+}
+
+/// Read-only view of a token in a glob.
+///
+/// See `Glob::tokens`.
+#[derive(Clone, Copy, Debug)]
+pub struct GlobToken<'g> {
+    token: &'g Token<'g>,
+}
+
+impl<'g> GlobToken<'g> {
+    pub fn kind(&self) -> TokenKind<'g> {
+        match self.token {
+            Token::Alternative(ref alternative) => TokenKind::Alternative {
+                name: alternative.name(),
+            },
+            Token::Class {
+                is_negated,
+                ref archetypes,
+            } => TokenKind::Class {
+                is_negated: *is_negated,
+                archetypes,
+            },
+            Token::Literal(ref literal) => TokenKind::Literal(literal),
+            Token::NumericRange(range) => TokenKind::NumericRange(*range),
+            Token::Prefix(ref prefix) => TokenKind::Prefix(prefix),
+            Token::Repetition(ref repetition) => {
+                let (lower, upper) = repetition.bounds();
+                TokenKind::Repetition { lower, upper }
+            }
+            Token::Separator => TokenKind::Separator,
+            Token::Wildcard(wildcard) => TokenKind::Wildcard(*wildcard),
+        }
+    }
+
+    /// Gets the token sequences nested within the token.
+    ///
+    /// Alternatives have a sequence for each of their branches and repetitions
+    /// have a single sequence of the repeated tokens. Other tokens have no
+    /// nested sequences.
+    pub fn branches(&self) -> Vec<Vec<GlobToken<'g>>> {
+        fn view<'g>(tokens: &'g [Token<'g>]) -> Vec<GlobToken<'g>> {
+            tokens.iter().map(GlobToken::from).collect()
+        }
+
+        match self.token {
+            Token::Alternative(ref alternative) => alternative
+                .branches()
+                .iter()
+                .map(|tokens| view(tokens))
+                .collect(),
+            Token::Repetition(ref repetition) => vec![view(repetition.tokens())],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Determines if the token only matches invariant text, such as literals
+    /// and separators.
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self.token,
+            Token::Literal(_) | Token::Prefix(_) | Token::Separator
+        )
+    }
+}
+
+impl<'g> From<&'g Token<'g>> for GlobToken<'g> {
+    fn from(token: &'g Token<'g>) -> Self {
+        GlobToken { token }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Component<'t>(SmallVec<[&'t Token<'t>; 4]>);
 
@@ -194,9 +670,20 @@ where
 {
     let mut tokens = tokens.into_iter().peekable();
     let mut prefix = String::new();
-    if let Some(Token::Separator) = tokens.peek() {
-        // Include any rooting separator at the beginning of the token sequence.
-        prefix.push(MAIN_SEPARATOR);
+    match tokens.peek() {
+        Some(Token::Prefix(root)) => {
+            // Include any Windows prefix and its root. Prefixes are always
+            // rooted, but the rooting separator may be parsed as a part of a
+            // tree token.
+            prefix.push_str(&root.to_native_root());
+            tokens.next();
+        }
+        Some(Token::Separator) => {
+            // Include any rooting separator at the beginning of the token
+            // sequence.
+            prefix.push(MAIN_SEPARATOR);
+        }
+        _ => {}
     }
     // TODO: Replace `map`, `take_while`, and `flatten` with `map_while`
     //       when it stabilizes.
@@ -205,7 +692,7 @@ where
             .map(|component| component.literal())
             .take_while(|literal| literal.is_some())
             .flatten()
-            .join(&MAIN_SEPARATOR.to_string()),
+            .join(MAIN_SEPARATOR_STR),
     );
     if prefix.is_empty() {
         None
@@ -215,6 +702,21 @@ where
     }
 }
 
+/// Determines if a token sequence ends with a tree wildcard, such that it
+/// matches every path beneath any directory that it matches.
+pub fn is_exhaustive<'t, I>(tokens: I) -> bool
+where
+    I: IntoIterator<Item = &'t Token<'t>>,
+{
+    match tokens.into_iter().last() {
+        Some(Token::Wildcard(Wildcard::Tree)) => true,
+        Some(Token::Alternative(ref alternative)) => {
+            alternative.branches().iter().all(is_exhaustive)
+        }
+        _ => false,
+    }
+}
+
 // TODO: Patterns like `/**` do not parse correctly. The initial separator is
 //       considered a part of a tree token. This means that the root is lost,
 //       such that `/**` and `**` are equivalent.
@@ -240,7 +742,18 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
         )
     }
 
-    fn literal<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn literal<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
+    where
+        E: ParseError<&'i str>,
+    {
+        literal_excluding("/?*$[]{},<>\\")(input)
+    }
+
+    // Literals in repetitions also exclude colons `:`, which delimit the
+    // bounds of the repetition.
+    fn literal_excluding<'i, E>(
+        excluded: &'static str,
+    ) -> impl FnMut(&'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
@@ -251,7 +764,7 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
                 //       etc. For example, to escape `*`, either `\*` or `[*]`
                 //       can be used.
                 bytes::escaped_transform(
-                    no_adjacent_tree(bytes::is_not("/?*$[]{},\\")),
+                    no_adjacent_tree(bytes::is_not(excluded)),
                     '\\',
                     branch::alt((
                         combinator::value("?", bytes::tag("?")),
@@ -262,22 +775,25 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
                         combinator::value("{", bytes::tag("{")),
                         combinator::value("}", bytes::tag("}")),
                         combinator::value(",", bytes::tag(",")),
+                        combinator::value("<", bytes::tag("<")),
+                        combinator::value(">", bytes::tag(">")),
+                        combinator::value(":", bytes::tag(":")),
                     )),
                 ),
                 |text: &str| !text.is_empty(),
             ),
-            Token::from,
-        )(input)
+            |text| -> Token<'i> { Token::from(text) },
+        )
     }
 
-    fn separator<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn separator<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
         combinator::value(Token::Separator, bytes::tag("/"))(input)
     }
 
-    fn wildcard<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn wildcard<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
@@ -318,7 +834,7 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
         ))(input)
     }
 
-    fn class<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn class<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
@@ -338,6 +854,15 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
             };
 
             multi::many1(branch::alt((
+                // POSIX classes like `[:alpha:]` are delimited by brackets and
+                // colons. Unrecognized names are rejected.
+                combinator::map(
+                    combinator::map_opt(
+                        sequence::delimited(bytes::tag("[:"), character::alpha1, bytes::tag(":]")),
+                        PosixClass::from_name,
+                    ),
+                    Archetype::from,
+                ),
                 combinator::map(
                     sequence::separated_pair(escaped_character, bytes::tag("-"), escaped_character),
                     Archetype::from,
@@ -359,36 +884,200 @@ pub fn parse(text: &str) -> Result<Vec<Token<'_>>, GlobError> {
         )(input)
     }
 
-    fn alternative<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn alternative<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
+        // Names begin with a letter or underscore `_` followed by any number of
+        // letters, digits, and underscores, as in regular expressions. Names
+        // are delimited by angle brackets, which are never literals, so that
+        // names cannot be confused with literal text like `a:b` or `C:/`.
+        fn name<'i, E>(input: &'i str) -> IResult<&'i str, &'i str, E>
+        where
+            E: ParseError<&'i str>,
+        {
+            sequence::delimited(
+                bytes::tag("<"),
+                combinator::recognize(sequence::pair(
+                    branch::alt((character::alpha1, bytes::tag("_"))),
+                    bytes::take_while(|x: char| x.is_ascii_alphanumeric() || x == '_'),
+                )),
+                bytes::tag(">"),
+            )(input)
+        }
+
+        // Ranges like `{01..20}` are alternatives with a single branch that
+        // consists of a numeric range token.
+        fn range<'i, E>(input: &'i str) -> IResult<&'i str, Vec<Vec<Token<'i>>>, E>
+        where
+            E: ParseError<&'i str>,
+        {
+            fn bound<'i, E>(input: &'i str) -> IResult<&'i str, (u64, usize), E>
+            where
+                E: ParseError<&'i str>,
+            {
+                combinator::map_opt(character::digit1, |digits: &str| {
+                    digits.parse::<u64>().ok().map(|n| {
+                        let is_padded = digits.len() > 1 && digits.starts_with('0');
+                        (n, if is_padded { digits.len() } else { 0 })
+                    })
+                })(input)
+            }
+
+            combinator::map(
+                sequence::terminated(
+                    sequence::separated_pair(bound, bytes::tag(".."), bound),
+                    combinator::peek(bytes::tag("}")),
+                ),
+                |((start, start_width), (end, end_width))| {
+                    // Both bounds are padded to the width of the widest bound
+                    // if either bound is padded.
+                    let width = if start_width > 0 || end_width > 0 {
+                        digits(start)
+                            .max(digits(end))
+                            .max(start_width)
+                            .max(end_width)
+                    }
+                    else {
+                        0
+                    };
+                    vec![vec![Token::NumericRange(NumericRange::new(
+                        start, end, width,
+                    ))]]
+                },
+            )(input)
+        }
+
+        fn digits(n: u64) -> usize {
+            n.to_string().len()
+        }
+
         sequence::delimited(
             bytes::tag("{"),
             combinator::map(
-                multi::separated_list1(bytes::tag(","), glob),
-                |alternatives| Alternative::from(alternatives).into(),
+                sequence::pair(
+                    combinator::opt(name),
+                    branch::alt((range, multi::separated_list1(bytes::tag(","), glob))),
+                ),
+                |(name, alternatives)| match name {
+                    Some(name) => Alternative::named(name, alternatives).into(),
+                    None => Alternative::from(alternatives).into(),
+                },
             ),
             bytes::tag("}"),
         )(input)
     }
 
-    fn glob<'i, E>(input: &'i str) -> IResult<&'i str, Vec<Token>, E>
+    fn repetition<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
+    where
+        E: ParseError<&'i str>,
+    {
+        fn bound<'i, E>(input: &'i str) -> IResult<&'i str, usize, E>
+        where
+            E: ParseError<&'i str>,
+        {
+            combinator::map_opt(character::digit1, |digits: &str| {
+                digits.parse::<usize>().ok()
+            })(input)
+        }
+
+        // Bounds are either exact, as in `<?:3>`, or a range with an optional
+        // upper bound, as in `<?:1,3>` or `<?:1,>`.
+        fn bounds<'i, E>(input: &'i str) -> IResult<&'i str, (usize, Option<usize>), E>
+        where
+            E: ParseError<&'i str>,
+        {
+            combinator::verify(
+                branch::alt((
+                    sequence::separated_pair(bound, bytes::tag(","), combinator::opt(bound)),
+                    combinator::map(bound, |n| (n, Some(n))),
+                )),
+                |(lower, upper)| upper.is_none_or(|upper| *lower <= upper),
+            )(input)
+        }
+
+        combinator::map(
+            sequence::delimited(
+                bytes::tag("<"),
+                sequence::separated_pair(
+                    multi::many1(branch::alt((
+                        literal_excluding("/?*$[]{},<>:\\"),
+                        alternative,
+                        repetition,
+                        wildcard,
+                        class,
+                        separator,
+                    ))),
+                    bytes::tag(":"),
+                    bounds,
+                ),
+                bytes::tag(">"),
+            ),
+            |(tokens, (lower, upper))| Token::Repetition(Repetition::new(tokens, lower, upper)),
+        )(input)
+    }
+
+    fn glob<'i, E>(input: &'i str) -> IResult<&'i str, Vec<Token<'i>>, E>
     where
         E: ParseError<&'i str>,
     {
         multi::many1(branch::alt((
             literal,
             alternative,
+            repetition,
             wildcard,
             class,
             separator,
         )))(input)
     }
 
-    let tokens = combinator::all_consuming(glob)(text)
-        .map(|(_, tokens)| tokens)
-        .map_err(GlobError::from)?;
+    // Windows prefixes are only recognized on Windows at the beginning of a
+    // glob and must be followed by a separator, as in `C:/` or
+    // `//server/share/`. Elsewhere, text like `x:/` is a literal.
+    fn prefix<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
+    where
+        E: ParseError<&'i str>,
+    {
+        let name = || bytes::is_not("/?*$[]{},<>:\\");
+        combinator::map(
+            sequence::terminated(
+                branch::alt((
+                    combinator::map(
+                        sequence::terminated(
+                            character::satisfy(|x| x.is_ascii_alphabetic()),
+                            bytes::tag(":"),
+                        ),
+                        Prefix::Disk,
+                    ),
+                    combinator::map(
+                        sequence::preceded(
+                            bytes::tag("//"),
+                            sequence::separated_pair(name(), bytes::tag("/"), name()),
+                        ),
+                        |(server, share): (&str, &str)| Prefix::Unc {
+                            server: server.into(),
+                            share: share.into(),
+                        },
+                    ),
+                )),
+                combinator::peek(bytes::tag("/")),
+            ),
+            Token::Prefix,
+        )(input)
+    }
+
+    let tokens = combinator::all_consuming(combinator::map(
+        sequence::pair(
+            combinator::map(
+                combinator::cond(cfg!(windows), combinator::opt(prefix)),
+                Option::flatten,
+            ),
+            glob,
+        ),
+        |(prefix, tokens)| prefix.into_iter().chain(tokens).collect::<Vec<_>>(),
+    ))(text)
+    .map(|(_, tokens)| tokens)
+    .map_err(GlobError::from)?;
     rule::check(tokens.iter())?;
     Ok(tokens)
 }
@@ -449,6 +1138,19 @@ mod tests {
             token::literal_path_prefix(token::parse("a/b/*/c").unwrap().iter()),
             Some(Path::new("a/b/").to_path_buf()),
         );
+        assert_eq!(
+            token::literal_path_prefix(token::parse("C:/a/*").unwrap().iter()),
+            Some(Path::new("C:/a").to_path_buf()),
+        );
+        assert_eq!(
+            token::literal_path_prefix(token::parse("C:/**").unwrap().iter()),
+            Some(Path::new("C:/").to_path_buf()),
+        );
+        #[cfg(windows)]
+        assert_eq!(
+            token::literal_path_prefix(token::parse("//server/share/a/*").unwrap().iter()),
+            Some(Path::new("//server/share/a").to_path_buf()),
+        );
 
         assert!(token::literal_path_prefix(token::parse("**").unwrap().iter()).is_none());
         assert!(token::literal_path_prefix(token::parse("a*").unwrap().iter()).is_none());
@@ -0,0 +1,1826 @@
+//! Traversal of directory trees.
+//!
+//! This module matches globs against the paths of files in a directory tree
+//! and is only available with the `fs` feature. Globs can be parsed and matched
+//! against paths without it.
+
+#[cfg(feature = "async")]
+use futures_core::Stream;
+use itertools::{EitherOrBoth, Itertools as _};
+use regex::bytes::Regex;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt::{self, Debug};
+use std::fs::{self, FileType, Metadata};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{self, Arc};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::SystemTime;
+#[cfg(feature = "async")]
+use tokio::sync::mpsc;
+use walkdir::{self, DirEntry, WalkDir};
+
+use crate::cancel::Cancellation;
+use crate::glob::capture;
+use crate::glob::gitignore::Ignores;
+use crate::glob::token::{self, Token};
+use crate::glob::{BytePath, Captures, Glob, GlobError};
+use crate::text;
+
+/// Number of entries buffered by a `WalkStream` before its traversal waits.
+#[cfg(feature = "async")]
+const STREAM_CAPACITY: usize = 64;
+
+/// Number of entries buffered by a `ParallelWalk` before its readers wait.
+const PARALLEL_CAPACITY: usize = 1024;
+
+impl From<walkdir::Error> for GlobError {
+    fn from(error: walkdir::Error) -> Self {
+        match (error.loop_ancestor(), error.path()) {
+            (Some(_), Some(path)) => GlobError::LinkLoop(path.into()),
+            _ => GlobError::Walk(error),
+        }
+    }
+}
+
+/// Entry read by a `ParallelWalk`.
+///
+/// Unlike `walkdir::DirEntry`, this is constructed from entries read directly
+/// from the file system.
+#[derive(Clone, Debug)]
+struct ReadEntry {
+    path: PathBuf,
+    file_type: FileType,
+    depth: usize,
+    is_symlink: bool,
+    follow: bool,
+}
+
+#[derive(Debug)]
+enum Entry<'e> {
+    Walk(Cow<'e, DirEntry>),
+    Read(ReadEntry),
+}
+
+/// Describes a file matching a `Glob` in a directory tree.
+#[derive(Debug)]
+pub struct WalkEntry<'e> {
+    entry: Entry<'e>,
+    captures: Captures<'e>,
+}
+
+impl<'e> WalkEntry<'e> {
+    pub fn into_owned(self) -> WalkEntry<'static> {
+        let WalkEntry { entry, captures } = self;
+        WalkEntry {
+            entry: match entry {
+                Entry::Walk(entry) => Entry::Walk(Cow::Owned(entry.into_owned())),
+                Entry::Read(entry) => Entry::Read(entry),
+            },
+            captures: captures.into_owned(),
+        }
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        match self.entry {
+            Entry::Walk(Cow::Borrowed(entry)) => entry.path().to_path_buf(),
+            Entry::Walk(Cow::Owned(entry)) => entry.into_path(),
+            Entry::Read(entry) => entry.path,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self.entry {
+            Entry::Walk(ref entry) => entry.path(),
+            Entry::Read(ref entry) => &entry.path,
+        }
+    }
+
+    pub fn file_type(&self) -> FileType {
+        match self.entry {
+            Entry::Walk(ref entry) => entry.file_type(),
+            Entry::Read(ref entry) => entry.file_type,
+        }
+    }
+
+    /// Determines if the entry is a symbolic link.
+    ///
+    /// Unlike `file_type`, this is `true` for links even when links are
+    /// followed.
+    pub fn path_is_symlink(&self) -> bool {
+        match self.entry {
+            Entry::Walk(ref entry) => entry.path_is_symlink(),
+            Entry::Read(ref entry) => entry.is_symlink,
+        }
+    }
+
+    // TODO: On some platforms, traversing a directory tree also yields file
+    //       metadata (e.g., Windows). Forward this metadata to path printing
+    //       using `lscolors` in `nym-cli` to avoid unnecessary reads.
+    pub fn metadata(&self) -> Result<Metadata, GlobError> {
+        match self.entry {
+            Entry::Walk(ref entry) => entry.metadata().map_err(From::from),
+            Entry::Read(ref entry) => if entry.follow {
+                fs::metadata(&entry.path)
+            }
+            else {
+                fs::symlink_metadata(&entry.path)
+            }
+            .map_err(|error| GlobError::Read(entry.path.clone(), error)),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self.entry {
+            Entry::Walk(ref entry) => entry.depth(),
+            Entry::Read(ref entry) => entry.depth,
+        }
+    }
+
+    pub fn captures(&self) -> &Captures<'e> {
+        &self.captures
+    }
+
+    /// Gets the bytes of the path as it was matched.
+    ///
+    /// This is the path relative to the root of the walk with normalized
+    /// separators and is the same as the full text of the captures. Prefer
+    /// this to converting `path` into bytes again.
+    pub fn matched(&self) -> &[u8] {
+        self.captures.matched()
+    }
+}
+
+impl<'t> Glob<'t> {
+    pub fn walk(&self, directory: impl AsRef<Path>, behavior: impl Into<WalkBehavior>) -> Walk<'_> {
+        let behavior = behavior.into();
+        // The directory tree is traversed from `root`, which may include a path
+        // prefix from the glob pattern. `Walk` patterns are only applied to
+        // path components following the `prefix` in `root`. Prefixes are used
+        // as paths verbatim, so relative prefixes are instead matched by
+        // component regexes when matching without regard to case.
+        let is_prefixed = !self.is_case_insensitive || self.has_root();
+        let (prefix, root) = if self.tokens.is_empty() {
+            // An invariant glob, such as the remainder of a partitioned
+            // literal glob, matches only `directory` itself, which is matched
+            // relative to its parent.
+            let root: Cow<'_, Path> = directory.as_ref().into();
+            (
+                root.parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .to_path_buf()
+                    .into(),
+                root,
+            )
+        }
+        else if let Some(prefix) =
+            token::literal_path_prefix(self.tokens.iter()).filter(|_| is_prefixed)
+        {
+            let root: Cow<'_, Path> = directory.as_ref().join(&prefix).into();
+            if prefix.is_absolute() {
+                // Note that absolute paths replace paths with which they are
+                // joined, so there is no prefix.
+                (PathBuf::new().into(), root)
+            }
+            else {
+                (directory.as_ref().into(), root)
+            }
+        }
+        else {
+            let root: Cow<'_, Path> = directory.as_ref().into();
+            (root.clone(), root)
+        };
+        if self.tokens.is_empty() || (self.is_literal() && is_prefixed) {
+            // The root of the walk is the only path that a literal glob can
+            // match, so no regular expressions are needed.
+            let depth = root
+                .strip_prefix(&prefix)
+                .unwrap_or(&root)
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .count();
+            // A missing file is not an error, because it is not matched.
+            let is_missing = matches!(
+                fs::symlink_metadata(&root),
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound
+            );
+            let strategy =
+                if !is_missing && (behavior.min_depth.max(1)..=behavior.depth).contains(&depth) {
+                    Strategy::Literal
+                }
+                else {
+                    Strategy::Empty
+                };
+            return Walk::new(
+                Ok(strategy),
+                Vec::new(),
+                prefix.into_owned(),
+                &root,
+                behavior,
+            )
+            .beneath(directory.as_ref());
+        }
+        let regexes = Walk::compile(self.tokens.iter(), self.is_case_insensitive);
+        Walk::new(
+            self.compiled()
+                .map(|compiled| Strategy::Regex(&compiled.regex)),
+            regexes,
+            prefix.into_owned(),
+            &root,
+            behavior,
+        )
+    }
+
+    /// Walks a directory tree on a blocking thread and streams matched files.
+    ///
+    /// The traversal is the same as `walk`, but it is performed by the blocking
+    /// thread pool of the current Tokio runtime, so this function panics if it
+    /// is called outside of a runtime. The traversal waits while the buffer of
+    /// the stream is full and stops when the stream is dropped.
+    #[cfg(feature = "async")]
+    pub fn read_stream(
+        &self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+    ) -> WalkStream {
+        let glob = self.clone().into_owned();
+        let directory = directory.as_ref().to_path_buf();
+        let behavior = behavior.into();
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+        tokio::task::spawn_blocking(move || {
+            for entry in glob.walk(directory, behavior) {
+                if sender.blocking_send(entry).is_err() {
+                    // The stream has been dropped.
+                    break;
+                }
+            }
+        });
+        WalkStream { receiver }
+    }
+}
+
+/// Traverses a directory tree via a `Walk` instance.
+///
+/// This macro emits an interruptable loop that executes a block of code
+/// whenever a `WalkEntry` or error is encountered while traversing a directory
+/// tree. The block may return from its function or otherwise interrupt and
+/// subsequently resume the loop.
+///
+/// Note that if the block attempts to emit a `WalkEntry` across a function
+/// boundary that the entry must copy its contents via `into_owned`.
+macro_rules! walk {
+    ($walk:expr => |$entry:ident| $f:block) => {
+        // Errors that occur before the traversal, such as failure to compile
+        // a glob, are emitted first.
+        if let Some(error) = $walk.error.take() {
+            let $entry = Err(error);
+            $f
+        }
+        // `while-let` avoids a mutable borrow of `walk`, which would prevent a
+        // subsequent call to `skip_current_dir` within the loop body.
+        #[allow(clippy::while_let_on_iterator)]
+        #[allow(unreachable_code)]
+        'walk: while let Some(entry) = $walk.walk.next() {
+            if matches!($walk.strategy, Strategy::Empty)
+                || $walk.matched >= $walk.behavior.limit
+                || $walk.is_cancelled()
+            {
+                break 'walk;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    let $entry = Err(error.into());
+                    $f
+                    continue 'walk; // May be unreachable.
+                }
+            };
+            if (!$walk.behavior.hidden && is_hidden(&entry))
+                || $walk.is_excluded(entry.path())
+                || $walk.is_ignored(&entry)
+            {
+                // Do not descend into hidden, excluded, nor ignored
+                // directories.
+                if entry.file_type().is_dir() {
+                    $walk.walk.skip_current_dir();
+                }
+                continue 'walk;
+            }
+            if let Strategy::Literal = $walk.strategy {
+                // The root of a literal walk is its only entry and it matches
+                // without regard to depth, which has already been checked.
+                // Its ancestors are never traversed, so they are checked
+                // here instead.
+                if $walk.has_rejected_ancestor(entry.path()) {
+                    continue 'walk;
+                }
+                let path = entry
+                    .path()
+                    .strip_prefix(&$walk.prefix)
+                    .expect("path is not in tree");
+                let entry = WalkEntry {
+                    entry: Entry::Walk(Cow::Borrowed(&entry)),
+                    captures: Captures::from_matched(BytePath::from_path(path)),
+                };
+                if $walk.behavior.is_filtered(&entry) {
+                    continue 'walk;
+                }
+                $walk.matched += 1;
+                let $entry = Ok(entry);
+                $f
+                continue 'walk; // May be unreachable.
+            }
+            // Shallow entries are not matched, but hidden, excluded, and
+            // ignored directories must still be detected above, so this does
+            // not use the minimum depth of `WalkDir`.
+            if entry.depth() < $walk.behavior.min_depth {
+                continue 'walk;
+            }
+            let path = entry
+                .path()
+                .strip_prefix(&$walk.prefix)
+                .expect("path is not in tree");
+            if path.to_str().is_none() {
+                // Do not descend into directories with names that cannot be
+                // matched against component regexes.
+                if entry.file_type().is_dir() {
+                    $walk.walk.skip_current_dir();
+                }
+                let $entry = Err(GlobError::Encoding(entry.path().to_path_buf()));
+                $f
+                continue 'walk; // May be unreachable.
+            }
+            // The byte form of the path is used both to match components and
+            // to extract captures, so it is converted only once.
+            let bytes = BytePath::from_path(path);
+            for candidate in bytes.components().zip_longest($walk.regexes.iter()) {
+                match candidate {
+                    EitherOrBoth::Both(component, regex) => {
+                        if regex.is_match(component) {
+                            if let Some(captures) = $walk.captures(&bytes) {
+                                let entry = WalkEntry {
+                                    entry: Entry::Walk(Cow::Borrowed(&entry)),
+                                    captures,
+                                };
+                                if $walk.behavior.is_filtered(&entry) {
+                                    continue 'walk;
+                                }
+                                $walk.matched += 1;
+                                let $entry = Ok(entry);
+                                $f
+                                continue 'walk; // May be unreachable.
+                            }
+                        }
+                        else {
+                            // Do not descend into directories that do not
+                            // match the corresponding component regex.
+                            if entry.file_type().is_dir() {
+                                $walk.walk.skip_current_dir();
+                            }
+                            continue 'walk;
+                        }
+                    }
+                    EitherOrBoth::Left(_) => {
+                        if let Some(captures) = $walk.captures(&bytes) {
+                            let entry = WalkEntry {
+                                entry: Entry::Walk(Cow::Borrowed(&entry)),
+                                captures,
+                            };
+                            if $walk.behavior.is_filtered(&entry) {
+                                continue 'walk;
+                            }
+                            $walk.matched += 1;
+                            let $entry = Ok(entry);
+                            $f
+                        }
+                        // The entire path has been matched against the regex,
+                        // so the remaining components need not be examined.
+                        continue 'walk;
+                    }
+                    EitherOrBoth::Right(_) => {
+                        continue 'walk;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Configures the traversal of a directory tree by a `Walk`.
+///
+/// `WalkBehavior` can be constructed from a `usize`, which is interpreted as
+/// the maximum depth of the traversal. All other behaviors use their defaults.
+#[derive(Clone, Debug)]
+pub struct WalkBehavior {
+    /// Maximum depth of the traversal.
+    ///
+    /// A depth of one only includes entries within the root directory.
+    pub depth: usize,
+    /// Minimum depth of yielded entries.
+    ///
+    /// Entries that are shallower than this depth are not yielded, but their
+    /// directories are still traversed. A minimum depth of one includes
+    /// entries within the root directory. The root directory itself is never
+    /// yielded, so a minimum depth of zero is the same as one.
+    pub min_depth: usize,
+    /// Determines if hidden files and directories are traversed and yielded.
+    ///
+    /// Files and directories are hidden if their names begin with a dot `.`.
+    /// Hidden directories are never descended into when this is `false`. The
+    /// root directory of the traversal is never considered hidden.
+    pub hidden: bool,
+    /// Determines if ignore files like `.gitignore` are respected.
+    ///
+    /// When `true`, paths that are ignored by `.gitignore` and `.ignore` files
+    /// are not yielded and ignored directories are never descended into. Ignore
+    /// files in the root directory and its ancestors (up to the root of any
+    /// enclosing Git repository) also apply.
+    pub ignore: bool,
+    /// Determines if symbolic links are followed.
+    ///
+    /// When `true`, symbolic links are read as their targets and linked
+    /// directories are traversed. Links that form a loop are reported as
+    /// `GlobError::LinkLoop` errors and are not traversed, but the walk
+    /// continues.
+    pub follow: bool,
+    /// Maximum number of matched entries yielded.
+    ///
+    /// The traversal terminates once this many entries have matched, so the
+    /// remainder of the directory tree is never read. Errors do not count
+    /// toward this limit.
+    pub limit: usize,
+    /// Order in which the entries of each directory are traversed.
+    ///
+    /// Directories are traversed depth-first, so the entries of a directory
+    /// are always yielded before the entries of its next sibling. See
+    /// `WalkOrder`.
+    pub order: WalkOrder,
+    /// Determines if directories are read in parallel.
+    ///
+    /// This applies to the walks of from-patterns and transforms. Walks of
+    /// globs are always sequential, but can be read in parallel via
+    /// `Walk::parallel`, which describes the order of entries and limits.
+    pub parallel: bool,
+    /// Predicate on the metadata of matched entries.
+    ///
+    /// Metadata is read once for each matched entry and symbolic links are
+    /// only read as their targets when `follow` is `true`. Entries that are
+    /// rejected by the predicate or for which metadata cannot be read are not
+    /// yielded and do not count toward `limit`.
+    pub filter: Option<MetadataFilter>,
+}
+
+impl WalkBehavior {
+    fn is_filtered(&self, entry: &WalkEntry<'_>) -> bool {
+        self.filter.as_ref().is_some_and(|filter| {
+            !entry
+                .metadata()
+                .is_ok_and(|metadata| filter.is_match(&metadata))
+        })
+    }
+}
+
+impl Default for WalkBehavior {
+    fn default() -> Self {
+        WalkBehavior {
+            depth: usize::MAX,
+            min_depth: 1,
+            hidden: true,
+            ignore: false,
+            follow: false,
+            limit: usize::MAX,
+            order: WalkOrder::Unsorted,
+            parallel: false,
+            filter: None,
+        }
+    }
+}
+
+/// Predicate on the metadata of the entries of a `Walk`.
+///
+/// See `WalkBehavior::filter`.
+#[derive(Clone)]
+pub struct MetadataFilter(Arc<dyn Fn(&Metadata) -> bool + Send + Sync>);
+
+impl MetadataFilter {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: 'static + Fn(&Metadata) -> bool + Send + Sync,
+    {
+        MetadataFilter(Arc::new(f))
+    }
+
+    pub fn is_match(&self, metadata: &Metadata) -> bool {
+        (self.0)(metadata)
+    }
+}
+
+impl Debug for MetadataFilter {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_tuple("MetadataFilter")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Order of the entries of each directory in a `Walk`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WalkOrder {
+    /// Entries are traversed in an unspecified order that depends on the
+    /// platform.
+    #[default]
+    Unsorted,
+    /// Entries are traversed in the lexicographic order of their names.
+    Name,
+    /// Entries are traversed in the natural order of their names (see
+    /// `text::natural_cmp`), such that `file2` is yielded before `file10`.
+    Natural,
+    /// Entries are traversed from least to most recently modified.
+    ///
+    /// Entries with the same modification time (or for which the time cannot
+    /// be read) are traversed in the lexicographic order of their names.
+    Modified,
+}
+
+impl WalkOrder {
+    pub fn is_sorted(&self) -> bool {
+        !matches!(self, WalkOrder::Unsorted)
+    }
+
+    /// Compares entries with the given paths in the same directory.
+    fn cmp_siblings(&self, left: &Path, right: &Path, times: &mut ModifiedTimes) -> cmp::Ordering {
+        match self {
+            WalkOrder::Unsorted => cmp::Ordering::Equal,
+            WalkOrder::Name => left.file_name().cmp(&right.file_name()),
+            WalkOrder::Natural => text::natural_cmp(
+                &left.file_name().unwrap_or_default().to_string_lossy(),
+                &right.file_name().unwrap_or_default().to_string_lossy(),
+            ),
+            WalkOrder::Modified => times
+                .get(left)
+                .cmp(&times.get(right))
+                .then_with(|| left.file_name().cmp(&right.file_name())),
+        }
+    }
+
+    /// Compares paths in the order of a sorted `Walk`.
+    ///
+    /// Paths are compared by their first differing components, which are
+    /// siblings, and directories are ordered before their contents.
+    fn cmp_paths(&self, left: &Path, right: &Path, times: &mut ModifiedTimes) -> cmp::Ordering {
+        if let WalkOrder::Natural = self {
+            return natural_path_cmp(left, right);
+        }
+        let mut parent = PathBuf::new();
+        for components in left.components().zip_longest(right.components()) {
+            match components {
+                EitherOrBoth::Both(left, right) if left == right => {
+                    parent.push(left);
+                }
+                EitherOrBoth::Both(left, right) => {
+                    return self.cmp_siblings(&parent.join(left), &parent.join(right), times);
+                }
+                EitherOrBoth::Left(_) => {
+                    return cmp::Ordering::Greater;
+                }
+                EitherOrBoth::Right(_) => {
+                    return cmp::Ordering::Less;
+                }
+            }
+        }
+        cmp::Ordering::Equal
+    }
+}
+
+/// Modification times of entries in a directory tree.
+///
+/// Times are read at most once for each entry, because sorting compares each
+/// entry many times.
+#[derive(Debug)]
+struct ModifiedTimes {
+    follow: bool,
+    times: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl ModifiedTimes {
+    fn new(follow: bool) -> Self {
+        ModifiedTimes {
+            follow,
+            times: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &Path) -> Option<SystemTime> {
+        let follow = self.follow;
+        *self.times.entry(path.to_path_buf()).or_insert_with(|| {
+            if follow {
+                fs::metadata(path)
+            }
+            else {
+                fs::symlink_metadata(path)
+            }
+            .and_then(|metadata| metadata.modified())
+            .ok()
+        })
+    }
+
+    fn clear(&mut self) {
+        self.times.clear();
+    }
+}
+
+impl From<usize> for WalkBehavior {
+    fn from(depth: usize) -> Self {
+        WalkBehavior {
+            depth,
+            ..Default::default()
+        }
+    }
+}
+
+/// Excludes paths in a directory tree from a `Walk`.
+///
+/// Paths are matched against the regex relative to the root of the exclusion.
+#[derive(Clone, Debug)]
+struct Exclusion {
+    root: PathBuf,
+    regex: Regex,
+}
+
+impl Exclusion {
+    fn is_match(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root)
+            .map(|path| self.regex.is_match(BytePath::from_path(path).as_ref()))
+            .unwrap_or(false)
+    }
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    is_hidden_name(entry.file_name())
+}
+
+fn is_hidden_name(name: &OsStr) -> bool {
+    name.to_str()
+        .map(|name| name.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// Walks a directory tree and matches paths against a regular expression.
+///
+/// Paths are matched relative to `directory` and the regular expression must
+/// match the entire path. Unlike globs, regular expressions cannot be split
+/// into components, so directories are never skipped because they cannot
+/// match.
+pub(in crate) fn walk_regex(
+    regex: &Regex,
+    directory: impl AsRef<Path>,
+    behavior: impl Into<WalkBehavior>,
+) -> Walk<'_> {
+    let directory = directory.as_ref();
+    Walk::new(
+        Ok(Strategy::Regex(regex)),
+        Vec::new(),
+        directory.to_path_buf(),
+        directory,
+        behavior.into(),
+    )
+}
+
+/// Determines how a `Walk` matches entries.
+#[derive(Clone, Copy, Debug)]
+enum Strategy<'g> {
+    /// Entries are matched against a regular expression.
+    Regex(&'g Regex),
+    /// The root is the only entry and is matched without a regular expression.
+    Literal,
+    /// No entries are matched.
+    Empty,
+}
+
+/// Iterator over files matching a `Glob` in a directory tree.
+pub struct Walk<'g> {
+    strategy: Strategy<'g>,
+    error: Option<GlobError>,
+    regexes: Vec<Regex>,
+    exclusions: Vec<Exclusion>,
+    ignores: Option<Ignores>,
+    behavior: WalkBehavior,
+    matched: usize,
+    prefix: PathBuf,
+    root: PathBuf,
+    base: PathBuf,
+    names: Option<capture::Names>,
+    walk: walkdir::IntoIter,
+    cancellation: Option<Cancellation>,
+}
+
+impl<'g> Walk<'g> {
+    /// Constructs a walk.
+    ///
+    /// If `strategy` is an error, then the walk yields only that error.
+    fn new(
+        strategy: Result<Strategy<'g>, GlobError>,
+        regexes: Vec<Regex>,
+        prefix: PathBuf,
+        root: &Path,
+        behavior: WalkBehavior,
+    ) -> Self {
+        let (strategy, error) = match strategy {
+            Ok(strategy) => (strategy, None),
+            Err(error) => (Strategy::Empty, Some(error)),
+        };
+        Walk {
+            strategy,
+            error,
+            regexes,
+            exclusions: Vec::new(),
+            ignores: behavior.ignore.then(|| Ignores::new(root)),
+            matched: 0,
+            names: match strategy {
+                Strategy::Regex(regex) => capture::names(regex),
+                Strategy::Literal | Strategy::Empty => None,
+            },
+            prefix,
+            root: root.to_path_buf(),
+            base: root.to_path_buf(),
+            cancellation: None,
+            walk: {
+                let walk = WalkDir::new(root).follow_links(behavior.follow);
+                let walk = if let Strategy::Literal = strategy {
+                    walk.min_depth(0).max_depth(0)
+                }
+                else {
+                    walk.min_depth(1).max_depth(behavior.depth)
+                };
+                if behavior.order.is_sorted() {
+                    let order = behavior.order;
+                    let mut times = ModifiedTimes::new(behavior.follow);
+                    let mut directory = PathBuf::new();
+                    walk.sort_by(move |left, right| {
+                        // Each directory is sorted in turn, so the times of
+                        // any previous directory are no longer needed.
+                        if let Some(parent) = left.path().parent() {
+                            if parent != directory {
+                                directory = parent.to_path_buf();
+                                times.clear();
+                            }
+                        }
+                        order.cmp_siblings(left.path(), right.path(), &mut times)
+                    })
+                }
+                else {
+                    walk
+                }
+                .into_iter()
+            },
+            behavior,
+        }
+    }
+
+    fn compile<'t, I>(tokens: I, is_case_insensitive: bool) -> Vec<Regex>
+    where
+        I: IntoIterator<Item = &'t Token<'t>>,
+        I::IntoIter: Clone,
+    {
+        let mut regexes = Vec::new();
+        for component in token::components(tokens) {
+            if let [Token::Prefix(ref prefix)] = component.tokens() {
+                // Prefixes may span more than one component of a normalized
+                // path, such as `server` and `share` in `//server/share`.
+                for literal in prefix.components() {
+                    match Glob::compile_tokens([Token::Literal(literal)], is_case_insensitive) {
+                        Ok(regex) => regexes.push(regex),
+                        Err(_) => return regexes,
+                    }
+                }
+                continue;
+            }
+            if component.tokens().iter().any(|token| match token {
+                Token::Alternative(ref alternative) => alternative.has_component_boundary(),
+                Token::Repetition(ref repetition) => repetition.has_component_boundary(),
+                token => token.is_component_boundary(),
+            }) {
+                // NOTE: `token::components` omits any separators outside of
+                //       alternatives, so this will not stop at top-level
+                //       separators.
+                // Stop at component boundaries, such as tree wildcards or any
+                // boundary within an alternative or repetition token.
+                break;
+            }
+            else {
+                // Component regexes only prune the traversal and the glob
+                // regex is compiled separately, so stop at any component that
+                // fails to compile.
+                match Glob::compile_tokens(component.tokens().iter().cloned(), is_case_insensitive)
+                {
+                    Ok(regex) => regexes.push(regex),
+                    Err(_) => break,
+                }
+            }
+        }
+        regexes
+    }
+
+    /// Excludes paths matching globs from the walk.
+    ///
+    /// Each glob is paired with the directory against which it is matched,
+    /// such as the directory given to `Glob::walk` and an exclusion like
+    /// `**/tmp/**`. Excluded directories are not traversed, so their contents
+    /// are never yielded nor read. If an exclusion fails to compile, then the
+    /// walk yields only that error.
+    pub fn exclude<'x, I, P>(mut self, exclusions: I) -> Self
+    where
+        I: IntoIterator<Item = (P, &'x Glob<'x>)>,
+        P: Into<PathBuf>,
+    {
+        for (root, glob) in exclusions {
+            match glob.compiled() {
+                Ok(compiled) => self.exclusions.push(Exclusion {
+                    root: root.into(),
+                    regex: compiled.regex.clone(),
+                }),
+                Err(error) => {
+                    // Do not walk without all of the exclusions.
+                    self.strategy = Strategy::Empty;
+                    self.error.get_or_insert(error);
+                }
+            }
+        }
+        self
+    }
+
+    /// Stops the walk when a `Cancellation` is cancelled.
+    ///
+    /// Once cancelled, no further entries nor errors are yielded.
+    pub fn cancel_on(mut self, cancellation: &Cancellation) -> Self {
+        self.cancellation = Some(cancellation.clone());
+        self
+    }
+
+    /// Sets the directory beneath which the ancestors of a literal root are
+    /// checked.
+    ///
+    /// The root of a literal walk is read directly, so hidden and excluded
+    /// directories between `base` and the root are not otherwise detected.
+    pub(in crate) fn beneath(mut self, base: impl Into<PathBuf>) -> Self {
+        self.base = base.into();
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    fn captures<'p>(&self, path: &'p BytePath<'_>) -> Option<Captures<'p>> {
+        match self.strategy {
+            Strategy::Regex(regex) => regex
+                .captures(path.as_ref())
+                .map(|captures| Captures::from(captures).with_names(self.names.clone())),
+            Strategy::Literal | Strategy::Empty => None,
+        }
+    }
+
+    fn is_ignored(&mut self, entry: &DirEntry) -> bool {
+        self.ignores
+            .as_mut()
+            .map(|ignores| ignores.is_ignored(entry))
+            .unwrap_or(false)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclusions
+            .iter()
+            .any(|exclusion| exclusion.is_match(path))
+    }
+
+    fn has_rejected_ancestor(&self, path: &Path) -> bool {
+        path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| *ancestor != self.base && ancestor.starts_with(&self.base))
+            .any(|ancestor| {
+                (!self.behavior.hidden && ancestor.file_name().is_some_and(is_hidden_name))
+                    || self.is_excluded(ancestor)
+            })
+    }
+
+    /// Walks the directory tree in parallel.
+    ///
+    /// Directories are read concurrently by a Rayon thread pool, which can be
+    /// much faster than a sequential walk for large directory trees. The
+    /// traversal begins immediately and entries are yielded in the order in
+    /// which they are read, which varies from walk to walk. If
+    /// `WalkBehavior::order` is sorted, then the entire directory tree is read
+    /// before any entries are yielded and entries are yielded in the same order
+    /// as a sequential walk. Otherwise, the entries yielded by a walk with a
+    /// limit are those that happen to be read first.
+    ///
+    /// Walks of literal globs and walks with errors (such as an exclusion that
+    /// fails to compile) are not parallel.
+    pub fn parallel(self) -> ParallelWalk<'g> {
+        let regex = match self.strategy {
+            Strategy::Regex(regex) if self.error.is_none() => regex.clone(),
+            _ => {
+                return ParallelWalk {
+                    reader: Reader::Sequential(Box::new(self)),
+                };
+            }
+        };
+        let Walk {
+            regexes,
+            exclusions,
+            ignores,
+            behavior,
+            prefix,
+            root,
+            names,
+            cancellation,
+            ..
+        } = self;
+        let (sender, receiver) = sync::mpsc::sync_channel(PARALLEL_CAPACITY);
+        let traversal = Arc::new(Traversal {
+            regex,
+            regexes,
+            exclusions,
+            behavior,
+            prefix,
+            names,
+            cancellation,
+            stop: Cancellation::new(),
+            matched: AtomicUsize::new(0),
+        });
+        {
+            let traversal = traversal.clone();
+            thread::spawn(move || traversal.run(root, ignores, sender));
+        }
+        ParallelWalk {
+            reader: Reader::Parallel {
+                traversal,
+                receiver,
+            },
+        }
+    }
+
+    /// Calls a closure on each matched file or error.
+    ///
+    /// This function does not copy the contents of paths and captures when
+    /// emitting entries and so may be more efficient than external iteration
+    /// via `Iterator` (and `Iterator::for_each`).
+    pub fn for_each(mut self, mut f: impl FnMut(Result<WalkEntry, GlobError>)) {
+        walk!(self => |entry| {
+            f(entry);
+        });
+    }
+}
+
+impl<'g> Iterator for Walk<'g> {
+    type Item = Result<WalkEntry<'static>, GlobError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        walk!(self => |entry| {
+            return Some(entry.map(|entry: WalkEntry| entry.into_owned()));
+        });
+        None
+    }
+}
+
+/// Iterator over files matching a `Glob` in a directory tree that is read in
+/// parallel.
+///
+/// See `Walk::parallel`.
+pub struct ParallelWalk<'g> {
+    reader: Reader<'g>,
+}
+
+enum Reader<'g> {
+    Sequential(Box<Walk<'g>>),
+    Parallel {
+        traversal: Arc<Traversal>,
+        receiver: sync::mpsc::Receiver<Result<WalkEntry<'static>, GlobError>>,
+    },
+}
+
+impl<'g> Drop for ParallelWalk<'g> {
+    fn drop(&mut self) {
+        if let Reader::Parallel { ref traversal, .. } = self.reader {
+            // Stop reading the directory tree once the walk is dropped.
+            traversal.stop.cancel();
+        }
+    }
+}
+
+impl<'g> Iterator for ParallelWalk<'g> {
+    type Item = Result<WalkEntry<'static>, GlobError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader {
+            Reader::Sequential(ref mut walk) => walk.next(),
+            Reader::Parallel {
+                ref traversal,
+                ref receiver,
+            } => {
+                if traversal.is_cancelled() {
+                    None
+                }
+                else {
+                    receiver.recv().ok()
+                }
+            }
+        }
+    }
+}
+
+/// Shared state of a `ParallelWalk`.
+struct Traversal {
+    regex: Regex,
+    regexes: Vec<Regex>,
+    exclusions: Vec<Exclusion>,
+    behavior: WalkBehavior,
+    prefix: PathBuf,
+    names: Option<capture::Names>,
+    cancellation: Option<Cancellation>,
+    stop: Cancellation,
+    matched: AtomicUsize,
+}
+
+/// Directory read by a `ParallelWalk`.
+struct Directory {
+    path: PathBuf,
+    depth: usize,
+    ignores: Option<Ignores>,
+    // Canonical paths of the directory and its ancestors, which are used to
+    // detect link loops. This is empty if links are not followed.
+    ancestors: Vec<PathBuf>,
+}
+
+type Sender = sync::mpsc::SyncSender<Result<WalkEntry<'static>, GlobError>>;
+
+impl Traversal {
+    fn run(&self, root: PathBuf, ignores: Option<Ignores>, sender: Sender) {
+        // Like `WalkDir`, the root is never yielded and a root that is not a
+        // directory has no entries.
+        if fs::metadata(&root).is_ok_and(|metadata| !metadata.is_dir()) {
+            return;
+        }
+        // Readers wait while the channel is full, so the directory tree is
+        // read by a dedicated thread pool rather than the global pool, which
+        // may be needed to make progress on the matched entries.
+        let pool = match rayon::ThreadPoolBuilder::new().build() {
+            Ok(pool) => pool,
+            Err(error) => {
+                let error = io::Error::other(error.to_string());
+                let _ = sender.send(Err(GlobError::Read(root, error)));
+                return;
+            }
+        };
+        let directory = Directory {
+            ancestors: if self.behavior.follow {
+                fs::canonicalize(&root).into_iter().collect()
+            }
+            else {
+                Vec::new()
+            },
+            path: root,
+            depth: 0,
+            ignores,
+        };
+        if self.behavior.order.is_sorted() {
+            let (unsorted, receiver) = sync::mpsc::sync_channel(PARALLEL_CAPACITY);
+            let mut entries = Vec::new();
+            pool.in_place_scope(|scope| {
+                scope.spawn(|scope| self.read(scope, directory, unsorted));
+                entries.extend(receiver);
+            });
+            let mut times = ModifiedTimes::new(self.behavior.follow);
+            // Errors without paths are ordered first.
+            entries.sort_by(|left, right| match (path_of(left), path_of(right)) {
+                (Some(left), Some(right)) => self.behavior.order.cmp_paths(left, right, &mut times),
+                (left, right) => left.cmp(&right),
+            });
+            let mut matched = 0usize;
+            for entry in entries {
+                if matched >= self.behavior.limit {
+                    break;
+                }
+                if entry.is_ok() {
+                    matched += 1;
+                }
+                if sender.send(entry).is_err() {
+                    break;
+                }
+            }
+        }
+        else {
+            pool.scope(|scope| self.read(scope, directory, sender));
+        }
+    }
+
+    /// Reads the entries of a directory and spawns reads of its
+    /// subdirectories.
+    fn read<'s>(&'s self, scope: &rayon::Scope<'s>, directory: Directory, sender: Sender) {
+        let send = |entry| {
+            if sender.send(entry).is_err() {
+                // The walk has been dropped.
+                self.stop.cancel();
+            }
+        };
+        if self.is_stopped() {
+            return;
+        }
+        let entries = match fs::read_dir(&directory.path) {
+            Ok(entries) => entries,
+            Err(error) => {
+                send(Err(GlobError::Read(directory.path, error)));
+                return;
+            }
+        };
+        for entry in entries {
+            if self.is_stopped() {
+                return;
+            }
+            let entry = match entry.and_then(|entry| {
+                let file_type = entry.file_type()?;
+                Ok((entry.path(), file_type))
+            }) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    send(Err(GlobError::Read(directory.path.clone(), error)));
+                    continue;
+                }
+            };
+            let (path, file_type) = entry;
+            let is_symlink = file_type.is_symlink();
+            let file_type = if is_symlink && self.behavior.follow {
+                match fs::metadata(&path) {
+                    Ok(metadata) => metadata.file_type(),
+                    Err(error) => {
+                        send(Err(GlobError::Read(path, error)));
+                        continue;
+                    }
+                }
+            }
+            else {
+                file_type
+            };
+            let is_dir = file_type.is_dir();
+            let mut ancestors = Vec::new();
+            if self.behavior.follow && is_dir {
+                let canonical = if is_symlink {
+                    fs::canonicalize(&path).ok()
+                }
+                else {
+                    directory
+                        .ancestors
+                        .last()
+                        .zip(path.file_name())
+                        .map(|(parent, name)| parent.join(name))
+                };
+                if let Some(canonical) = canonical {
+                    if directory.ancestors.contains(&canonical) {
+                        send(Err(GlobError::LinkLoop(path)));
+                        continue;
+                    }
+                    ancestors = directory.ancestors.clone();
+                    ancestors.push(canonical);
+                }
+            }
+            if (!self.behavior.hidden && path.file_name().is_some_and(is_hidden_name))
+                || self.is_excluded(&path)
+                || directory
+                    .ignores
+                    .as_ref()
+                    .is_some_and(|ignores| ignores.is_match(&path, is_dir))
+            {
+                // Do not descend into hidden, excluded, nor ignored
+                // directories.
+                continue;
+            }
+            let depth = directory.depth + 1;
+            let entry = ReadEntry {
+                path,
+                file_type,
+                depth,
+                is_symlink,
+                follow: self.behavior.follow,
+            };
+            // Shallow entries are not matched, but their directories are
+            // traversed. See the `walk!` macro.
+            if depth >= self.behavior.min_depth {
+                let path = entry
+                    .path
+                    .strip_prefix(&self.prefix)
+                    .expect("path is not in tree");
+                if path.to_str().is_none() {
+                    send(Err(GlobError::Encoding(entry.path)));
+                    continue;
+                }
+                let bytes = BytePath::from_path(path);
+                if self.is_pruned(&bytes) {
+                    continue;
+                }
+                if let Some(captures) = self.captures(&bytes) {
+                    let entry = WalkEntry {
+                        entry: Entry::Read(entry.clone()),
+                        captures: captures.into_owned(),
+                    };
+                    if !self.behavior.is_filtered(&entry) {
+                        if !self.behavior.order.is_sorted()
+                            && self.matched.fetch_add(1, atomic::Ordering::Relaxed)
+                                >= self.behavior.limit
+                        {
+                            self.stop.cancel();
+                            return;
+                        }
+                        send(Ok(entry));
+                    }
+                }
+            }
+            if is_dir && depth < self.behavior.depth {
+                let directory = Directory {
+                    ignores: directory
+                        .ignores
+                        .as_ref()
+                        .map(|ignores| ignores.descend(&entry.path)),
+                    path: entry.path,
+                    depth,
+                    ancestors,
+                };
+                let sender = sender.clone();
+                scope.spawn(move |scope| self.read(scope, directory, sender));
+            }
+        }
+    }
+
+    /// Determines if a path does not match the component regexes, in which
+    /// case it cannot match and neither can any of its descendants.
+    fn is_pruned(&self, path: &BytePath<'_>) -> bool {
+        path.components()
+            .zip(self.regexes.iter())
+            .any(|(component, regex)| !regex.is_match(component))
+    }
+
+    fn captures<'p>(&self, path: &'p BytePath<'_>) -> Option<Captures<'p>> {
+        self.regex
+            .captures(path.as_ref())
+            .map(|captures| Captures::from(captures).with_names(self.names.clone()))
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclusions
+            .iter()
+            .any(|exclusion| exclusion.is_match(path))
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled() || self.is_cancelled()
+    }
+}
+
+fn path_of<'e>(entry: &'e Result<WalkEntry<'_>, GlobError>) -> Option<&'e Path> {
+    match entry {
+        Ok(ref entry) => Some(entry.path()),
+        Err(ref error) => error.path(),
+    }
+}
+
+/// Compares paths in the order of a `Walk` sorted by `WalkOrder::Natural`.
+///
+/// Components are compared in natural order (see `text::natural_cmp`) and
+/// directories are ordered before their contents.
+fn natural_path_cmp(left: &Path, right: &Path) -> cmp::Ordering {
+    for components in left.iter().zip_longest(right.iter()) {
+        match components {
+            EitherOrBoth::Both(left, right) => {
+                match text::natural_cmp(&left.to_string_lossy(), &right.to_string_lossy()) {
+                    cmp::Ordering::Equal => continue,
+                    ordering => {
+                        return ordering;
+                    }
+                }
+            }
+            EitherOrBoth::Left(_) => {
+                return cmp::Ordering::Greater;
+            }
+            EitherOrBoth::Right(_) => {
+                return cmp::Ordering::Less;
+            }
+        }
+    }
+    cmp::Ordering::Equal
+}
+
+/// Stream over files matching a `Glob` in a directory tree.
+///
+/// See `Glob::read_stream`.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct WalkStream {
+    receiver: mpsc::Receiver<Result<WalkEntry<'static>, GlobError>>,
+}
+
+#[cfg(feature = "async")]
+impl Stream for WalkStream {
+    type Item = Result<WalkEntry<'static>, GlobError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::{Component, Path, PathBuf};
+
+    use crate::cancel::Cancellation;
+    use crate::fixture::TreeFixture;
+    use crate::glob::walk::{Exclusion, MetadataFilter, WalkBehavior, WalkOrder};
+    use crate::glob::Glob;
+
+    #[test]
+    fn match_exclusion_relative_to_root() {
+        let (prefix, glob) = Glob::partitioned("a/**/node_modules").unwrap();
+        let exclusion = Exclusion {
+            root: Path::new("tree").join(prefix),
+            regex: glob.compiled().unwrap().regex.clone(),
+        };
+
+        assert!(exclusion.is_match(Path::new("tree/a/node_modules")));
+        assert!(exclusion.is_match(Path::new("tree/a/b/node_modules")));
+
+        assert!(!exclusion.is_match(Path::new("tree/b/node_modules")));
+        assert!(!exclusion.is_match(Path::new("a/node_modules")));
+    }
+
+    #[test]
+    fn stop_walk_when_cancelled() {
+        let glob = Glob::new("src/**/*.rs").unwrap();
+        let cancellation = Cancellation::new();
+        let mut walk = glob
+            .walk(env!("CARGO_MANIFEST_DIR"), usize::MAX)
+            .cancel_on(&cancellation);
+
+        assert!(walk.next().is_some());
+        cancellation.cancel();
+        assert!(walk.next().is_none());
+    }
+
+    #[test]
+    fn walk_entry_matched_path() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let glob = Glob::new("src/*/*.rs").unwrap();
+        let mut walk = glob.walk(directory, usize::MAX).peekable();
+        assert!(walk.peek().is_some());
+        for entry in walk {
+            let entry = entry.unwrap();
+            let path = entry.path().strip_prefix(directory).unwrap();
+            assert_eq!(entry.matched(), path.to_str().unwrap().as_bytes());
+            assert!(entry.matched().starts_with(b"src/"));
+        }
+    }
+
+    #[test]
+    fn walk_literal_glob() {
+        let glob = Glob::new("src/lib.rs").unwrap();
+        let entries: Vec<_> = glob
+            .walk(env!("CARGO_MANIFEST_DIR"), usize::MAX)
+            .map(Result::unwrap)
+            .collect();
+        assert!(glob.regex.get().is_none());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].matched(), b"src/lib.rs");
+
+        // The file is beneath the maximum depth.
+        assert_eq!(glob.walk(env!("CARGO_MANIFEST_DIR"), 1).count(), 0);
+        assert_eq!(
+            Glob::new("src/missing.rs")
+                .unwrap()
+                .walk(env!("CARGO_MANIFEST_DIR"), usize::MAX)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn walk_partitioned_literal_glob() {
+        let tree = TreeFixture::new()
+            .file("album/a.flac", "")
+            .file(".hidden/b.flac", "")
+            .file("tmp/c.flac", "");
+        let walk = |text: &str, behavior: WalkBehavior| {
+            let (prefix, glob) = Glob::partitioned(text).unwrap();
+            let exclusion = Glob::new("tmp").unwrap();
+            glob.walk(tree.path().join(prefix), behavior)
+                .beneath(tree.path())
+                .exclude([(tree.path(), &exclusion)])
+                .map(|entry| entry.unwrap().matched().to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        // The root of the walk is matched relative to its parent.
+        assert_eq!(
+            walk("album/a.flac", WalkBehavior::default()),
+            [b"a.flac".to_vec()]
+        );
+        assert!(walk("album/missing.flac", WalkBehavior::default()).is_empty());
+        // Hidden and excluded ancestors are detected even though they are not
+        // traversed.
+        assert_eq!(
+            walk(".hidden/b.flac", WalkBehavior::default()),
+            [b"b.flac".to_vec()]
+        );
+        assert!(walk(
+            ".hidden/b.flac",
+            WalkBehavior {
+                hidden: false,
+                ..Default::default()
+            },
+        )
+        .is_empty());
+        assert!(walk("tmp/c.flac", WalkBehavior::default()).is_empty());
+    }
+
+    #[test]
+    fn walk_glob_with_exclusions() {
+        let tree = TreeFixture::new()
+            .file("a.flac", "")
+            .file("album/b.flac", "")
+            .file("album/tmp/c.flac", "")
+            .file("tmp/d.flac", "");
+        let exclusion = Glob::new("**/tmp/**").unwrap();
+        let mut paths: Vec<_> = Glob::new("**/*.flac")
+            .unwrap()
+            .walk(tree.path(), usize::MAX)
+            .exclude([(tree.path(), &exclusion)])
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [b"a.flac".to_vec(), b"album/b.flac".to_vec()]);
+    }
+
+    #[test]
+    fn walk_glob_with_ignore_files() {
+        let tree = TreeFixture::new()
+            .file(".git/info/exclude", "*.log\n")
+            .file(".gitignore", "build/\n!keep.log\n")
+            .file("a.txt", "")
+            .file("b.log", "")
+            .file("keep.log", "")
+            .file("build/c.txt", "");
+        let behavior = WalkBehavior {
+            hidden: false,
+            ignore: true,
+            ..Default::default()
+        };
+        let mut paths: Vec<_> = Glob::new("**")
+            .unwrap()
+            .walk(tree.path(), behavior)
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [b"a.txt".to_vec(), b"keep.log".to_vec()]);
+    }
+
+    #[test]
+    fn walk_glob_with_ignore_files_from_subdirectory() {
+        let tree = TreeFixture::new()
+            .dir(".git")
+            .file(".gitignore", "*.log\n/sub/build/\n")
+            .file("sub/a.txt", "")
+            .file("sub/b.log", "")
+            .file("sub/build/c.txt", "");
+        let behavior = WalkBehavior {
+            ignore: true,
+            ..Default::default()
+        };
+        // Walk a relative path to a subdirectory of the repository, as the
+        // command line interface does by default. The working directory is
+        // shared by all tests, so it is read but never changed.
+        let directory: PathBuf = env::current_dir()
+            .unwrap()
+            .components()
+            .skip(1)
+            .map(|_| Component::ParentDir)
+            .chain(tree.join("sub").components().skip(1))
+            .collect();
+        assert!(directory.is_relative());
+        let paths: Vec<_> = Glob::new("**")
+            .unwrap()
+            .walk(directory, behavior)
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        assert_eq!(paths, [b"a.txt".to_vec()]);
+    }
+
+    #[test]
+    fn walk_glob_with_ignore_files_from_repository_root() {
+        // Ignore files above the root of a repository do not apply when the
+        // repository is walked.
+        let tree = TreeFixture::new()
+            .file(".ignore", "*.txt\n")
+            .dir("repository/.git")
+            .file("repository/a.txt", "");
+        let behavior = WalkBehavior {
+            ignore: true,
+            ..Default::default()
+        };
+        let paths: Vec<_> = Glob::new("*.txt")
+            .unwrap()
+            .walk(tree.join("repository"), behavior)
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        assert_eq!(paths, [b"a.txt".to_vec()]);
+    }
+
+    #[test]
+    fn walk_glob_with_metadata_filter() {
+        let tree = TreeFixture::new()
+            .file("a.txt", "")
+            .file("b.txt", "")
+            .file("c.txt", "c")
+            .file("d.txt", "d");
+        let glob = Glob::new("*.txt").unwrap();
+        // Filtered entries do not count toward the limit.
+        let behavior = WalkBehavior {
+            limit: 1,
+            order: WalkOrder::Name,
+            filter: Some(MetadataFilter::new(|metadata| metadata.len() > 0)),
+            ..Default::default()
+        };
+        let paths: Vec<_> = glob
+            .walk(tree.path(), behavior.clone())
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        assert_eq!(paths, [b"c.txt".to_vec()]);
+        let paths: Vec<_> = glob
+            .walk(tree.path(), behavior)
+            .parallel()
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        assert_eq!(paths, [b"c.txt".to_vec()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_glob_following_links() {
+        use std::os::unix::fs;
+
+        use crate::error::ErrorKind;
+
+        let tree = TreeFixture::new().file("a/b.txt", "");
+        fs::symlink(tree.join("a"), tree.join("link")).unwrap();
+        fs::symlink(tree.path(), tree.join("a/loop")).unwrap();
+        let walk = |follow| -> (Vec<_>, Vec<_>) {
+            let behavior = WalkBehavior {
+                follow,
+                ..Default::default()
+            };
+            let (mut paths, mut errors) = (Vec::new(), Vec::new());
+            for entry in Glob::new("**/*.txt").unwrap().walk(tree.path(), behavior) {
+                match entry {
+                    Ok(entry) => paths.push(entry.matched().to_vec()),
+                    Err(error) => errors.push(error.kind()),
+                }
+            }
+            paths.sort();
+            (paths, errors)
+        };
+
+        assert_eq!(walk(false), (vec![b"a/b.txt".to_vec()], vec![]));
+        // The loop is detected rather than traversed forever.
+        let (paths, errors) = walk(true);
+        assert_eq!(paths, [b"a/b.txt".to_vec(), b"link/b.txt".to_vec()]);
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|kind| *kind == ErrorKind::LinkLoop));
+    }
+
+    #[test]
+    fn walk_glob_in_parallel() {
+        let tree = TreeFixture::new()
+            .file(".gitignore", "*.log\n")
+            .file(".hidden/a.txt", "")
+            .file("a/file2.txt", "")
+            .file("a/file10.txt", "")
+            .file("a/b/c.txt", "")
+            .file("a/b/d.log", "")
+            .file("tmp/e.txt", "")
+            .file("f.txt", "");
+        let exclusion = Glob::new("tmp/**").unwrap();
+        let behavior = |order, limit| WalkBehavior {
+            hidden: false,
+            ignore: true,
+            order,
+            limit,
+            ..Default::default()
+        };
+        let glob = Glob::new("**/*.txt").unwrap();
+        let sequential: Vec<_> = glob
+            .walk(tree.path(), behavior(WalkOrder::Natural, usize::MAX))
+            .exclude([(tree.path(), &exclusion)])
+            .map(|entry| entry.unwrap().matched().to_vec())
+            .collect();
+        assert_eq!(
+            sequential,
+            [
+                b"a/b/c.txt".to_vec(),
+                b"a/file2.txt".to_vec(),
+                b"a/file10.txt".to_vec(),
+                b"f.txt".to_vec(),
+            ],
+        );
+        let parallel = |behavior| -> Vec<_> {
+            glob.walk(tree.path(), behavior)
+                .exclude([(tree.path(), &exclusion)])
+                .parallel()
+                .map(|entry| entry.unwrap().matched().to_vec())
+                .collect()
+        };
+
+        // Sorted parallel walks yield entries in the same order as sequential
+        // walks.
+        assert_eq!(
+            parallel(behavior(WalkOrder::Natural, usize::MAX)),
+            sequential
+        );
+        assert_eq!(parallel(behavior(WalkOrder::Natural, 2)), sequential[..2]);
+
+        let mut paths = parallel(behavior(WalkOrder::Unsorted, usize::MAX));
+        paths.sort();
+        let mut expected = sequential.clone();
+        expected.sort();
+        assert_eq!(paths, expected);
+        assert_eq!(parallel(behavior(WalkOrder::Unsorted, 2)).len(), 2);
+    }
+
+    #[test]
+    fn walk_glob_in_order() {
+        use filetime::FileTime;
+
+        let tree = TreeFixture::new()
+            .file("file10.txt", "")
+            .file("file2.txt", "")
+            .file("a/file1.txt", "");
+        for (n, path) in ["file10.txt", "file2.txt", "a"].iter().enumerate() {
+            let time = FileTime::from_unix_time(1_000_000 * (n as i64 + 1), 0);
+            filetime::set_file_mtime(tree.join(path), time).unwrap();
+        }
+        let glob = Glob::new("**/*.txt").unwrap();
+        let paths = |order, parallel| -> Vec<_> {
+            let walk = glob.walk(
+                tree.path(),
+                WalkBehavior {
+                    order,
+                    ..Default::default()
+                },
+            );
+            let entries: Vec<_> = if parallel {
+                walk.parallel().collect()
+            }
+            else {
+                walk.collect()
+            };
+            entries
+                .into_iter()
+                .map(|entry| String::from_utf8(entry.unwrap().matched().to_vec()).unwrap())
+                .collect()
+        };
+
+        for parallel in [false, true] {
+            assert_eq!(
+                paths(WalkOrder::Name, parallel),
+                ["a/file1.txt", "file10.txt", "file2.txt"],
+            );
+            assert_eq!(
+                paths(WalkOrder::Natural, parallel),
+                ["a/file1.txt", "file2.txt", "file10.txt"],
+            );
+            assert_eq!(
+                paths(WalkOrder::Modified, parallel),
+                ["file10.txt", "file2.txt", "a/file1.txt"],
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_glob_in_parallel_following_links() {
+        use std::os::unix::fs;
+
+        use crate::error::ErrorKind;
+
+        let tree = TreeFixture::new().file("a/b.txt", "");
+        fs::symlink(tree.join("a"), tree.join("link")).unwrap();
+        fs::symlink(tree.path(), tree.join("a/loop")).unwrap();
+        let behavior = WalkBehavior {
+            follow: true,
+            order: WalkOrder::Natural,
+            ..Default::default()
+        };
+        let (mut paths, mut errors) = (Vec::new(), Vec::new());
+        for entry in Glob::new("**/*.txt")
+            .unwrap()
+            .walk(tree.path(), behavior)
+            .parallel()
+        {
+            match entry {
+                Ok(entry) => paths.push(entry.matched().to_vec()),
+                Err(error) => errors.push(error.kind()),
+            }
+        }
+        assert_eq!(paths, [b"a/b.txt".to_vec(), b"link/b.txt".to_vec()]);
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|kind| *kind == ErrorKind::LinkLoop));
+    }
+
+    #[test]
+    fn walk_case_insensitive_glob() {
+        let tree = TreeFixture::new()
+            .file("Photos/a.JPG", "")
+            .file("Photos/b.png", "")
+            .file("c.jpg", "");
+        let paths = |glob: Glob<'_>| -> Vec<_> {
+            let mut paths: Vec<_> = glob
+                .walk(tree.path(), usize::MAX)
+                .filter_map(Result::ok)
+                .map(|entry| entry.matched().to_vec())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        assert!(paths(Glob::new("photos/*.jpg").unwrap()).is_empty());
+        assert_eq!(
+            paths(Glob::new("photos/*.jpg").unwrap().case_insensitive()),
+            [b"Photos/a.JPG".to_vec()],
+        );
+        assert_eq!(
+            paths(Glob::new("**/*.jpg").unwrap().case_insensitive()),
+            [b"Photos/a.JPG".to_vec(), b"c.jpg".to_vec()],
+        );
+        assert_eq!(
+            paths(Glob::new("C.JPG").unwrap().case_insensitive()),
+            [b"c.jpg".to_vec()],
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_non_utf8_path_error() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt as _;
+
+        use crate::error::ErrorKind;
+
+        let tree = TreeFixture::new().file(OsStr::from_bytes(b"\xff.txt"), "");
+
+        let glob = Glob::new("*.txt").unwrap();
+        let errors: Vec<_> = glob
+            .walk(tree.path(), usize::MAX)
+            .map(|entry| entry.unwrap_err().kind())
+            .collect();
+        assert_eq!(errors, [ErrorKind::Encoding]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn read_glob_stream() {
+        use futures_core::Stream;
+        use std::future;
+        use std::pin::Pin;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let paths = runtime.block_on(async {
+            let glob = Glob::new("src/*.rs").unwrap();
+            let mut stream = glob.read_stream(env!("CARGO_MANIFEST_DIR"), usize::MAX);
+            let mut paths = Vec::new();
+            while let Some(entry) =
+                future::poll_fn(|context| Pin::new(&mut stream).poll_next(context)).await
+            {
+                paths.push(entry.unwrap().into_path());
+            }
+            paths
+        });
+
+        assert!(paths.iter().any(|path| path.ends_with("src/lib.rs")));
+        assert!(paths
+            .iter()
+            .all(|path| path.extension() == Some("rs".as_ref())));
+    }
+}
@@ -2,11 +2,23 @@
     html_logo_url = "https://raw.githubusercontent.com/olson-sean-k/nym/master/doc/nym.svg?sanitize=true"
 )]
 
+#[cfg(feature = "fs")]
 pub mod actuator;
+#[cfg(feature = "fs")]
+pub mod cancel;
+#[cfg(feature = "fs")]
 pub mod environment;
+pub mod error;
+#[cfg(feature = "fs")]
+pub mod filter;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixture;
 pub mod glob;
 pub mod manifest;
 pub mod memoize;
 pub mod pattern;
+#[cfg(feature = "fs")]
+pub mod report;
 pub mod text;
+#[cfg(feature = "fs")]
 pub mod transform;
@@ -4,22 +4,64 @@ use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::error::ErrorKind;
+
 type SourceGroup<P> = SmallVec<[P; 1]>;
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
 #[non_exhaustive]
 pub enum ManifestError {
     #[error("detected collision in route destination path: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::manifest::path_collision),
+            help(
+                "more than one source resolves to this destination; use captures or the \
+                 `{{!n}}` property to distinguish them"
+            )
+        )
+    )]
     PathCollision(PathBuf),
 }
 
+impl ManifestError {
+    /// Gets the category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            ManifestError::PathCollision(_) => ErrorKind::Collision,
+        }
+    }
+
+    /// Gets the path at which the error occurred.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            ManifestError::PathCollision(ref path) => Some(path),
+        }
+    }
+}
+
 pub struct Route<M, P>
 where
     P: AsRef<Path>,
 {
     sources: SourceGroup<P>,
     destination: P,
-    phantom: PhantomData<M>,
+    phantom: PhantomData<fn() -> M>,
+}
+
+impl<M, P> Clone for Route<M, P>
+where
+    P: AsRef<Path> + Clone,
+{
+    fn clone(&self) -> Self {
+        Route {
+            sources: self.sources.clone(),
+            destination: self.destination.clone(),
+            phantom: PhantomData,
+        }
+    }
 }
 
 impl<M, P> Route<M, P>
@@ -70,9 +112,12 @@ pub trait Routing: Default {
     fn paths(&self) -> Box<dyn '_ + ExactSizeIterator<Item = (SourceGroup<&'_ Path>, &'_ Path)>>;
 }
 
+/// Routing in which each source has exactly one distinct destination. Routes
+/// are ordered by insertion.
 #[derive(Clone, Debug, Default)]
 pub struct Bijective {
     inner: BiMap<PathBuf, PathBuf>,
+    order: Vec<PathBuf>,
 }
 
 impl Routing for Bijective {
@@ -81,16 +126,18 @@ impl Routing for Bijective {
             Err(ManifestError::PathCollision(destination))
         }
         else {
-            self.inner.insert_no_overwrite(source, destination).unwrap();
+            self.inner
+                .insert_no_overwrite(source.clone(), destination)
+                .unwrap();
+            self.order.push(source);
             Ok(())
         }
     }
 
     fn paths(&self) -> Box<dyn '_ + ExactSizeIterator<Item = (SourceGroup<&'_ Path>, &'_ Path)>> {
-        Box::new(
-            self.inner
-                .iter()
-                .map(|(source, destination)| (smallvec![source.as_ref()], destination.as_ref())),
-        )
+        Box::new(self.order.iter().map(move |source| {
+            let destination = self.inner.get_by_left(source).unwrap();
+            (smallvec![source.as_ref()], destination.as_ref())
+        }))
     }
 }
@@ -1,34 +1,48 @@
+//! Memoization that can be shared between threads.
+//!
+//! `Memoized` and `Memo` use interior mutability, so they can be shared by
+//! reference (such as across parallel traversal or actuation) and are `Send`
+//! and `Sync` when their contents are.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+/// Lazily computed output of a function.
+///
+/// If threads call `get` concurrently before the output is available, then
+/// the function may be called more than once, but only one output is kept.
 #[derive(Debug)]
 pub struct Memoized<O, E, F>
 where
     F: Fn() -> Result<O, E>,
 {
     f: F,
-    output: Option<O>,
+    output: OnceLock<O>,
 }
 
 impl<O, E, F> Memoized<O, E, F>
 where
     F: Fn() -> Result<O, E>,
 {
-    pub fn get(&mut self) -> Result<&O, E> {
-        if let Some(ref output) = self.output {
+    pub fn get(&self) -> Result<&O, E> {
+        if let Some(output) = self.output.get() {
             Ok(output)
         }
         else {
             let output = (self.f)()?;
-            self.output = Some(output);
-            Ok(self.output.as_ref().unwrap())
+            Ok(self.output.get_or_init(|| output))
         }
     }
 
     pub fn peek(&self) -> Option<&O> {
-        self.output.as_ref()
+        self.output.get()
     }
 
     pub fn take(self) -> Result<O, E> {
         let Memoized { f, output } = self;
-        if let Some(output) = output {
+        if let Some(output) = output.into_inner() {
             Ok(output)
         }
         else {
@@ -59,6 +73,277 @@ where
     F: Fn() -> Result<O, E>,
 {
     fn from(f: F) -> Self {
-        Memoized { f, output: None }
+        Memoized {
+            f,
+            output: OnceLock::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Lru<K, V> {
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn touch<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.tick += 1;
+        let tick = self.tick;
+        let (ref value, ref mut used) = *self.entries.get_mut(key)?;
+        let key = self
+            .recency
+            .remove(used)
+            .expect("memo entry has no recency");
+        *used = tick;
+        self.recency.insert(tick, key);
+        Some(value)
+    }
+
+    fn insert(&mut self, capacity: usize, key: K, value: V) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        let previous = self.remove(&key);
+        while self.entries.len() >= capacity {
+            if let Some((_, key)) = self.recency.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (value, tick));
+        previous
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let (value, used) = self.entries.remove(key)?;
+        self.recency.remove(&used);
+        Some(value)
+    }
+}
+
+/// Bounded, keyed cache that evicts the least recently used entries.
+///
+/// Entries are used when they are inserted or read via `get` or
+/// `get_or_insert_with`. When an insertion exceeds the capacity, the least
+/// recently used entry is evicted. Values are cloned when read, so large
+/// values should be shared via `Arc`.
+#[derive(Debug)]
+pub struct Memo<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    capacity: usize,
+    lru: Mutex<Lru<K, V>>,
+}
+
+impl<K, V> Memo<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Constructs a `Memo` that holds at most `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "memo capacity must be nonzero");
+        Memo {
+            capacity,
+            lru: Mutex::new(Lru {
+                tick: 0,
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Gets the value of a key and marks it as the most recently used.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.lock().touch(key).cloned()
+    }
+
+    /// Gets the value of a key without marking it as used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.lock().entries.get(key).map(|(value, _)| value.clone())
+    }
+
+    /// Gets the value of a key or inserts the output of a function.
+    ///
+    /// The `Memo` is not locked while the function is called, so concurrent
+    /// callers may compute the value of the same key more than once. Errors
+    /// are not cached, so the function is called again the next time the key
+    /// is requested.
+    pub fn get_or_insert_with<E, F>(&self, key: K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let value = f(&key)?;
+        self.insert(key, value.clone());
+        Ok(value)
+    }
+
+    /// Inserts a value and returns the previous value of the key, if any.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.lock().insert(self.capacity, key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.lock().remove(key)
+    }
+
+    pub fn clear(&self) {
+        let mut lru = self.lock();
+        lru.entries.clear();
+        lru.recency.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru<K, V>> {
+        self.lru.lock().expect("failed to lock memo")
+    }
+}
+
+impl<K, V> Clone for Memo<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn clone(&self) -> Self {
+        Memo {
+            capacity: self.capacity,
+            lru: Mutex::new(self.lock().clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::memoize::{Memo, Memoized};
+
+    #[test]
+    fn evict_least_recently_inserted() {
+        let memo = Memo::new(2);
+        memo.insert("a", 1);
+        memo.insert("b", 2);
+        memo.insert("c", 3);
+
+        assert_eq!(memo.len(), 2);
+        assert_eq!(memo.peek("a"), None);
+        assert_eq!(memo.peek("b"), Some(2));
+        assert_eq!(memo.peek("c"), Some(3));
+    }
+
+    #[test]
+    fn evict_least_recently_read() {
+        let memo = Memo::new(2);
+        memo.insert("a", 1);
+        memo.insert("b", 2);
+        assert_eq!(memo.get("a"), Some(1));
+        memo.insert("c", 3);
+
+        assert_eq!(memo.peek("a"), Some(1));
+        assert_eq!(memo.peek("b"), None);
+    }
+
+    #[test]
+    fn peek_without_use() {
+        let memo = Memo::new(2);
+        memo.insert("a", 1);
+        memo.insert("b", 2);
+        assert_eq!(memo.peek("a"), Some(1));
+        memo.insert("c", 3);
+
+        assert_eq!(memo.peek("a"), None);
+    }
+
+    #[test]
+    fn replace_without_eviction() {
+        let memo = Memo::new(2);
+        memo.insert("a", 1);
+        memo.insert("b", 2);
+
+        assert_eq!(memo.insert("a", 10), Some(1));
+        assert_eq!(memo.len(), 2);
+        assert_eq!(memo.peek("b"), Some(2));
+        // Replacing `a` uses it, so `b` is evicted next.
+        memo.insert("c", 3);
+        assert_eq!(memo.peek("a"), Some(10));
+        assert_eq!(memo.peek("b"), None);
+    }
+
+    #[test]
+    fn insert_output_but_not_error() {
+        let memo = Memo::new(1);
+
+        assert_eq!(memo.get_or_insert_with("a", |_| Err(())), Err(()));
+        assert!(memo.is_empty());
+        assert_eq!(memo.get_or_insert_with("a", |_| Ok::<_, ()>(1)), Ok(1));
+        assert_eq!(
+            memo.get_or_insert_with("a", |_| -> Result<_, ()> { panic!() }),
+            Ok(1)
+        );
+        assert_eq!(memo.get_or_insert_with("b", |_| Ok::<_, ()>(2)), Ok(2));
+        assert_eq!(memo.peek("a"), None);
+    }
+
+    #[test]
+    fn share_memo_between_threads() {
+        let memo = Memo::new(8);
+        let memoized = Memoized::from(|| Ok::<_, ()>(memo.len()));
+        thread::scope(|scope| {
+            for n in 0..4 {
+                let memo = &memo;
+                let memoized = &memoized;
+                scope.spawn(move || {
+                    memo.insert(n, n * 2);
+                    memoized.get().unwrap();
+                });
+            }
+        });
+
+        assert_eq!(memo.len(), 4);
+        assert_eq!(memo.peek(&3), Some(6));
+        assert!(memoized.peek().is_some());
     }
 }
@@ -1,40 +1,311 @@
-use itertools::Itertools;
-use std::path::{Path, PathBuf};
+#[cfg(feature = "fs")]
+use itertools::{Either, Itertools};
+use regex::bytes::{Regex, RegexBuilder};
+#[cfg(feature = "fs")]
+use std::collections::HashSet;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::path::PathBuf;
 
-use crate::glob::{Glob, GlobError, WalkEntry};
+#[cfg(feature = "fs")]
+use crate::cancel::Cancellation;
+#[cfg(feature = "fs")]
+use crate::glob::{self, WalkBehavior, WalkEntry};
+use crate::glob::{Glob, GlobError, GlobSet};
+use crate::pattern::PatternError;
 
-// NOTE: If and when additional from-patterns are supported (such as raw binary
-//       regular expressions), `FromPattern` will no longer be so trivial.
-//       Moreover, glob types like `Entry` and `Captures` will need to be
-//       abstracted away (and `Selector` can be re-introduced).
+// NOTE: Glob types like `WalkEntry` and `Captures` are shared by all
+//       from-patterns. If and when from-patterns diverge further, these types
+//       may need to be abstracted away (and `Selector` can be re-introduced).
+
+/// Kind of file matched by a from-pattern.
+///
+/// From-patterns match files by default. Links are matched by their own paths
+/// regardless of whether or not they are followed, but when links are followed
+/// other kinds are matched by the files to which links point.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum FileKind {
+    #[default]
+    File,
+    Directory,
+    Link,
+}
+
+impl FileKind {
+    #[cfg(feature = "fs")]
+    fn is_match(&self, entry: &WalkEntry<'_>) -> bool {
+        match self {
+            FileKind::File => entry.file_type().is_file(),
+            FileKind::Directory => entry.file_type().is_dir(),
+            FileKind::Link => entry.path_is_symlink(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Matcher<'t> {
+    Glob(Glob<'t>),
+    Regex(Regex),
+    Set(GlobSet<'t>),
+}
 
 #[derive(Clone, Debug)]
 pub struct FromPattern<'t> {
     prefix: PathBuf,
-    glob: Glob<'t>,
+    matcher: Matcher<'t>,
+    excludes: Vec<(PathBuf, Glob<'t>)>,
+    kind: FileKind,
+    is_case_insensitive: bool,
 }
 
 impl<'t> FromPattern<'t> {
+    /// Constructs a from-pattern from a regular expression.
+    ///
+    /// The regular expression is matched against entire paths relative to the
+    /// directory that is walked. Named groups can be used by name in
+    /// to-patterns (see `ToPattern::with_named_captures`).
+    pub fn from_regex(text: &str) -> Result<Self, PatternError> {
+        Ok(FromPattern {
+            prefix: PathBuf::new(),
+            matcher: Matcher::Regex(
+                Regex::new(&format!("^(?:{})$", text)).map_err(PatternError::Regex)?,
+            ),
+            excludes: Vec::new(),
+            kind: FileKind::File,
+            is_case_insensitive: false,
+        })
+    }
+
+    pub fn into_owned(self) -> FromPattern<'static> {
+        let FromPattern {
+            prefix,
+            matcher,
+            excludes,
+            kind,
+            is_case_insensitive,
+        } = self;
+        FromPattern {
+            prefix,
+            matcher: match matcher {
+                Matcher::Glob(glob) => Matcher::Glob(glob.into_owned()),
+                Matcher::Regex(regex) => Matcher::Regex(regex),
+                Matcher::Set(set) => Matcher::Set(set.into_owned()),
+            },
+            excludes: excludes
+                .into_iter()
+                .map(|(prefix, glob)| (prefix, glob.into_owned()))
+                .collect(),
+            kind,
+            is_case_insensitive,
+        }
+    }
+
+    /// Matches only files of the given kind.
+    ///
+    /// Directories are traversed regardless of the kind of files that are
+    /// matched, so a from-pattern that matches directories may match both a
+    /// directory and its descendants.
+    pub fn with_kind(self, kind: FileKind) -> Self {
+        FromPattern { kind, ..self }
+    }
+
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// Excludes paths matching the given globs.
+    ///
+    /// Like the from-pattern itself, exclusions are applied relative to the
+    /// directory that is walked. Directories that match an exclusion are not
+    /// traversed.
+    pub fn with_excludes<I>(mut self, patterns: I) -> Result<Self, GlobError>
+    where
+        I: IntoIterator<Item = &'t str>,
+    {
+        for pattern in patterns {
+            self.push_exclude(pattern)?;
+        }
+        Ok(self)
+    }
+
+    pub(in crate) fn push_exclude(&mut self, pattern: &'t str) -> Result<(), GlobError> {
+        let (prefix, glob) = Glob::partitioned(pattern)?;
+        let glob = if self.is_case_insensitive {
+            glob.case_insensitive()
+        }
+        else {
+            glob
+        };
+        self.excludes.push((prefix, glob));
+        Ok(())
+    }
+
+    /// Matches paths and exclusions without regard to (ASCII) case.
+    ///
+    /// See `Glob::case_insensitive`.
+    pub fn case_insensitive(self) -> Result<Self, GlobError> {
+        let FromPattern {
+            prefix,
+            matcher,
+            excludes,
+            kind,
+            ..
+        } = self;
+        let matcher = match matcher {
+            Matcher::Glob(glob) => Matcher::Glob(glob.case_insensitive()),
+            Matcher::Regex(regex) => Matcher::Regex(
+                RegexBuilder::new(regex.as_str())
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(GlobError::Compile)?,
+            ),
+            Matcher::Set(set) => Matcher::Set(set.case_insensitive()),
+        };
+        Ok(FromPattern {
+            prefix,
+            matcher,
+            excludes: excludes
+                .into_iter()
+                .map(|(prefix, glob)| (prefix, glob.case_insensitive()))
+                .collect(),
+            kind,
+            is_case_insensitive: true,
+        })
+    }
+
+    /// Gets the index of the capture with the given name.
+    ///
+    /// Captures are named via alternatives in globs, such as `{<name>*}`, and
+    /// via groups in regular expressions, such as `(?P<name>.*)`. See
+    /// `GlobSet::capture_index` for from-patterns constructed from sets.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        match self.matcher {
+            Matcher::Glob(ref glob) => glob.capture_index(name),
+            Matcher::Set(ref set) => set.capture_index(name),
+            Matcher::Regex(ref regex) => regex
+                .capture_names()
+                .position(|capture| capture == Some(name)),
+        }
+    }
+
+    /// Determines if the from-pattern has a capture with the given name.
+    ///
+    /// Unlike `capture_index`, this is `true` for sets if any glob has a
+    /// capture with the given name.
+    pub fn has_capture(&self, name: &str) -> bool {
+        match self.matcher {
+            Matcher::Set(ref set) => set.globs().any(|glob| glob.capture_index(name).is_some()),
+            _ => self.capture_index(name).is_some(),
+        }
+    }
+
+    #[cfg(feature = "fs")]
     pub fn walk<'a>(
         &'a self,
         directory: impl 'a + AsRef<Path>,
-        depth: usize,
-    ) -> impl 'a + Iterator<Item = Result<WalkEntry, GlobError>> {
-        self.glob
-            .walk(directory.as_ref().join(&self.prefix), depth)
-            .filter_map_ok(|entry| {
-                if entry.file_type().is_file() {
+        behavior: impl Into<WalkBehavior>,
+    ) -> impl 'a + Iterator<Item = Result<WalkEntry<'static>, GlobError>> {
+        self.walk_with_cancellation(directory, behavior, None)
+    }
+
+    #[cfg(feature = "fs")]
+    pub(in crate) fn walk_with_cancellation<'a>(
+        &'a self,
+        directory: impl 'a + AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+        cancellation: Option<&Cancellation>,
+    ) -> impl 'a + Iterator<Item = Result<WalkEntry<'static>, GlobError>> {
+        let directory = directory.as_ref().to_path_buf();
+        // The limit applies to matched files rather than all matched entries,
+        // so it cannot be forwarded to the `Walk`.
+        let behavior = behavior.into();
+        let limit = behavior.limit;
+        let behavior = WalkBehavior {
+            limit: usize::MAX,
+            ..behavior
+        };
+        let is_parallel = behavior.parallel;
+        let walks: Vec<_> = match self.matcher {
+            Matcher::Glob(ref glob) => vec![glob
+                .walk(directory.join(&self.prefix), behavior)
+                .beneath(&directory)],
+            Matcher::Regex(ref regex) => vec![glob::walk_regex(regex, &directory, behavior)],
+            Matcher::Set(ref set) => set.walks(&directory, behavior).collect(),
+        };
+        // Files that match more than one glob of a set are only yielded once.
+        let is_union = walks.len() > 1;
+        let mut paths = HashSet::new();
+        let cancellation = cancellation.cloned();
+        walks
+            .into_iter()
+            .flat_map(move |walk| {
+                let walk = walk.exclude(
+                    self.excludes
+                        .iter()
+                        .map(|(prefix, glob)| (directory.join(prefix), glob)),
+                );
+                let walk = match cancellation {
+                    Some(ref cancellation) => walk.cancel_on(cancellation),
+                    None => walk,
+                };
+                if is_parallel {
+                    Either::Right(walk.parallel())
+                }
+                else {
+                    Either::Left(walk)
+                }
+            })
+            .filter_map_ok(move |entry| {
+                if self.kind.is_match(&entry) {
                     Some(entry)
                 }
                 else {
                     None
                 }
             })
+            .filter(move |entry| {
+                !is_union
+                    || entry
+                        .as_ref()
+                        .map_or(true, |entry| paths.insert(entry.path().to_path_buf()))
+            })
+            .scan(0usize, move |matched, entry| {
+                if *matched >= limit {
+                    None
+                }
+                else {
+                    if entry.is_ok() {
+                        *matched += 1;
+                    }
+                    Some(entry)
+                }
+            })
+    }
+}
+
+/// Constructs a from-pattern that matches files matching any glob in a set.
+///
+/// Captures are those of the first glob that matches a file (see `GlobSet`).
+impl<'t> From<GlobSet<'t>> for FromPattern<'t> {
+    fn from(set: GlobSet<'t>) -> Self {
+        FromPattern {
+            prefix: PathBuf::new(),
+            matcher: Matcher::Set(set),
+            excludes: Vec::new(),
+            kind: FileKind::File,
+            is_case_insensitive: false,
+        }
     }
 }
 
 impl<'t> From<(PathBuf, Glob<'t>)> for FromPattern<'t> {
     fn from((prefix, glob): (PathBuf, Glob<'t>)) -> Self {
-        FromPattern { prefix, glob }
+        FromPattern {
+            prefix,
+            matcher: Matcher::Glob(glob),
+            excludes: Vec::new(),
+            kind: FileKind::File,
+            is_case_insensitive: false,
+        }
     }
 }
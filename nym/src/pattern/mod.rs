@@ -1,29 +1,100 @@
 mod from;
 mod to;
 
-use nom::error::ErrorKind;
+use nom::error::ErrorKind as NomErrorKind;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::str::Utf8Error;
 use thiserror::Error;
 
-pub use crate::pattern::from::FromPattern;
-pub use crate::pattern::to::ToPattern;
+use crate::error::ErrorKind;
+
+pub use crate::pattern::from::{FileKind, FromPattern};
+pub use crate::pattern::to::{DigestCache, Resolution, ToPattern};
+
+#[cfg(feature = "fs")]
+pub(in crate) use crate::pattern::to::relative_parent;
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
 #[non_exhaustive]
 pub enum PatternError {
     #[error("capture not found in from-pattern")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::pattern::capture_not_found),
+            help("named captures in to-patterns must be named in the from-pattern")
+        )
+    )]
     CaptureNotFound,
     #[error("failed to parse pattern: {0}")]
-    Parse(nom::Err<(String, ErrorKind)>),
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::pattern::parse),
+            help(
+                "captures and properties must be delimited by braces, such as `{{#1}}` or \
+                 `{{!b3sum}}`"
+            )
+        )
+    )]
+    Parse(nom::Err<(String, NomErrorKind)>),
     #[error("failed to encode capture in to-pattern: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::pattern::encoding)))]
     Encoding(Utf8Error),
     #[error("failed to read property in to-pattern: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::pattern::property)))]
     Property(io::Error),
+    #[error("failed to read property of `{0}` in to-pattern: {1}")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(code(nym::pattern::source_property))
+    )]
+    SourceProperty(PathBuf, io::Error),
+    #[error("failed to parse regular expression: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::pattern::regex)))]
+    Regex(regex::Error),
+}
+
+impl PatternError {
+    /// Gets the category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            PatternError::CaptureNotFound => ErrorKind::Invalid,
+            PatternError::Parse(_) | PatternError::Regex(_) => ErrorKind::Parse,
+            PatternError::Encoding(_) => ErrorKind::Encoding,
+            PatternError::Property(ref error) | PatternError::SourceProperty(_, ref error) => {
+                ErrorKind::from_io(error)
+            }
+        }
+    }
+
+    /// Gets the path of the file for which a property could not be read, if
+    /// any.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            PatternError::SourceProperty(ref path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Gets the byte offset in the given pattern text at which parsing failed.
+    ///
+    /// Returns `None` if the error is not a parsing error.
+    pub fn parse_offset(&self, text: &str) -> Option<usize> {
+        match self {
+            PatternError::Parse(nom::Err::Error((ref remaining, _)))
+            | PatternError::Parse(nom::Err::Failure((ref remaining, _))) => {
+                Some(text.len().saturating_sub(remaining.len()))
+            }
+            _ => None,
+        }
+    }
 }
 
-impl<'i> From<nom::Err<(&'i str, ErrorKind)>> for PatternError {
-    fn from(error: nom::Err<(&'i str, ErrorKind)>) -> Self {
+impl<'i> From<nom::Err<(&'i str, NomErrorKind)>> for PatternError {
+    fn from(error: nom::Err<(&'i str, NomErrorKind)>) -> Self {
         PatternError::Parse(error.to_owned())
     }
 }
@@ -0,0 +1,244 @@
+use os_str_bytes::{OsStrBytes as _, OsStringBytes as _};
+use std::collections::HashMap;
+use std::convert::TryInto as _;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, ErrorKind, Write as _};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Mutex, MutexGuard};
+use std::time::UNIX_EPOCH;
+
+/// First line of a digest cache file, which identifies its format.
+const HEADER: &[u8] = b"nym-digest-cache 1\n";
+
+type Entries = HashMap<(PathBuf, String), Entry>;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Stamp {
+    size: u64,
+    modified: u128,
+}
+
+impl Stamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Stamp {
+            size: metadata.len(),
+            modified: modified.as_nanos(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    stamp: Stamp,
+    digest: Vec<u8>,
+}
+
+/// Cache of file digests that can be persisted across runs.
+///
+/// Digests are keyed by the canonical path of a file and the name of the
+/// digest (such as `b3sum`). A cached digest is only used if the size and
+/// modification time of its file are unchanged; otherwise, it is computed
+/// again and replaced. To-patterns use a cache for properties like `{!b3sum}`
+/// via `ToPattern::with_digest_cache`.
+#[derive(Debug, Default)]
+pub struct DigestCache {
+    entries: Mutex<Entries>,
+}
+
+impl DigestCache {
+    pub fn new() -> Self {
+        DigestCache::default()
+    }
+
+    /// Reads a cache from a file.
+    ///
+    /// If there is no file at the given path, then the cache is empty.
+    /// Entries that cannot be parsed and files in an unknown format are
+    /// ignored, since they can always be computed again.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Ok(DigestCache::new());
+            }
+            Err(error) => {
+                return Err(error);
+            }
+        };
+        let mut entries = HashMap::new();
+        if let Some(records) = data.strip_prefix(HEADER) {
+            entries.extend(
+                records
+                    .split(|byte| *byte == b'\0')
+                    .filter_map(parse_record),
+            );
+        }
+        Ok(DigestCache {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Writes the cache to a file.
+    ///
+    /// Entries for files that no longer exist or that have changed are not
+    /// written. The file is replaced atomically, so concurrent readers never
+    /// observe a partially written cache.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut staged = path.as_os_str().to_owned();
+        staged.push(".tmp");
+        let staged = PathBuf::from(staged);
+        {
+            let mut file = BufWriter::new(File::create(&staged)?);
+            file.write_all(HEADER)?;
+            let entries = self.lock();
+            for ((path, name), entry) in entries.iter() {
+                if Stamp::read(path).as_ref() != Some(&entry.stamp) {
+                    continue;
+                }
+                write!(
+                    file,
+                    "{} {} {} {} ",
+                    name,
+                    entry.stamp.size,
+                    entry.stamp.modified,
+                    to_hex(&entry.digest),
+                )?;
+                file.write_all(&path.as_os_str().to_raw_bytes())?;
+                file.write_all(b"\0")?;
+            }
+            file.flush()?;
+        }
+        fs::rename(&staged, path)
+    }
+
+    /// Gets the digest of a file or computes and inserts it.
+    ///
+    /// `name` distinguishes digests of the same file, such as `b3sum` and
+    /// `md5sum`. If the file cannot be canonicalized or its metadata is
+    /// unavailable, then the digest is computed but not cached.
+    pub fn get_or_insert_with<const N: usize, F>(
+        &self,
+        name: &str,
+        path: impl AsRef<Path>,
+        f: F,
+    ) -> io::Result<[u8; N]>
+    where
+        F: FnOnce() -> io::Result<[u8; N]>,
+    {
+        let path = path.as_ref();
+        let (path, stamp) = match (fs::canonicalize(path), Stamp::read(path)) {
+            (Ok(path), Some(stamp)) => (path, stamp),
+            _ => {
+                return f();
+            }
+        };
+        let key = (path, name.to_owned());
+        if let Some(entry) = self.lock().get(&key) {
+            if entry.stamp == stamp {
+                if let Ok(digest) = entry.digest.as_slice().try_into() {
+                    return Ok(digest);
+                }
+            }
+        }
+        let digest = f()?;
+        self.lock().insert(
+            key,
+            Entry {
+                stamp,
+                digest: digest.to_vec(),
+            },
+        );
+        Ok(digest)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().expect("failed to lock digest cache")
+    }
+}
+
+/// Parses a record of the form `name size modified digest path`.
+fn parse_record(record: &[u8]) -> Option<((PathBuf, String), Entry)> {
+    let mut fields = record.splitn(5, |byte| *byte == b' ');
+    let mut field = || fields.next().and_then(|field| str::from_utf8(field).ok());
+    let name = field()?.to_owned();
+    let size = field()?.parse().ok()?;
+    let modified = field()?.parse().ok()?;
+    let digest = from_hex(field()?)?;
+    let path = fields.next().filter(|path| !path.is_empty())?;
+    let path = PathBuf::from(OsString::from_raw_vec(path.to_vec()).ok()?);
+    Some((
+        (path, name),
+        Entry {
+            stamp: Stamp { size, modified },
+            digest,
+        },
+    ))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::fixture::TreeFixture;
+    use crate::pattern::DigestCache;
+
+    #[test]
+    fn reuse_digest_until_file_changes() {
+        let tree = TreeFixture::new().file("file", "a");
+        let file = tree.join("file");
+        let cache = tree.join("cache");
+
+        let digests = DigestCache::new();
+        assert_eq!(
+            digests
+                .get_or_insert_with("test", &file, || Ok([1u8]))
+                .unwrap(),
+            [1]
+        );
+        digests.write(&cache).unwrap();
+
+        let digests = DigestCache::read(&cache).unwrap();
+        assert_eq!(digests.len(), 1);
+        assert_eq!(
+            digests
+                .get_or_insert_with("test", &file, || Ok([2u8]))
+                .unwrap(),
+            [1]
+        );
+
+        fs::write(&file, "ab").unwrap();
+        assert_eq!(
+            digests
+                .get_or_insert_with("test", &file, || Ok([3u8]))
+                .unwrap(),
+            [3]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+mod cache;
 mod token;
 
 use chrono::offset::Local;
@@ -5,8 +6,11 @@ use chrono::DateTime;
 use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::fs;
+#[cfg(any(feature = "property-b3sum", feature = "property-md5sum"))]
+use std::io;
 use std::path::Path;
 use std::str::{self, FromStr};
+use std::sync::Arc;
 
 use crate::glob::Captures;
 use crate::memoize::Memoized;
@@ -15,45 +19,174 @@ use crate::pattern::to::token::{
     TextFormatter, Token,
 };
 use crate::pattern::PatternError;
-use crate::text;
+use crate::text::TextFormat as _;
+
+pub use crate::pattern::to::cache::DigestCache;
+
+/// Context in which a to-pattern is resolved.
+///
+/// `directory` is the directory tree in which `source` was matched and the
+/// output of the `{!dirs}` property is the parent directory of the source
+/// relative to it. `position` is the one-based position of the source among
+/// all matched sources and is the output of the `{!n}` property.
+#[derive(Clone, Copy, Debug)]
+pub struct Resolution<'r> {
+    pub source: &'r Path,
+    pub directory: &'r Path,
+    pub captures: &'r Captures<'r>,
+    pub position: usize,
+}
 
 #[derive(Clone, Debug)]
 pub struct ToPattern<'t> {
     tokens: Vec<Token<'t>>,
+    digests: Option<Arc<DigestCache>>,
 }
 
 impl<'t> ToPattern<'t> {
     pub fn new(text: &'t str) -> Result<Self, PatternError> {
-        token::parse(text).map(|tokens| ToPattern { tokens })
+        token::parse(text).map(|tokens| ToPattern {
+            tokens,
+            digests: None,
+        })
     }
 
     pub fn into_owned(self) -> ToPattern<'static> {
-        let ToPattern { tokens } = self;
+        let ToPattern { tokens, digests } = self;
         let tokens = tokens.into_iter().map(|token| token.into_owned()).collect();
-        ToPattern { tokens }
+        ToPattern { tokens, digests }
+    }
+
+    /// Reads and writes digest properties like `{!b3sum}` via a cache, so that
+    /// the digests of unchanged files are not computed again.
+    pub fn with_digest_cache(mut self, digests: Arc<DigestCache>) -> Self {
+        self.digests = Some(digests);
+        self
+    }
+
+    /// Resolves named captures to indices.
+    ///
+    /// Named captures that are not resolved this way are looked up by name
+    /// when the to-pattern is resolved, such as `{@name}` for the glob
+    /// `{<name>*}.txt`. Fails if `f` does not recognize a name.
+    pub fn with_named_captures<F>(mut self, f: F) -> Result<Self, PatternError>
+    where
+        F: Fn(&str) -> Option<usize>,
+    {
+        for token in &mut self.tokens {
+            if let Token::Substitution(Substitution {
+                subject:
+                    Subject::Capture(Capture {
+                        ref mut identifier, ..
+                    }),
+                ..
+            }) = *token
+            {
+                if let Identifier::Name(ref name) = *identifier {
+                    let index = f(name).ok_or(PatternError::CaptureNotFound)?;
+                    *identifier = Identifier::Index(index);
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// Gets the indices of the captures used by the to-pattern.
+    ///
+    /// Named captures that have not been resolved to indices (see
+    /// `with_named_captures`) are not included.
+    pub fn capture_indices(&self) -> impl '_ + Iterator<Item = usize> {
+        self.tokens.iter().filter_map(|token| match *token {
+            Token::Substitution(Substitution {
+                subject:
+                    Subject::Capture(Capture {
+                        identifier: Identifier::Index(index),
+                        ..
+                    }),
+                ..
+            }) => Some(index),
+            _ => None,
+        })
+    }
+
+    /// Gets the names of the captures used by the to-pattern that have not
+    /// been resolved to indices (see `with_named_captures`).
+    pub fn capture_names(&self) -> impl '_ + Iterator<Item = &'_ str> {
+        self.tokens.iter().filter_map(|token| match *token {
+            Token::Substitution(Substitution {
+                subject:
+                    Subject::Capture(Capture {
+                        identifier: Identifier::Name(ref name),
+                        ..
+                    }),
+                ..
+            }) => Some(name.as_ref()),
+            _ => None,
+        })
+    }
+
+    #[cfg(any(feature = "property-b3sum", feature = "property-md5sum"))]
+    fn digest<const N: usize, F>(&self, name: &str, source: &Path, f: F) -> io::Result<[u8; N]>
+    where
+        F: FnOnce() -> io::Result<[u8; N]>,
+    {
+        match self.digests {
+            Some(ref digests) => digests.get_or_insert_with(name, source, f),
+            None => f(),
+        }
+    }
+
+    /// Resolves the to-pattern against a source path and its captures.
+    ///
+    /// See `Resolution`.
+    pub fn resolve(&self, resolution: Resolution<'_>) -> Result<String, PatternError> {
+        let mut output = String::new();
+        self.resolve_into(&mut output, resolution)?;
+        Ok(output)
     }
 
-    pub fn resolve(
+    /// Resolves the to-pattern and appends the output to a buffer.
+    ///
+    /// This is the same as `resolve`, but writes directly into `output`, so a
+    /// buffer can be reused when resolving many sources. If an error occurs,
+    /// then `output` may contain partial output.
+    pub fn resolve_into(
         &self,
-        source: impl AsRef<Path>,
-        captures: &Captures<'_>,
-    ) -> Result<String, PatternError> {
+        output: &mut String,
+        resolution: Resolution<'_>,
+    ) -> Result<(), PatternError> {
+        let Resolution {
+            source,
+            directory,
+            captures,
+            position,
+        } = resolution;
         #[cfg(feature = "property-b3sum")]
-        let mut b3sum =
-            Memoized::from(|| fs::read(source.as_ref()).map(|data| blake3::hash(data.as_ref())));
-        let mut ctime = Memoized::from(|| {
-            fs::metadata(source.as_ref())
+        let b3sum = Memoized::from(|| {
+            self.digest("b3sum", source, || {
+                fs::read(source).map(|data| *blake3::hash(data.as_ref()).as_bytes())
+            })
+            .map(blake3::Hash::from)
+        });
+        let ctime = Memoized::from(|| {
+            fs::metadata(source)
                 .and_then(|metadata| metadata.created())
                 .map(DateTime::<Local>::from)
         });
         #[cfg(feature = "property-md5sum")]
-        let mut md5sum = Memoized::from(|| fs::read(source.as_ref()).map(md5::compute));
-        let mut mtime = Memoized::from(|| {
-            fs::metadata(source.as_ref())
+        let md5sum = Memoized::from(|| {
+            self.digest("md5sum", source, || {
+                fs::read(source).map(|data| md5::compute(data).0)
+            })
+            .map(md5::Digest)
+        });
+        let mtime = Memoized::from(|| {
+            fs::metadata(source)
                 .and_then(|metadata| metadata.modified())
                 .map(DateTime::<Local>::from)
         });
-        let mut output = String::new();
+        let size = Memoized::from(|| fs::metadata(source).map(|metadata| metadata.len()));
+        let property_error = |error| PatternError::SourceProperty(source.to_path_buf(), error);
         for token in &self.tokens {
             match *token {
                 Token::Substitution(Substitution {
@@ -67,9 +200,7 @@ impl<'t> ToPattern<'t> {
                         }) => {
                             let capture = match identifier {
                                 Identifier::Index(ref index) => captures.get(*index),
-                                // TODO: Get captures by name when using
-                                //       from-patterns that support it.
-                                Identifier::Name(_) => None,
+                                Identifier::Name(ref name) => captures.name(name),
                             }
                             // Do not include empty captures. Captures that do
                             // not participate in a match and empty match text
@@ -89,32 +220,44 @@ impl<'t> ToPattern<'t> {
                             match *property {
                                 #[cfg(feature = "property-b3sum")]
                                 Property::B3Sum(ref fmt) => {
-                                    b3sum.get().map_err(PatternError::Property)?.fmt(fmt).into()
+                                    b3sum.get().map_err(property_error)?.fmt(fmt).into()
                                 }
                                 Property::CTime(ref fmt) => {
-                                    ctime.get().map_err(PatternError::Property)?.fmt(fmt).into()
+                                    ctime.get().map_err(property_error)?.fmt(fmt).into()
+                                }
+                                Property::Directories => {
+                                    let directories = relative_parent(source, directory);
+                                    directories.to_string_lossy().into_owned().into()
                                 }
                                 #[cfg(feature = "property-md5sum")]
-                                Property::Md5Sum(ref fmt) => md5sum
-                                    .get()
-                                    .map_err(PatternError::Property)?
-                                    .fmt(fmt)
+                                Property::Md5Sum(ref fmt) => {
+                                    md5sum.get().map_err(property_error)?.fmt(fmt).into()
+                                }
+                                #[cfg(feature = "property-mime")]
+                                Property::Mime => mime_guess::from_path(source)
+                                    .first_or_octet_stream()
+                                    .essence_str()
+                                    .to_owned()
                                     .into(),
                                 Property::MTime(ref fmt) => {
-                                    mtime.get().map_err(PatternError::Property)?.fmt(fmt).into()
+                                    mtime.get().map_err(property_error)?.fmt(fmt).into()
+                                }
+                                Property::Position => position.to_string().into(),
+                                Property::Size => {
+                                    size.get().map_err(property_error)?.to_string().into()
                                 }
                             },
                             None,
                         ),
                     };
-                    output.push_str(substitute(text.as_ref(), condition, formatters).as_ref());
+                    substitute_into(output, text.as_ref(), condition, formatters);
                 }
                 Token::Literal(ref text) => {
                     output.push_str(text);
                 }
             }
         }
-        Ok(output)
+        Ok(())
     }
 }
 
@@ -134,52 +277,61 @@ impl FromStr for ToPattern<'static> {
     }
 }
 
-fn substitute<'t>(
-    text: &'t str,
-    condition: Option<&Condition<'t>>,
+/// Gets the parent directory of a source relative to the directory tree in
+/// which it was matched.
+///
+/// The path is empty if the source is not in a subdirectory of the tree.
+pub(in crate) fn relative_parent<'p>(source: &'p Path, directory: &Path) -> &'p Path {
+    source
+        .parent()
+        .and_then(|parent| parent.strip_prefix(directory).ok())
+        .unwrap_or_else(|| Path::new(""))
+}
+
+/// Appends substitution text to a buffer after applying its condition and
+/// formatters.
+fn substitute_into(
+    output: &mut String,
+    text: &str,
+    condition: Option<&Condition<'_>>,
     formatters: &[TextFormatter],
-) -> Cow<'t, str> {
-    let text: Cow<str> = if let Some(condition) = condition {
-        match (text.is_empty(), &condition.non_empty, &condition.empty) {
-            (true, _, Some(ref empty)) => empty.0.clone(),
+) {
+    let parts: [&str; 3] = match condition {
+        Some(condition) => match (text.is_empty(), &condition.non_empty, &condition.empty) {
+            (true, _, Some(ref empty)) => ["", empty.0.as_ref(), ""],
             (false, Some(ref non_empty), _) => match non_empty {
                 NonEmptyCase::Surround {
                     ref prefix,
                     ref postfix,
-                } => format!("{}{}{}", prefix, text, postfix,).into(),
-                NonEmptyCase::Literal(ref literal) => literal.clone(),
+                } => [prefix.as_ref(), text, postfix.as_ref()],
+                NonEmptyCase::Literal(ref literal) => ["", literal.as_ref(), ""],
             },
-            (true, _, None) | (false, None, _) => text.into(),
-        }
-    }
-    else {
-        text.into()
+            (true, _, None) | (false, None, _) => ["", text, ""],
+        },
+        None => ["", text, ""],
     };
     if formatters.is_empty() {
-        text
+        // Write directly into the buffer when there is nothing to format.
+        for part in parts {
+            output.push_str(part);
+        }
     }
     else {
-        let mut text = text.into_owned();
-        for formatter in formatters {
-            text = match *formatter {
-                TextFormatter::Coalesce { ref from, to } => text::coalesce(&text, from, to),
-                TextFormatter::Pad {
-                    shim,
-                    alignment,
-                    width,
-                } => text::pad(&text, shim, alignment, width).into_owned(),
-                TextFormatter::Lower => text.to_lowercase(),
-                TextFormatter::Title => titlecase::titlecase(&text),
-                TextFormatter::Upper => text.to_uppercase(),
-            };
-        }
-        text.into()
+        let text: Cow<str> = match parts {
+            ["", text, ""] => text.into(),
+            parts => parts.concat().into(),
+        };
+        output.push_str(formatters.format(text.as_ref()).as_ref());
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::pattern::ToPattern;
+    use std::path::Path;
+
+    use crate::error::ErrorKind;
+    use crate::glob::{BytePath, Glob};
+    use crate::pattern::{Resolution, ToPattern};
 
     #[test]
     fn parse_to_pattern() {
@@ -227,6 +379,140 @@ mod tests {
         ToPattern::new("{@[capture\\[0\\]]}").unwrap();
     }
 
+    #[test]
+    fn parse_to_pattern_property() {
+        ToPattern::new("{!ctime}").unwrap();
+        ToPattern::new("{!mtime:[%Y]}").unwrap();
+        ToPattern::new("{!n}").unwrap();
+        ToPattern::new("{!dirs}/{#1}").unwrap();
+        ToPattern::new("{!N|>2[0]}").unwrap();
+        ToPattern::new("{!size}").unwrap();
+        #[cfg(feature = "property-b3sum")]
+        ToPattern::new("{!b3sum:[16]}").unwrap();
+    }
+
+    #[test]
+    fn resolve_to_pattern_position() {
+        let pattern = ToPattern::new("chapter-{!n|>2[0]}.md").unwrap();
+        assert_eq!(
+            pattern
+                .resolve(Resolution {
+                    source: Path::new(""),
+                    directory: Path::new(""),
+                    captures: &Default::default(),
+                    position: 7,
+                })
+                .unwrap(),
+            "chapter-07.md",
+        );
+    }
+
+    #[test]
+    fn resolve_to_pattern_into_buffer() {
+        let glob = Glob::new("*_*.txt").unwrap();
+        let path = BytePath::from_path(Path::new("a-b_.txt"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        let pattern = ToPattern::new("{#1|upper}{#2?[-],[]:[none]}{#1?[(],[)]:}.md").unwrap();
+
+        let mut output = String::from("prefix/");
+        pattern
+            .resolve_into(
+                &mut output,
+                Resolution {
+                    source: Path::new(""),
+                    directory: Path::new(""),
+                    captures: &captures,
+                    position: 1,
+                },
+            )
+            .unwrap();
+        assert_eq!(output, "prefix/A-Bnone(a-b).md");
+        assert_eq!(
+            pattern
+                .resolve(Resolution {
+                    source: Path::new(""),
+                    directory: Path::new(""),
+                    captures: &captures,
+                    position: 1,
+                })
+                .unwrap(),
+            "A-Bnone(a-b).md"
+        );
+    }
+
+    #[test]
+    fn resolve_to_pattern_property_error_path() {
+        let pattern = ToPattern::new("{!size}").unwrap();
+        let error = pattern
+            .resolve(Resolution {
+                source: Path::new("does/not/exist"),
+                directory: Path::new(""),
+                captures: &Default::default(),
+                position: 1,
+            })
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert_eq!(error.path(), Some(Path::new("does/not/exist")));
+    }
+
+    #[test]
+    fn resolve_to_pattern_directories() {
+        let pattern = ToPattern::new("backup/{!dirs}/file").unwrap();
+        assert_eq!(
+            pattern
+                .resolve(Resolution {
+                    source: Path::new("tree/a/b/file"),
+                    directory: Path::new("tree"),
+                    captures: &Default::default(),
+                    position: 1,
+                })
+                .unwrap(),
+            "backup/a/b/file",
+        );
+    }
+
+    #[test]
+    fn resolve_to_pattern_named_captures() {
+        let glob = Glob::new("{<name>*}_*.txt").unwrap();
+        let path = BytePath::from_path(Path::new("a_b.txt"));
+        let captures = glob.captures(&path).unwrap().unwrap();
+        let pattern = ToPattern::new("{@[name]}-{#2}.md").unwrap();
+        assert_eq!(
+            pattern
+                .resolve(Resolution {
+                    source: Path::new(""),
+                    directory: Path::new(""),
+                    captures: &captures,
+                    position: 1,
+                })
+                .unwrap(),
+            "a-b.md"
+        );
+        assert_eq!(
+            pattern
+                .with_named_captures(|name| glob.capture_index(name))
+                .unwrap()
+                .resolve(Resolution {
+                    source: Path::new(""),
+                    directory: Path::new(""),
+                    captures: &captures,
+                    position: 1,
+                })
+                .unwrap(),
+            "a-b.md",
+        );
+    }
+
+    #[test]
+    fn index_to_pattern_named_captures() {
+        let pattern = ToPattern::new("{@[name]}.md").unwrap();
+        assert!(pattern
+            .clone()
+            .with_named_captures(|name| if name == "name" { Some(1) } else { None })
+            .is_ok());
+        assert!(pattern.with_named_captures(|_| None).is_err());
+    }
+
     #[test]
     fn reject_to_pattern_with_empty_case_surround() {
         assert!(ToPattern::new("{#1?:[prefix],[postfix]}").is_err());
@@ -1,11 +1,10 @@
 use chrono::{DateTime, TimeZone};
-use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::fmt::Display;
 use std::num::ParseIntError;
 
 use crate::pattern::PatternError;
-use crate::text::Alignment;
+use crate::text::{Alignment, Case, Coalesce, Pad, TextFormat};
 
 #[derive(Clone, Debug)]
 pub enum Identifier<'t> {
@@ -141,18 +140,19 @@ impl<'t> From<Property<'t>> for Subject<'t> {
 
 #[derive(Clone, Debug)]
 pub enum TextFormatter {
-    Coalesce {
-        from: SmallVec<[char; 4]>,
-        to: char,
-    },
-    Pad {
-        shim: char,
-        alignment: Alignment,
-        width: usize,
-    },
-    Lower,
-    Title,
-    Upper,
+    Coalesce(Coalesce),
+    Pad(Pad),
+    Case(Case),
+}
+
+impl TextFormat for TextFormatter {
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match *self {
+            TextFormatter::Coalesce(ref coalesce) => coalesce.format(text),
+            TextFormatter::Pad(ref pad) => pad.format(text),
+            TextFormatter::Case(ref case) => case.format(text),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -180,15 +180,13 @@ pub trait PropertyFormat<M> {
 
 // Numeric formats that include alphabetic characters are always lowercase where
 // applicable.
-#[derive(Clone, Copy, Debug)]
+#[cfg(any(feature = "property-b3sum", feature = "property-md5sum"))]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum DigestFormat {
+    #[default]
     Hexadecimal,
-}
-
-impl Default for DigestFormat {
-    fn default() -> Self {
-        DigestFormat::Hexadecimal
-    }
+    /// Hexadecimal truncated to the given number of leading characters.
+    TruncatedHexadecimal(usize),
 }
 
 #[cfg(feature = "property-b3sum")]
@@ -196,6 +194,9 @@ impl PropertyFormat<DigestFormat> for blake3::Hash {
     fn fmt(&self, fmt: &DigestFormat) -> String {
         match fmt {
             DigestFormat::Hexadecimal => self.to_hex().as_str().to_owned(),
+            DigestFormat::TruncatedHexadecimal(ref width) => {
+                self.to_hex().as_str().chars().take(*width).collect()
+            }
         }
     }
 }
@@ -205,6 +206,9 @@ impl PropertyFormat<DigestFormat> for md5::Digest {
     fn fmt(&self, fmt: &DigestFormat) -> String {
         match fmt {
             DigestFormat::Hexadecimal => format!("{:x}", self),
+            DigestFormat::TruncatedHexadecimal(ref width) => {
+                format!("{:x}", self).chars().take(*width).collect()
+            }
         }
     }
 }
@@ -251,9 +255,14 @@ pub enum Property<'t> {
     #[cfg(feature = "property-b3sum")]
     B3Sum(DigestFormat),
     CTime(DateTimeFormat<'t>),
+    Directories,
     #[cfg(feature = "property-md5sum")]
     Md5Sum(DigestFormat),
+    #[cfg(feature = "property-mime")]
+    Mime,
     MTime(DateTimeFormat<'t>),
+    Position,
+    Size,
 }
 
 impl<'t> Property<'t> {
@@ -262,9 +271,14 @@ impl<'t> Property<'t> {
             #[cfg(feature = "property-b3sum")]
             Property::B3Sum(fmt) => Property::B3Sum(fmt),
             Property::CTime(fmt) => Property::CTime(fmt.into_owned()),
+            Property::Directories => Property::Directories,
             #[cfg(feature = "property-md5sum")]
             Property::Md5Sum(fmt) => Property::Md5Sum(fmt),
+            #[cfg(feature = "property-mime")]
+            Property::Mime => Property::Mime,
             Property::MTime(fmt) => Property::MTime(fmt.into_owned()),
+            Property::Position => Property::Position,
+            Property::Size => Property::Size,
         }
     }
 }
@@ -302,7 +316,7 @@ impl From<String> for Token<'static> {
     }
 }
 
-pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
+pub fn parse(text: &str) -> Result<Vec<Token<'_>>, PatternError> {
     use nom::bytes::complete as bytes;
     use nom::character::complete as character;
     use nom::error::{FromExternalError, ParseError};
@@ -359,14 +373,14 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
         )))(input)
     }
 
-    fn literal<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn literal<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: ParseError<&'i str>,
     {
         combinator::map(escaped(bytes::is_not("{}\\")), Token::from)(input)
     }
 
-    fn identifier<'i, E>(input: &'i str) -> IResult<&'i str, Identifier, E>
+    fn identifier<'i, E>(input: &'i str) -> IResult<&'i str, Identifier<'i>, E>
     where
         E: FromExternalError<&'i str, ParseIntError> + ParseError<&'i str>,
     {
@@ -383,7 +397,7 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
         ))(input)
     }
 
-    fn condition<'i, E>(input: &'i str) -> IResult<&'i str, Condition, E>
+    fn condition<'i, E>(input: &'i str) -> IResult<&'i str, Condition<'i>, E>
     where
         E: ParseError<&'i str>,
     {
@@ -438,9 +452,11 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
                                 ))),
                             )),
                         ),
-                        |(from, to)| TextFormatter::Coalesce {
-                            from: from.chars().collect(),
-                            to,
+                        |(from, to)| {
+                            TextFormatter::Coalesce(Coalesce {
+                                from: from.chars().collect(),
+                                to,
+                            })
                         },
                     ),
                     combinator::map(
@@ -462,15 +478,26 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
                                 )),
                             ))),
                         )),
-                        |(alignment, width, shim)| TextFormatter::Pad {
-                            shim,
-                            alignment,
-                            width,
+                        |(alignment, width, shim)| {
+                            TextFormatter::Pad(Pad {
+                                shim,
+                                alignment,
+                                width,
+                            })
                         },
                     ),
-                    combinator::value(TextFormatter::Lower, bytes::tag_no_case("lower")),
-                    combinator::value(TextFormatter::Title, bytes::tag_no_case("title")),
-                    combinator::value(TextFormatter::Upper, bytes::tag_no_case("upper")),
+                    combinator::value(
+                        TextFormatter::Case(Case::Lower),
+                        bytes::tag_no_case("lower"),
+                    ),
+                    combinator::value(
+                        TextFormatter::Case(Case::Title),
+                        bytes::tag_no_case("title"),
+                    ),
+                    combinator::value(
+                        TextFormatter::Case(Case::Upper),
+                        bytes::tag_no_case("upper"),
+                    ),
                 )),
             ),
         )(input)
@@ -478,7 +505,7 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
 
     /// Parses a capture substition (identifier, condition, and text
     /// formatters).
-    fn capture<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn capture<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: FromExternalError<&'i str, ParseIntError> + ParseError<&'i str>,
     {
@@ -501,7 +528,7 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
     }
 
     /// Parses a property substitution (property format and text formatters).
-    fn property<'i, E>(input: &'i str) -> IResult<&'i str, Token, E>
+    fn property<'i, E>(input: &'i str) -> IResult<&'i str, Token<'i>, E>
     where
         E: FromExternalError<&'i str, ParseIntError> + ParseError<&'i str>,
     {
@@ -517,27 +544,55 @@ pub fn parse(text: &str) -> Result<Vec<Token>, PatternError> {
             )(input)
         }
 
+        /// Parses a digest format with an optional width argument.
+        #[cfg(any(feature = "property-b3sum", feature = "property-md5sum"))]
+        fn fmt_digest<'i, E>(input: &'i str) -> IResult<&'i str, DigestFormat, E>
+        where
+            E: FromExternalError<&'i str, ParseIntError> + ParseError<&'i str>,
+        {
+            combinator::map(
+                combinator::opt(sequence::preceded(
+                    bytes::tag(":"),
+                    bracketed(combinator::map_res(character::digit1, |text: &'i str| {
+                        text.parse::<usize>()
+                    })),
+                )),
+                |width| {
+                    width
+                        .map(DigestFormat::TruncatedHexadecimal)
+                        .unwrap_or_default()
+                },
+            )(input)
+        }
+
         combinator::map(
             braced(sequence::tuple((
                 sequence::preceded(
                     character::char('!'),
                     branch::alt((
                         #[cfg(feature = "property-b3sum")]
-                        combinator::map(bytes::tag_no_case("b3sum"), |_| {
-                            Property::B3Sum(Default::default())
-                        }),
+                        sequence::preceded(
+                            bytes::tag_no_case("b3sum"),
+                            combinator::map(fmt_digest, Property::B3Sum),
+                        ),
                         sequence::preceded(
                             bytes::tag_no_case("ctime"),
                             combinator::map(fmt_from_str, Property::CTime),
                         ),
+                        combinator::map(bytes::tag_no_case("dirs"), |_| Property::Directories),
                         #[cfg(feature = "property-md5sum")]
-                        combinator::map(bytes::tag_no_case("md5sum"), |_| {
-                            Property::Md5Sum(Default::default())
-                        }),
+                        sequence::preceded(
+                            bytes::tag_no_case("md5sum"),
+                            combinator::map(fmt_digest, Property::Md5Sum),
+                        ),
+                        #[cfg(feature = "property-mime")]
+                        combinator::map(bytes::tag_no_case("mime"), |_| Property::Mime),
                         sequence::preceded(
                             bytes::tag_no_case("mtime"),
                             combinator::map(fmt_from_str, Property::MTime),
                         ),
+                        combinator::map(bytes::tag_no_case("n"), |_| Property::Position),
+                        combinator::map(bytes::tag_no_case("size"), |_| Property::Size),
                     )),
                 ),
                 branch::alt((formatters, combinator::success(Vec::new()))),
@@ -0,0 +1,58 @@
+//! Observation of transforms and actuation.
+//!
+//! A `Reporter` receives typed events as a `Transform` reads a manifest and as
+//! an `Actuator` writes routes. All functions do nothing by default, so
+//! implementations need only handle the events that they care about. `()` is
+//! a reporter that ignores all events.
+
+use std::io;
+use std::path::Path;
+
+/// Non-fatal problem encountered while reading a manifest.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum Warning<'w> {
+    /// A symbolic link loop was detected and the link was not followed.
+    LinkLoop(&'w Path),
+    /// A route was skipped, because the policy does not overwrite its
+    /// destination or its destination is identical to its source.
+    Skipped {
+        source: &'w Path,
+        destination: &'w Path,
+    },
+}
+
+/// Receives events from transforms and actuators.
+///
+/// Functions receive `&self`, because actuators may write routes in parallel.
+/// Reporters that accumulate state should use interior mutability.
+pub trait Reporter {
+    /// Called when a source matches a from-pattern.
+    fn matched(&self, _source: &Path) {}
+
+    /// Called when a route has been planned and inserted into a manifest.
+    fn planned(&self, _source: &Path, _destination: &Path) {}
+
+    /// Called when a non-fatal problem is encountered.
+    fn warned(&self, _warning: Warning<'_>) {}
+
+    /// Called with the number of bytes written as file data is written to a
+    /// destination.
+    ///
+    /// This is only called if `is_progress_reported` returns `true`.
+    fn progressed(&self, _destination: &Path, _bytes: u64) {}
+
+    /// Determines if progress is reported via `progressed`.
+    ///
+    /// Reporting progress requires writing file data in chunks, which forgoes
+    /// platform features like copy-on-write, so this returns `false` by
+    /// default.
+    fn is_progress_reported(&self) -> bool {
+        false
+    }
+
+    /// Called when a route has been written or has failed to be written.
+    fn written(&self, _destination: &Path, _result: Result<(), &io::Error>) {}
+}
+
+impl Reporter for () {}
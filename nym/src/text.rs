@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::iter;
 use unicode_width::UnicodeWidthStr;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -8,6 +10,111 @@ pub enum Alignment {
     Center,
 }
 
+/// Formats text.
+///
+/// This is the interface used by the text formatters of to-patterns (such as
+/// `{#1|upper}`). Formatters can be composed via `then`, in which case they
+/// are applied in order.
+pub trait TextFormat {
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str>;
+
+    fn then<T>(self, next: T) -> Then<Self, T>
+    where
+        Self: Sized,
+        T: TextFormat,
+    {
+        Then(self, next)
+    }
+}
+
+impl<T> TextFormat for &'_ T
+where
+    T: TextFormat + ?Sized,
+{
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        T::format(self, text)
+    }
+}
+
+impl<T> TextFormat for [T]
+where
+    T: TextFormat,
+{
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        self.iter().fold(Cow::Borrowed(text), |text, formatter| {
+            match formatter.format(text.as_ref()) {
+                Cow::Borrowed(_) => text,
+                Cow::Owned(formatted) => formatted.into(),
+            }
+        })
+    }
+}
+
+/// Formatter that applies one formatter and then another.
+///
+/// See `TextFormat::then`.
+#[derive(Clone, Copy, Debug)]
+pub struct Then<A, B>(A, B);
+
+impl<A, B> TextFormat for Then<A, B>
+where
+    A: TextFormat,
+    B: TextFormat,
+{
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match self.0.format(text) {
+            Cow::Borrowed(text) => self.1.format(text),
+            Cow::Owned(text) => self.1.format(&text).into_owned().into(),
+        }
+    }
+}
+
+/// Replaces any of a set of characters with a character (see `coalesce`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coalesce {
+    pub from: Vec<char>,
+    pub to: char,
+}
+
+impl TextFormat for Coalesce {
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        coalesce(text, &self.from, self.to).into()
+    }
+}
+
+/// Pads text to a width (see `pad`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pad {
+    pub shim: char,
+    pub alignment: Alignment,
+    pub width: usize,
+}
+
+impl TextFormat for Pad {
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        pad(text, self.shim, self.alignment, self.width)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Case {
+    Lower,
+    /// Title case, in which the first letter of most words is uppercase.
+    Title,
+    Upper,
+}
+
+impl TextFormat for Case {
+    fn format<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        match *self {
+            Case::Lower => text.to_lowercase(),
+            Case::Title => titlecase::titlecase(text),
+            Case::Upper => text.to_uppercase(),
+        }
+        .into()
+    }
+}
+
 pub fn coalesce(text: &str, from: &[char], to: char) -> String {
     text.chars()
         .map(|character| {
@@ -21,7 +128,7 @@ pub fn coalesce(text: &str, from: &[char], to: char) -> String {
         .collect()
 }
 
-pub fn pad(text: &str, shim: char, alignment: Alignment, width: usize) -> Cow<str> {
+pub fn pad(text: &str, shim: char, alignment: Alignment, width: usize) -> Cow<'_, str> {
     let n = UnicodeWidthStr::width(text);
     if n >= width {
         text.into()
@@ -45,9 +152,53 @@ pub fn pad(text: &str, shim: char, alignment: Alignment, width: usize) -> Cow<st
     }
 }
 
+/// Compares text in natural order.
+///
+/// Runs of ASCII digits are compared by their numeric value rather than
+/// character by character, so `file2` is ordered before `file10`. Other text is
+/// compared by character.
+pub fn natural_cmp(left: &str, right: &str) -> Ordering {
+    let mut left = left.chars().peekable();
+    let mut right = right.chars().peekable();
+    loop {
+        let ordering = match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => {
+                return Ordering::Equal;
+            }
+            (None, Some(_)) => {
+                return Ordering::Less;
+            }
+            (Some(_), None) => {
+                return Ordering::Greater;
+            }
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let l: String = iter::from_fn(|| left.next_if(char::is_ascii_digit)).collect();
+                let r: String = iter::from_fn(|| right.next_if(char::is_ascii_digit)).collect();
+                let (lz, rz) = (l.trim_start_matches('0'), r.trim_start_matches('0'));
+                // Numbers with more leading zeros are ordered after equivalent
+                // numbers with fewer leading zeros.
+                lz.len()
+                    .cmp(&rz.len())
+                    .then_with(|| lz.cmp(rz))
+                    .then_with(|| l.len().cmp(&r.len()))
+            }
+            (Some(l), Some(r)) => {
+                left.next();
+                right.next();
+                l.cmp(&r)
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::text::{self, Alignment};
+    use std::cmp::Ordering;
+
+    use crate::text::{self, Alignment, Case, Coalesce, Pad, TextFormat};
 
     #[test]
     fn coalesce_identity() {
@@ -104,4 +255,41 @@ mod tests {
             "too much text"
         );
     }
+
+    #[test]
+    fn natural_cmp_numeric() {
+        assert_eq!(text::natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(text::natural_cmp("file10", "file2"), Ordering::Greater);
+        assert_eq!(text::natural_cmp("file02", "file2"), Ordering::Greater);
+        assert_eq!(text::natural_cmp("file2", "file2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_cmp_mixed() {
+        assert_eq!(text::natural_cmp("a10b2", "a10b10"), Ordering::Less);
+        assert_eq!(text::natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(text::natural_cmp("b1", "a2"), Ordering::Greater);
+    }
+
+    #[test]
+    fn format_then() {
+        let formatter = Coalesce {
+            from: vec!['_'],
+            to: ' ',
+        }
+        .then(Case::Title)
+        .then(Pad {
+            shim: '.',
+            alignment: Alignment::Left,
+            width: 12,
+        });
+        assert_eq!(formatter.format("the_quick").as_ref(), "The Quick...");
+    }
+
+    #[test]
+    fn format_slice() {
+        let formatters: &[&dyn TextFormat] = &[&Case::Upper, &Case::Lower];
+        assert_eq!(formatters.format("Fox").as_ref(), "fox");
+        assert_eq!(<[Case]>::format(&[], "Fox").as_ref(), "Fox");
+    }
 }
@@ -1,38 +1,163 @@
 use faccess::PathExt as _;
+use notify::{DebouncedEvent, RecursiveMode, Watcher as _};
+use rayon::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::{File, Metadata};
+use std::io::{self, BufReader, Read};
+use std::iter;
+#[cfg(feature = "async")]
+use std::panic;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
 use thiserror::Error;
+use tracing::{debug, info};
 
-use crate::environment::Environment;
-use crate::glob::GlobError;
+use crate::cancel::Cancellation;
+use crate::environment::{Environment, Overwrite, Policy};
+use crate::error::ErrorKind;
+use crate::glob::{GlobError, MetadataFilter, WalkBehavior, WalkEntry, WalkOrder};
 use crate::manifest::{Manifest, ManifestError, Routing};
-use crate::pattern::{FromPattern, PatternError, ToPattern};
+use crate::pattern::{self, FromPattern, PatternError, Resolution, ToPattern};
+use crate::report::{Reporter, Warning};
 
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "diagnostics", derive(miette::Diagnostic))]
 #[non_exhaustive]
 pub enum TransformError {
     #[error("failed to apply glob: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(transparent))]
     Glob(GlobError),
     #[error("failed to resolve to-pattern: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(transparent))]
     PatternResolution(PatternError),
     #[error("failed to insert route: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(transparent))]
     RouteInsertion(ManifestError),
     #[error("destination is a directory: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(code(nym::policy::destination_not_a_file))
+    )]
     DestinationNotAFile(PathBuf),
     #[error("destination file already exists: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::policy::destination_already_exists),
+            help("the overwrite policy forbids replacing existing files")
+        )
+    )]
     DestinationAlreadyExists(PathBuf),
     #[error("destination parent directory does not exist: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::policy::destination_orphaned),
+            help("the parents policy forbids creating parent directories")
+        )
+    )]
     DestinationOrphaned(PathBuf),
     #[error("cannot write to destination: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(code(nym::policy::destination_not_writable))
+    )]
     DestinationNotWritable(PathBuf),
+    #[error("destination is not a file path: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(
+            code(nym::policy::destination_invalid),
+            help("destinations must have a file name, such as `a/b.txt`")
+        )
+    )]
+    DestinationInvalid(PathBuf),
     #[error("cannot read from source: `{0}`")]
+    #[cfg_attr(
+        feature = "diagnostics",
+        diagnostic(code(nym::policy::source_not_readable))
+    )]
     SourceNotReadable(PathBuf),
+    #[error("failed to compare source with destination `{0}`: {1}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::policy::comparison)))]
+    Comparison(PathBuf, io::Error),
+    #[error("failed to watch directory: {0}")]
+    #[cfg_attr(feature = "diagnostics", diagnostic(code(nym::transform::watch)))]
+    Watch(notify::Error),
 }
 
+impl TransformError {
+    /// Gets the category of the error.
+    ///
+    /// Errors that are reported by policy are categorized by their cause. For
+    /// example, `DestinationOrphaned` is `ErrorKind::NotFound`.
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            TransformError::Glob(ref error) => error.kind(),
+            TransformError::PatternResolution(ref error) => error.kind(),
+            TransformError::RouteInsertion(ref error) => error.kind(),
+            TransformError::DestinationNotAFile(_) => ErrorKind::NotAFile,
+            TransformError::DestinationAlreadyExists(_) => ErrorKind::AlreadyExists,
+            TransformError::DestinationOrphaned(_) => ErrorKind::NotFound,
+            TransformError::DestinationInvalid(_) => ErrorKind::Invalid,
+            TransformError::DestinationNotWritable(_) | TransformError::SourceNotReadable(_) => {
+                ErrorKind::PermissionDenied
+            }
+            TransformError::Comparison(_, ref error)
+            | TransformError::Watch(notify::Error::Io(ref error)) => ErrorKind::from_io(error),
+            TransformError::Watch(notify::Error::PathNotFound) => ErrorKind::NotFound,
+            TransformError::Watch(_) => ErrorKind::Other,
+        }
+    }
+
+    /// Gets the path at which the error occurred, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            TransformError::Glob(ref error) => error.path(),
+            TransformError::PatternResolution(ref error) => error.path(),
+            TransformError::RouteInsertion(ref error) => error.path(),
+            TransformError::DestinationNotAFile(ref path)
+            | TransformError::DestinationAlreadyExists(ref path)
+            | TransformError::DestinationOrphaned(ref path)
+            | TransformError::DestinationNotWritable(ref path)
+            | TransformError::DestinationInvalid(ref path)
+            | TransformError::SourceNotReadable(ref path)
+            | TransformError::Comparison(ref path, _) => Some(path),
+            TransformError::Watch(_) => None,
+        }
+    }
+}
+
+/// Determines how routes to the same destination are resolved.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Collision {
+    /// Routes to a destination that has already been routed are rejected.
+    #[default]
+    Reject,
+    /// Destinations that have already been routed or that already exist are
+    /// suffixed with an incrementing number, such as `photo-2.jpg`.
+    ///
+    /// Because existing files are numbered, the overwrite policy only applies
+    /// to destinations that are the same as their sources.
+    Number,
+}
+
+/// Number of matched sources for which destinations are resolved in parallel
+/// before they are planned.
+const RESOLVE_CHUNK_LEN: usize = 1024;
+
 #[derive(Clone, Debug)]
 pub struct Transform<'e, 'f, 't> {
     environment: &'e Environment,
     from: FromPattern<'f>,
     to: ToPattern<'t>,
+    sidecars: Vec<String>,
+    is_relative: bool,
+    is_flat: bool,
+    collision: Collision,
+    cancellation: Option<Cancellation>,
 }
 
 impl<'e, 'f, 't> Transform<'e, 'f, 't> {
@@ -45,16 +170,317 @@ impl<'e, 'f, 't> Transform<'e, 'f, 't> {
             environment,
             from,
             to,
+            sidecars: Vec::new(),
+            is_relative: false,
+            is_flat: false,
+            collision: Collision::Reject,
+            cancellation: None,
         }
     }
 
+    /// Preserves the directory structure of sources in destinations.
+    ///
+    /// The file name of each resolved destination is prefixed with the parent
+    /// directory of its source relative to the directory tree (see the
+    /// `{!dirs}` property). For example, if `photos/2020/a.jpg` is resolved
+    /// to `backup/a.jpg`, then its destination is `backup/photos/2020/a.jpg`.
+    pub fn relative(mut self) -> Self {
+        self.is_relative = true;
+        self.is_flat = false;
+        self
+    }
+
+    /// Routes sources into the directory resolved by the to-pattern.
+    ///
+    /// The destination of each source is its file name joined to the resolved
+    /// path, so matches in a directory tree are collapsed into a single
+    /// directory. For example, if `photos/2020/a.jpg` is resolved to `flat`,
+    /// then its destination is `flat/a.jpg`. This is typically used with
+    /// `Collision::Number`.
+    pub fn flatten(mut self) -> Self {
+        self.is_flat = true;
+        self.is_relative = false;
+        self
+    }
+
+    /// Sets the strategy used to resolve routes to the same destination.
+    pub fn collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// Routes companion files along with matched files.
+    ///
+    /// Sidecars are files that share the path of a matched file except for
+    /// their extension, such as subtitles next to a video. When a matched file
+    /// is routed, any of its sidecars with the given extensions are routed to
+    /// its destination with the extension replaced.
+    pub fn with_sidecars<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.sidecars.extend(
+            extensions
+                .into_iter()
+                .map(|extension| extension.as_ref().trim_start_matches('.').to_owned()),
+        );
+        self
+    }
+
+    /// Stops reading when a `Cancellation` is cancelled.
+    ///
+    /// When cancelled, reads return a manifest of the routes that have been
+    /// read so far. Use `Cancellation::is_cancelled` to determine if such a
+    /// manifest is partial.
+    pub fn with_cancellation(mut self, cancellation: &Cancellation) -> Self {
+        self.cancellation = Some(cancellation.clone());
+        self
+    }
+
     pub fn read<M>(
         &self,
         directory: impl AsRef<Path>,
-        depth: usize,
+        behavior: impl Into<WalkBehavior>,
+    ) -> Result<Manifest<M>, TransformError>
+    where
+        M: Routing,
+    {
+        self.read_with_reporter(directory, behavior, &())
+    }
+
+    /// Reads a manifest on a blocking thread.
+    ///
+    /// The manifest is the same as that read by `read`, but the directory tree
+    /// is traversed by the blocking thread pool of the current Tokio runtime,
+    /// so that the calling task is not blocked. This function panics if it is
+    /// called outside of a runtime.
+    #[cfg(feature = "async")]
+    pub async fn read_async<M>(
+        &self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+    ) -> Result<Manifest<M>, TransformError>
+    where
+        M: 'static + Routing + Send,
+    {
+        let environment = self.environment.clone();
+        let from = self.from.clone().into_owned();
+        let to = self.to.clone().into_owned();
+        let sidecars = self.sidecars.clone();
+        let is_relative = self.is_relative;
+        let is_flat = self.is_flat;
+        let collision = self.collision;
+        let cancellation = self.cancellation.clone();
+        let directory = directory.as_ref().to_path_buf();
+        let behavior = behavior.into();
+        let task = tokio::task::spawn_blocking(move || {
+            Transform {
+                environment: &environment,
+                from,
+                to,
+                sidecars,
+                is_relative,
+                is_flat,
+                collision,
+                cancellation,
+            }
+            .read(directory, behavior)
+        });
+        match task.await {
+            Ok(manifest) => manifest,
+            // Blocking tasks are never aborted, so the task must have panicked.
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        }
+    }
+
+    /// Reads a manifest and calls a closure on each non-fatal error.
+    ///
+    /// Non-fatal errors, such as symbolic link loops, do not interrupt reading
+    /// and the affected paths are not included in the manifest.
+    pub fn read_with_warnings<M, F>(
+        &self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+        f: F,
+    ) -> Result<Manifest<M>, TransformError>
+    where
+        M: Routing,
+        F: FnMut(GlobError),
+    {
+        struct WarningReporter<F>(RefCell<F>);
+
+        impl<F> Reporter for WarningReporter<F>
+        where
+            F: FnMut(GlobError),
+        {
+            fn warned(&self, warning: Warning<'_>) {
+                if let Warning::LinkLoop(path) = warning {
+                    (self.0.borrow_mut())(GlobError::LinkLoop(path.to_path_buf()));
+                }
+            }
+        }
+
+        self.read_with_reporter(directory, behavior, &WarningReporter(RefCell::new(f)))
+    }
+
+    /// Reads a manifest and reports matched sources, planned routes, and
+    /// non-fatal errors to a `Reporter`.
+    pub fn read_with_reporter<M, R>(
+        &self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+        reporter: &R,
+    ) -> Result<Manifest<M>, TransformError>
+    where
+        M: Routing,
+        R: Reporter + ?Sized,
+    {
+        self.read_filtered(directory.as_ref(), behavior.into(), reporter, |_| true)
+    }
+
+    /// Watches a directory and reads manifests of newly matched sources.
+    ///
+    /// The returned iterator first yields a manifest of the sources that match
+    /// when it is first polled. It then blocks until the directory changes
+    /// and yields a manifest of any sources that have not yet been routed.
+    /// Sources and destinations of previously yielded manifests are not
+    /// matched again while they exist, so transforms that write into the
+    /// watched directory do not apply to their own outputs. Changes are
+    /// debounced by `delay` and the iterator never yields empty manifests.
+    pub fn watch<M>(
+        &self,
+        directory: impl AsRef<Path>,
+        behavior: impl Into<WalkBehavior>,
+        delay: Duration,
+    ) -> Result<impl '_ + Iterator<Item = Result<Manifest<M>, TransformError>>, TransformError>
+    where
+        M: Routing,
+    {
+        self.watch_filtered(directory.as_ref(), behavior.into(), delay, |_| true)
+    }
+
+    fn watch_filtered<'a, M, P>(
+        &'a self,
+        directory: &Path,
+        behavior: WalkBehavior,
+        delay: Duration,
+        mut filter: P,
+    ) -> Result<impl 'a + Iterator<Item = Result<Manifest<M>, TransformError>>, TransformError>
+    where
+        M: Routing,
+        P: 'a + FnMut(&Path) -> bool,
+    {
+        let directory = directory.to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::watcher(sender, delay).map_err(TransformError::Watch)?;
+        watcher
+            .watch(&directory, RecursiveMode::Recursive)
+            .map_err(TransformError::Watch)?;
+        let mut routed = HashSet::new();
+        let mut changed = true;
+        Ok(iter::from_fn(move || {
+            // The watcher stops sending events when it is dropped.
+            let _ = &watcher;
+            loop {
+                if !changed {
+                    match receiver.recv() {
+                        Ok(DebouncedEvent::Error(error, _)) => {
+                            return Some(Err(TransformError::Watch(error)));
+                        }
+                        Ok(DebouncedEvent::NoticeRemove(_))
+                        | Ok(DebouncedEvent::NoticeWrite(_)) => {
+                            continue;
+                        }
+                        Ok(_) => {}
+                        Err(_) => {
+                            return None;
+                        }
+                    }
+                }
+                changed = false;
+                routed.retain(|path: &PathBuf| path.exists());
+                let manifest = match self.read_filtered::<M, _, _>(
+                    &directory,
+                    behavior.clone(),
+                    &(),
+                    |source| filter(source) && !routed.contains(source),
+                ) {
+                    Ok(manifest) => manifest,
+                    Err(error) => {
+                        return Some(Err(error));
+                    }
+                };
+                if manifest.routes().len() > 0 {
+                    for route in manifest.routes() {
+                        routed.extend(route.sources().map(|source| source.to_path_buf()));
+                        routed.insert(route.destination().to_path_buf());
+                    }
+                    return Some(Ok(manifest));
+                }
+            }
+        }))
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    /// Resolves the destination of a matched source.
+    ///
+    /// The to-pattern is resolved into `buffer`, which is cleared first, so
+    /// that it can be reused for each source.
+    fn resolve(
+        &self,
+        buffer: &mut String,
+        directory: &Path,
+        entry: &WalkEntry<'_>,
+        position: usize,
+    ) -> Result<PathBuf, TransformError> {
+        let source = entry.path();
+        buffer.clear();
+        self.to
+            .resolve_into(
+                buffer,
+                Resolution {
+                    source,
+                    directory,
+                    captures: entry.captures(),
+                    position,
+                },
+            )
+            .map_err(TransformError::PatternResolution)?;
+        let resolved = Path::new(buffer.as_str());
+        let mut destination = directory.to_path_buf();
+        match (self.is_relative, resolved.file_name()) {
+            (true, Some(name)) => {
+                destination.extend(resolved.parent());
+                destination.push(pattern::relative_parent(source, directory));
+                destination.push(name);
+            }
+            _ => {
+                destination.push(resolved);
+                if self.is_flat {
+                    destination.extend(source.file_name());
+                }
+            }
+        }
+        Ok(destination)
+    }
+
+    fn read_filtered<M, R, P>(
+        &self,
+        directory: &Path,
+        behavior: WalkBehavior,
+        reporter: &R,
+        mut filter: P,
     ) -> Result<Manifest<M>, TransformError>
     where
         M: Routing,
+        R: Reporter + ?Sized,
+        P: FnMut(&Path) -> bool,
     {
         #[cfg(windows)]
         fn normalize(path: impl Into<PathBuf>) -> PathBuf {
@@ -69,72 +495,728 @@ impl<'e, 'f, 't> Transform<'e, 'f, 't> {
             path.into()
         }
 
+        let policy = self.environment.policy();
         let mut manifest = Manifest::default();
-        for entry in self.from.walk(directory.as_ref(), depth) {
-            let entry = entry.map_err(TransformError::Glob)?;
-            let source = entry.path();
-            let mut destination = directory.as_ref().to_path_buf();
-            destination.push(
-                self.to
-                    .resolve(&source, entry.captures())
-                    .map_err(TransformError::PatternResolution)?,
-            );
-            self.verify_route_policy(source, &destination)?;
-            manifest
-                .insert(normalize(source), normalize(destination))
-                .map_err(TransformError::RouteInsertion)?;
+        let mut walk =
+            self.from
+                .walk_with_cancellation(directory, behavior, self.cancellation.as_ref());
+        let mut entries = Vec::with_capacity(RESOLVE_CHUNK_LEN);
+        let mut buffer = String::new();
+        let mut position = 0;
+        // Sources that have been routed, which may be matched sources or
+        // sidecars. Sources may not be routed more than once.
+        let mut routed = HashSet::new();
+        // Destinations that have been routed, which are only tracked when
+        // collisions are numbered.
+        let mut planned = HashSet::new();
+        loop {
+            // Entries are read and planned in chunks, so that the directory
+            // tree is never buffered in its entirety.
+            entries.clear();
+            for entry in walk.by_ref() {
+                match entry {
+                    Ok(entry) => {
+                        if filter(entry.path()) {
+                            entries.push(entry);
+                            if entries.len() == RESOLVE_CHUNK_LEN {
+                                break;
+                            }
+                        }
+                    }
+                    Err(GlobError::LinkLoop(ref path)) => {
+                        reporter.warned(Warning::LinkLoop(path));
+                    }
+                    Err(error) => {
+                        return Err(TransformError::Glob(error));
+                    }
+                }
+            }
+            if entries.is_empty() || self.is_cancelled() {
+                break;
+            }
+            // Destinations are resolved in parallel, because to-patterns may
+            // read files (such as `{!b3sum}`). Positions are only known up
+            // front if there are no sidecars, because sidecars of one source
+            // may otherwise be matched as sources themselves, so destinations
+            // are resolved in order in that case. Either way, routes are
+            // planned in order.
+            let destinations: Vec<_> = if self.sidecars.is_empty() {
+                entries
+                    .par_iter()
+                    .enumerate()
+                    .map_init(String::new, |buffer, (index, entry)| {
+                        (!self.is_cancelled())
+                            .then(|| self.resolve(buffer, directory, entry, position + index + 1))
+                    })
+                    .collect()
+            }
+            else {
+                entries.iter().map(|_| None).collect()
+            };
+            for (entry, destination) in entries.iter().zip(destinations) {
+                if self.is_cancelled() {
+                    break;
+                }
+                let source = entry.path();
+                if routed.contains(source) {
+                    continue;
+                }
+                reporter.matched(source);
+                position += 1;
+                let mut destination = match destination {
+                    Some(destination) => destination?,
+                    None => self.resolve(&mut buffer, directory, entry, position)?,
+                };
+                if policy.merge && destination.is_dir() {
+                    destination.extend(source.file_name());
+                }
+                if self.collision == Collision::Number {
+                    destination = number_collision(source, destination, &planned);
+                    planned.insert(destination.clone());
+                }
+                if !verify_route_policy(policy, source, &destination)? {
+                    info!(
+                        source = %source.display(),
+                        destination = %destination.display(),
+                        "skipped route",
+                    );
+                    reporter.warned(Warning::Skipped {
+                        source,
+                        destination: &destination,
+                    });
+                    continue;
+                }
+                debug!(
+                    source = %source.display(),
+                    destination = %destination.display(),
+                    "planned route",
+                );
+                manifest
+                    .insert(normalize(source), normalize(&destination))
+                    .map_err(TransformError::RouteInsertion)?;
+                reporter.planned(source, &destination);
+                routed.insert(source.to_path_buf());
+                for extension in &self.sidecars {
+                    let sidecar = source.with_extension(extension);
+                    if sidecar == source || !sidecar.is_file() || routed.contains(&sidecar) {
+                        continue;
+                    }
+                    let destination = destination.with_extension(extension);
+                    if !verify_route_policy(policy, &sidecar, &destination)? {
+                        reporter.warned(Warning::Skipped {
+                            source: &sidecar,
+                            destination: &destination,
+                        });
+                        continue;
+                    }
+                    debug!(
+                        source = %sidecar.display(),
+                        destination = %destination.display(),
+                        "planned sidecar route",
+                    );
+                    manifest
+                        .insert(normalize(&sidecar), normalize(&destination))
+                        .map_err(TransformError::RouteInsertion)?;
+                    reporter.planned(&sidecar, &destination);
+                    routed.insert(sidecar);
+                    if self.collision == Collision::Number {
+                        planned.insert(destination);
+                    }
+                }
+            }
         }
+        info!(routes = manifest.routes().len(), "read manifest");
         Ok(manifest)
     }
+}
 
-    // TODO: Are write permissions checked properly here? Parent directories are
-    //       not queried directly.
-    fn verify_route_policy(
+/// Suffixes a destination with the lowest number from two for which it has
+/// not been routed and does not exist.
+///
+/// Destinations that are the same as their sources are not considered to
+/// exist. The number is inserted before the extension, such as `photo-2.jpg`.
+fn number_collision(
+    source: &Path,
+    destination: PathBuf,
+    destinations: &HashSet<PathBuf>,
+) -> PathBuf {
+    let is_collision = |destination: &Path| {
+        destinations.contains(destination)
+            || (destination != source && destination.symlink_metadata().is_ok())
+    };
+    if !is_collision(&destination) {
+        return destination;
+    }
+    let stem = destination.file_stem().unwrap_or_default().to_owned();
+    let extension = destination.extension().map(ToOwned::to_owned);
+    (2usize..)
+        .map(|n| {
+            let mut name = stem.clone();
+            name.push(format!("-{}", n));
+            if let Some(ref extension) = extension {
+                name.push(".");
+                name.push(extension);
+            }
+            destination.with_file_name(name)
+        })
+        .find(|destination| !is_collision(destination))
+        .unwrap()
+}
+
+type Filter<'e> = Box<dyn 'e + Fn(&Path) -> bool>;
+
+/// Configures and reads a `Transform`.
+///
+/// Unlike `WalkBehavior`, depths are counted in directories below the
+/// directory that is read, so a depth of zero only matches files within that
+/// directory.
+///
+/// The policy of the transform, such as its overwrite mode, is that of its
+/// `Environment`, which also verifies and writes the manifests that it reads.
+pub struct TransformBuilder<'e, 'f, 't> {
+    transform: Transform<'e, 'f, 't>,
+    behavior: WalkBehavior,
+    filters: Vec<Filter<'e>>,
+}
+
+impl<'e, 'f, 't> TransformBuilder<'e, 'f, 't> {
+    pub(in crate) fn new(
+        environment: &'e Environment,
+        from: FromPattern<'f>,
+        to: ToPattern<'t>,
+    ) -> Self {
+        TransformBuilder {
+            transform: Transform::new(environment, from, to),
+            behavior: WalkBehavior::default(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum depth of directories that are traversed.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.behavior.depth = depth.saturating_add(1);
+        self
+    }
+
+    /// Sets the minimum depth of matched files.
+    ///
+    /// Directories that are shallower than this depth are still traversed.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.behavior.min_depth = depth.saturating_add(1);
+        self
+    }
+
+    /// Excludes paths matching the given globs.
+    ///
+    /// See `FromPattern::with_excludes`.
+    pub fn with_excludes<I>(mut self, patterns: I) -> Result<Self, GlobError>
+    where
+        I: IntoIterator<Item = &'f str>,
+    {
+        for pattern in patterns {
+            self.transform.from.push_exclude(pattern)?;
+        }
+        Ok(self)
+    }
+
+    /// Only routes sources for which a predicate returns `true`.
+    ///
+    /// Sources that are filtered are not counted by the `{!n}` property.
+    pub fn filter<F>(mut self, f: F) -> Self
+    where
+        F: 'e + Fn(&Path) -> bool,
+    {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Only matches files with metadata for which a predicate returns `true`.
+    ///
+    /// Unlike `filter`, the predicate is applied as files are matched, so the
+    /// metadata of each file is read only once and rejected files do not count
+    /// toward the `limit`. See `WalkBehavior::filter`.
+    pub fn filter_metadata<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Fn(&Metadata) -> bool + Send + Sync,
+    {
+        self.behavior.filter = Some(match self.behavior.filter.take() {
+            Some(filter) => {
+                MetadataFilter::new(move |metadata| filter.is_match(metadata) && f(metadata))
+            }
+            None => MetadataFilter::new(f),
+        });
+        self
+    }
+
+    /// Matches files in the natural order of their names.
+    ///
+    /// This is the same as `order(WalkOrder::Natural)`.
+    pub fn sorted(self) -> Self {
+        self.order(WalkOrder::Natural)
+    }
+
+    /// Determines the order in which files are matched.
+    ///
+    /// Positions (like `{!n}`) are assigned in this order. See
+    /// `WalkBehavior::order`.
+    pub fn order(mut self, order: WalkOrder) -> Self {
+        self.behavior.order = order;
+        self
+    }
+
+    /// Determines if hidden files and directories are matched and traversed.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.behavior.hidden = hidden;
+        self
+    }
+
+    /// Determines if ignore files like `.gitignore` are respected.
+    pub fn ignore(mut self, ignore: bool) -> Self {
+        self.behavior.ignore = ignore;
+        self
+    }
+
+    /// Determines if symbolic links are followed.
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.behavior.follow = follow;
+        self
+    }
+
+    /// Determines if directories are read in parallel.
+    ///
+    /// Unless files are matched in a sorted order, the order in which files
+    /// are matched (and so their positions) varies from read to read. See
+    /// `Walk::parallel`.
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.behavior.parallel = parallel;
+        self
+    }
+
+    /// Sets the maximum number of matched files.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.behavior.limit = limit;
+        self
+    }
+
+    /// See `Transform::with_cancellation`.
+    pub fn with_cancellation(mut self, cancellation: &Cancellation) -> Self {
+        self.transform = self.transform.with_cancellation(cancellation);
+        self
+    }
+
+    /// See `Transform::relative`.
+    pub fn relative(mut self) -> Self {
+        self.transform = self.transform.relative();
+        self
+    }
+
+    /// See `Transform::flatten`.
+    pub fn flatten(mut self) -> Self {
+        self.transform = self.transform.flatten();
+        self
+    }
+
+    /// See `Transform::collision`.
+    pub fn collision(mut self, collision: Collision) -> Self {
+        self.transform = self.transform.collision(collision);
+        self
+    }
+
+    /// See `Transform::with_sidecars`.
+    pub fn with_sidecars<I>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.transform = self.transform.with_sidecars(extensions);
+        self
+    }
+
+    pub fn read<M>(&self, directory: impl AsRef<Path>) -> Result<Manifest<M>, TransformError>
+    where
+        M: Routing,
+    {
+        self.read_with_reporter(directory, &())
+    }
+
+    /// See `Transform::read_with_reporter`.
+    pub fn read_with_reporter<M, R>(
         &self,
-        source: impl AsRef<Path>,
-        destination: impl AsRef<Path>,
-    ) -> Result<(), TransformError> {
-        let policy = self.environment.policy();
-        let source = source.as_ref();
-        let destination = destination.as_ref();
-        if !source.readable() {
-            return Err(TransformError::SourceNotReadable(source.into()));
-        }
-        if let Ok(metadata) = destination.metadata() {
-            if policy.overwrite {
-                if metadata.is_dir() {
-                    return Err(TransformError::DestinationNotAFile(destination.into()));
-                }
-                else if !destination.writable() {
-                    return Err(TransformError::DestinationNotWritable(destination.into()));
+        directory: impl AsRef<Path>,
+        reporter: &R,
+    ) -> Result<Manifest<M>, TransformError>
+    where
+        M: Routing,
+        R: Reporter + ?Sized,
+    {
+        self.transform.read_filtered(
+            directory.as_ref(),
+            self.behavior.clone(),
+            reporter,
+            |source| self.is_match(source),
+        )
+    }
+
+    /// See `Transform::watch`.
+    pub fn watch<M>(
+        &self,
+        directory: impl AsRef<Path>,
+        delay: Duration,
+    ) -> Result<impl '_ + Iterator<Item = Result<Manifest<M>, TransformError>>, TransformError>
+    where
+        M: Routing,
+    {
+        self.transform.watch_filtered(
+            directory.as_ref(),
+            self.behavior.clone(),
+            delay,
+            move |source| self.is_match(source),
+        )
+    }
+
+    fn is_match(&self, source: &Path) -> bool {
+        self.filters.iter().all(|filter| filter(source))
+    }
+}
+
+/// Verifies that a route is consistent with a policy.
+///
+/// Returns `false` if the route should be skipped, which occurs when the
+/// destination exists and the policy only overwrites some existing files or
+/// skips identical files.
+// TODO: Are write permissions checked properly here? Parent directories are
+//       not queried directly.
+pub(in crate) fn verify_route_policy(
+    policy: &Policy,
+    source: impl AsRef<Path>,
+    destination: impl AsRef<Path>,
+) -> Result<bool, TransformError> {
+    let source = source.as_ref();
+    let destination = destination.as_ref();
+    if !source.readable() {
+        return Err(TransformError::SourceNotReadable(source.into()));
+    }
+    if let Ok(metadata) = destination.metadata() {
+        if metadata.is_dir() {
+            return Err(TransformError::DestinationNotAFile(destination.into()));
+        }
+        if policy.skip_identical
+            && is_identical(source, destination)
+                .map_err(|error| TransformError::Comparison(destination.into(), error))?
+        {
+            return Ok(false);
+        }
+        let is_overwritten = match policy.overwrite {
+            Overwrite::Always => true,
+            Overwrite::Never => {
+                return Err(TransformError::DestinationAlreadyExists(destination.into()));
+            }
+            Overwrite::Newer => {
+                match (
+                    source.metadata().and_then(|source| source.modified()),
+                    metadata.modified(),
+                ) {
+                    (Ok(source), Ok(destination)) => source > destination,
+                    _ => false,
                 }
             }
-            else {
-                return Err(TransformError::DestinationAlreadyExists(destination.into()));
+            Overwrite::SizeDiffers => source
+                .metadata()
+                .map(|source| source.len() != metadata.len())
+                .unwrap_or(false),
+        };
+        if !is_overwritten {
+            return Ok(false);
+        }
+        if !destination.writable() {
+            return Err(TransformError::DestinationNotWritable(destination.into()));
+        }
+    }
+    else {
+        // Destinations like `` and `/` have no file name and cannot be
+        // written. Relative destinations like `a` have an empty parent, which
+        // is the working directory.
+        let parent = destination
+            .parent()
+            .filter(|_| destination.file_name().is_some())
+            .ok_or_else(|| TransformError::DestinationInvalid(destination.into()))?;
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        }
+        else {
+            parent
+        };
+        if policy.parents {
+            let parent = parent
+                .ancestors()
+                .find(|path| path.exists())
+                .ok_or_else(|| TransformError::DestinationOrphaned(destination.into()))?;
+            if !parent.writable() {
+                return Err(TransformError::DestinationNotWritable(destination.into()));
             }
         }
         else {
-            let parent = destination
-                .parent()
-                .expect("destination path has no parent");
-            if policy.parents {
-                let parent = parent
-                    .ancestors()
-                    .find(|path| path.exists())
-                    .expect("destination path has no existing ancestor");
-                if !parent.writable() {
-                    return Err(TransformError::DestinationNotWritable(destination.into()));
-                }
+            if !parent.exists() {
+                return Err(TransformError::DestinationOrphaned(destination.into()));
             }
-            else {
-                if !parent.exists() {
-                    return Err(TransformError::DestinationOrphaned(destination.into()));
-                }
-                if !parent.writable() {
-                    return Err(TransformError::DestinationNotWritable(destination.into()));
-                }
+            if !parent.writable() {
+                return Err(TransformError::DestinationNotWritable(destination.into()));
             }
         }
-        Ok(())
+    }
+    Ok(true)
+}
+
+/// Determines if two files have the same size and content.
+fn is_identical(source: &Path, destination: &Path) -> io::Result<bool> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    if source.metadata()?.len() != destination.metadata()?.len() {
+        return Ok(false);
+    }
+    let mut source = BufReader::new(File::open(source)?);
+    let mut destination = BufReader::new(File::open(destination)?);
+    let mut source_chunk = vec![0; CHUNK_SIZE];
+    let mut destination_chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let n = source.read(&mut source_chunk)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        destination.read_exact(&mut destination_chunk[..n])?;
+        if source_chunk[..n] != destination_chunk[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom as _;
+
+    use crate::actuator::Copy;
+    use crate::environment::{Environment, Policy};
+    use crate::error::ErrorKind;
+    use crate::fixture::TreeFixture;
+    use crate::glob::{Glob, GlobSet, WalkBehavior, WalkOrder};
+    use crate::manifest::{Bijective, Manifest};
+    use crate::pattern::{FileKind, FromPattern, ToPattern};
+    use crate::transform::Collision;
+
+    #[test]
+    fn read_and_write_copy_transform() {
+        let tree = TreeFixture::new()
+            .file("a/1.txt", "one")
+            .file("a/2.txt", "two")
+            .file("a/3.md", "three")
+            .dir("b");
+        let environment = Environment::new(Policy {
+            parents: true,
+            ..Default::default()
+        });
+        let transform = environment.transform(
+            FromPattern::from(Glob::partitioned("a/*.txt").unwrap()),
+            ToPattern::try_from("b/{#1}.bak").unwrap(),
+        );
+        let manifest = transform
+            .read::<Bijective>(tree.path(), usize::MAX)
+            .unwrap();
+        tree.assert_routes(&manifest, [("a/1.txt", "b/1.bak"), ("a/2.txt", "b/2.bak")]);
+
+        let actuator = environment.actuator();
+        for route in manifest.routes() {
+            actuator.write::<Copy, _>(route).unwrap();
+        }
+        tree.assert_files(["a/1.txt", "a/2.txt", "a/3.md", "b/1.bak", "b/2.bak"]);
+        tree.assert_contents("b/2.bak", "two");
+    }
+
+    #[test]
+    fn verify_manifest_with_invalid_destinations() {
+        let tree = TreeFixture::new().file("a.txt", "a");
+        let environment = Environment::new(Policy {
+            parents: true,
+            ..Default::default()
+        });
+        for destination in ["", "/"] {
+            let mut manifest = Manifest::<Bijective>::default();
+            manifest
+                .insert(tree.path().join("a.txt"), destination)
+                .unwrap();
+            let error = environment.verify(&manifest).unwrap_err();
+            assert!(
+                matches!(error.kind(), ErrorKind::Invalid | ErrorKind::NotAFile),
+                "{}",
+                error,
+            );
+        }
+    }
+
+    #[test]
+    fn read_transform_from_glob_set() {
+        let tree = TreeFixture::new()
+            .file("a.flac", "")
+            .file("album/b.mp3", "")
+            .file("album/tmp/c.mp3", "")
+            .file("d.txt", "");
+        let environment = Environment::new(Policy::default());
+        let transform = environment.transform(
+            FromPattern::from(GlobSet::partitioned([
+                Glob::partitioned("{<name>*}.flac").unwrap(),
+                Glob::partitioned("**/{<name>*}.mp3").unwrap(),
+                Glob::partitioned("**/*.{flac,mp3}").unwrap(),
+            ]))
+            .with_excludes(["**/tmp/**"])
+            .unwrap(),
+            ToPattern::try_from("{@[name]}.out").unwrap(),
+        );
+        let manifest = transform
+            .read::<Bijective>(tree.path(), usize::MAX)
+            .unwrap();
+        tree.assert_routes(&manifest, [("a.flac", "a.out"), ("album/b.mp3", "b.out")]);
+    }
+
+    #[test]
+    fn read_transform_from_regex() {
+        let tree = TreeFixture::new()
+            .file("2024-01-photo.jpg", "")
+            .file("2024-02-photo.png", "")
+            .file("photo.jpg", "");
+        let environment = Environment::new(Policy {
+            parents: true,
+            ..Default::default()
+        });
+        let from = FromPattern::from_regex(r"(?P<year>\d{4})-(\d{2})-(.*)\.(?:jpg|png)").unwrap();
+        let to = ToPattern::try_from("{@[year]}/{#2}/{#3}")
+            .unwrap()
+            .with_named_captures(|name| from.capture_index(name))
+            .unwrap();
+        let manifest = environment
+            .transform(from, to)
+            .read::<Bijective>(tree.path(), usize::MAX)
+            .unwrap();
+        tree.assert_routes(
+            &manifest,
+            [
+                ("2024-01-photo.jpg", "2024/01/photo"),
+                ("2024-02-photo.png", "2024/02/photo"),
+            ],
+        );
+    }
+
+    #[test]
+    fn read_transform_from_directories() {
+        let tree = TreeFixture::new()
+            .file("2023.photos/a.jpg", "")
+            .file("2024.photos/b.jpg", "")
+            .file("c.photos", "");
+        let environment = Environment::new(Policy::default());
+        let transform = environment.transform(
+            FromPattern::from(Glob::partitioned("*.photos").unwrap())
+                .with_kind(FileKind::Directory),
+            ToPattern::try_from("photos-{#1}").unwrap(),
+        );
+        let manifest = transform
+            .read::<Bijective>(tree.path(), usize::MAX)
+            .unwrap();
+        tree.assert_routes(
+            &manifest,
+            [
+                ("2023.photos", "photos-2023"),
+                ("2024.photos", "photos-2024"),
+            ],
+        );
+    }
+
+    #[test]
+    fn resolve_positions_in_order() {
+        let tree = (1..=64).fold(TreeFixture::new(), |tree, n| {
+            tree.file(format!("{}.txt", n), "")
+        });
+        let environment = Environment::new(Policy::default());
+        let transform = environment.transform(
+            FromPattern::from(Glob::partitioned("*.txt").unwrap()),
+            ToPattern::try_from("{!n}-{#1}.out").unwrap(),
+        );
+        let behavior = WalkBehavior {
+            order: WalkOrder::Natural,
+            ..Default::default()
+        };
+        let manifest = transform.read::<Bijective>(tree.path(), behavior).unwrap();
+        tree.assert_routes(
+            &manifest,
+            (1..=64).map(|n| (format!("{}.txt", n), format!("{}-{}.out", n, n))),
+        );
+        let sources: Vec<_> = manifest
+            .routes()
+            .map(|route| route.sources().next().unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            sources,
+            (1..=64)
+                .map(|n| tree.join(format!("{}.txt", n)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn merge_into_existing_directory() {
+        let tree = TreeFixture::new()
+            .file("a/1.txt", "one")
+            .file("b/1.txt/2.txt", "two");
+        let read = |merge| {
+            let environment = Environment::new(Policy {
+                merge,
+                ..Default::default()
+            });
+            environment
+                .transform(
+                    FromPattern::from(Glob::partitioned("a/*.txt").unwrap()),
+                    ToPattern::try_from("b/{#1}.txt").unwrap(),
+                )
+                .read::<Bijective>(tree.path(), usize::MAX)
+        };
+
+        assert!(matches!(
+            read(false).map(|_| ()),
+            Err(error) if error.kind() == ErrorKind::NotAFile,
+        ));
+        let manifest = read(true).unwrap();
+        tree.assert_routes(&manifest, [("a/1.txt", "b/1.txt/1.txt")]);
+    }
+
+    #[test]
+    fn flatten_and_number_collisions() {
+        let tree = TreeFixture::new()
+            .file("a/photo.jpg", "")
+            .file("b/c/photo.jpg", "")
+            .file("b/notes", "")
+            .file("d/notes", "")
+            .file("flat/photo.jpg", "");
+        let environment = Environment::new(Policy {
+            parents: true,
+            ..Default::default()
+        });
+        let transform = environment
+            .transform(
+                FromPattern::from(Glob::partitioned("{a,b,d}/**").unwrap()),
+                ToPattern::try_from("flat").unwrap(),
+            )
+            .flatten()
+            .collision(Collision::Number);
+        let behavior = WalkBehavior {
+            order: WalkOrder::Natural,
+            ..Default::default()
+        };
+        let manifest = transform.read::<Bijective>(tree.path(), behavior).unwrap();
+        tree.assert_routes(
+            &manifest,
+            [
+                ("a/photo.jpg", "flat/photo-2.jpg"),
+                ("b/c/photo.jpg", "flat/photo-3.jpg"),
+                ("b/notes", "flat/notes"),
+                ("d/notes", "flat/notes-2"),
+            ],
+        );
     }
 }